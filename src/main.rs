@@ -1,43 +1,141 @@
-#![no_std]
-#![no_main]
+// Examples are built for PSP. Host build (without 'psp' crate) contains only logic which doesn't touch PSP API and it's used to run its unit tests with 'cargo test'
+#![cfg_attr(target_os = "psp", no_std)]
+#![cfg_attr(target_os = "psp", no_main)]
+// Host build doesn't contain examples which use most of platform independent items
+#![cfg_attr(not(target_os = "psp"), allow(dead_code))]
 
+extern crate alloc;
+
+#[cfg(target_os = "psp")]
 use core::ffi::c_void;
 
+#[cfg(target_os = "psp")]
 use psp::{ self, * };
 #[allow(unused_imports)]
-use examples::{tui_output, user_inputs, sounds, time, file_system, graphics, graphic_deps::shapes};
+use examples::{checksum, file_deps::{json, xml}, graphic_deps::{tilemap, tiled, screenshot}, input_deps::{input_manager, analog, action_map, combo, osk, input_events, input_replay, cursor}, sound_deps::{audio_output, wav}};
+#[cfg(target_os = "psp")]
+#[allow(unused_imports)]
+use examples::{tui_output, user_inputs, sounds, time, game_loop, file_system, graphics, graphic_deps::{shapes, camera, obj_loader, lighting, render_state, render_target, particles, debug_draw, ge_target, texture_canvas, antialias, call_list, cursor_sprite}};
 
+#[cfg(target_os = "psp")]
 use crate::examples::types_def::Dimension;
 
+// Few 'psp' items used by platform independent logic, so 'psp::...' paths work in host build too
+#[cfg(not(target_os = "psp"))]
+extern crate self as psp;
+#[cfg(not(target_os = "psp"))]
+#[path = "./modules/host.rs"]
+mod host;
+#[cfg(not(target_os = "psp"))]
+use host::{sys, math};
+
 #[path = "./modules"]
 mod examples {
+    #[cfg(target_os = "psp")]
     pub mod tui_output;
+    #[cfg(target_os = "psp")]
     pub mod user_inputs;
+    #[cfg(target_os = "psp")]
     pub mod sounds;
+    #[cfg(target_os = "psp")]
     pub mod time;
+    #[cfg(target_os = "psp")]
+    pub mod game_loop;
+    #[cfg(target_os = "psp")]
     pub mod file_system;
+    pub mod checksum;
+    #[cfg(target_os = "psp")]
     pub mod graphics;
+    #[cfg(target_os = "psp")]
     #[path ="types.rs"]
     pub mod types_def;
     pub mod graphic_deps {
+        #[cfg(target_os = "psp")]
         pub mod shapes;
+        #[cfg(target_os = "psp")]
+        pub mod camera;
+        #[cfg(target_os = "psp")]
+        pub mod obj_loader;
+        #[cfg(target_os = "psp")]
+        pub mod lighting;
+        #[cfg(target_os = "psp")]
+        pub mod render_state;
+        #[cfg(target_os = "psp")]
+        pub mod render_target;
+        #[cfg(target_os = "psp")]
+        pub mod particles;
+        pub mod tilemap;
+        pub mod tiled;
+        pub mod screenshot;
+        #[cfg(target_os = "psp")]
+        pub mod debug_draw;
+        #[cfg(target_os = "psp")]
+        pub mod ge_target;
+        #[cfg(target_os = "psp")]
+        pub mod texture_canvas;
+        #[cfg(target_os = "psp")]
+        pub mod antialias;
+        #[cfg(target_os = "psp")]
+        pub mod call_list;
+        #[cfg(target_os = "psp")]
+        pub mod cursor_sprite;
+    }
+    pub mod file_deps {
+        pub mod json;
+        pub mod xml;
+    }
+    pub mod input_deps {
+        pub mod input_manager;
+        pub mod analog;
+        pub mod action_map;
+        pub mod input_replay;
+        pub mod combo;
+        pub mod osk;
+        pub mod input_events;
+        pub mod cursor;
+    }
+    pub mod sound_deps {
+        pub mod audio_output;
+        pub mod wav;
     }
 }
 
+#[cfg(target_os = "psp")]
 module!("PSP programming folder", 1, 0);
 
+#[cfg(target_os = "psp")]
 fn psp_main() {
     enable_home_button();
     tui_output::output();
 
     unsafe {
         // let _ = user_inputs::inputs_listener();
+        // let _ = user_inputs::record_and_replay();
         // let _ = sounds::play_sound();
+        // let _ = sounds::play_wav();
         // let _ = time::base();
         // let _ = file_system::file_sys();
         // let _ = file_system::dir_sys();
         // let _ = graphics::background();
         // let _ = graphics::draw_shapes();
+        // let _ = graphics::draw_shapes_aa();
         let _ = graphics::draw_shapes_native();
+        // let _ = graphics::draw_shapes_ge();
+        // let _ = graphics::draw_texture_canvas();
+        // let _ = graphics::draw_text_input();
+        // let _ = graphics::draw_pointer_ui();
+        // let _ = graphics::draw_scene_3d();
+        // let _ = graphics::draw_obj_model();
+        // let _ = graphics::draw_lit_scene();
+        // let _ = graphics::draw_render_to_texture();
+        // let _ = graphics::draw_particles();
+        // let _ = graphics::draw_tilemap();
+        // let _ = graphics::draw_tiled_map();
+        // let _ = graphics::draw_game_loop();
     }
+}
+
+#[cfg(not(target_os = "psp"))]
+fn main() {
+    println!("Examples run only on PSP, build them with 'cargo psp'. Host build is used to run unit tests with 'cargo test'");
 }
\ No newline at end of file
@@ -0,0 +1,36 @@
+/// CRC-32 (the one used by PNG chunks and zip), used by binary files of this project to detect damaged content
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continue CRC-32 of data split into parts: 'crc32_update(crc32(a), b)' is the same as CRC-32 of 'a' followed by 'b' (start with 0)
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            // Reversed polynomial 0x04c11db7
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn update_in_parts() {
+        assert_eq!(crc32_update(crc32(b"1234"), b"56789"), crc32(b"123456789"));
+        assert_eq!(crc32_update(crc32_update(0, b""), b"123456789"), crc32(b"123456789"));
+    }
+}
@@ -0,0 +1,386 @@
+use alloc::{vec::Vec, string::String};
+
+/// Value readed from JSON document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Object members in the same order as in document
+    Object(Vec<(String, Value)>),
+}
+
+/// Document isn't valid JSON. 'position' is byte offset where problem was found
+#[derive(Debug)]
+pub struct JsonError {
+    pub position: usize,
+}
+
+impl Value {
+    /// Member of object with specified key (None for missing key or when value isn't an object)
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None
+        }
+    }
+
+    /// Write value as compact JSON text
+    pub fn write(&self, out: &mut String) {
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Value::Number(number) => {
+                // Whole numbers are written without fraction part
+                if *number == (*number as i64) as f64 {
+                    out.push_str(&alloc::format!("{}", *number as i64));
+                } else {
+                    out.push_str(&alloc::format!("{}", number));
+                }
+            },
+            Value::String(text) => write_string(text, out),
+            Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.write(out);
+                }
+                out.push(']');
+            },
+            Value::Object(members) => {
+                out.push('{');
+                for (i, (name, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(name, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            },
+        }
+    }
+}
+
+/// Write string with escaped special characters
+fn write_string(text: &str, out: &mut String) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&alloc::format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How deep arrays and objects can be nested. Parser is recursive, so deeper documents could overflow small stack of PSP thread
+const MAX_DEPTH: usize = 64;
+
+/// Parse whole JSON document
+pub fn parse(src: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser { bytes: src.as_bytes(), position: 0, depth: 0 };
+
+    let value = parser.value()?;
+    parser.skip_whitespace();
+
+    // Nothing except whitespaces can be placed after the main value
+    if parser.position != parser.bytes.len() {
+        return Err(parser.error());
+    }
+
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// Amount of arrays and objects which contain currently parsed value
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self) -> JsonError {
+        JsonError { position: self.position }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    /// Consume expected text (e.g. keyword)
+    fn expect(&mut self, text: &str) -> Result<(), JsonError> {
+        if self.bytes[self.position..].starts_with(text.as_bytes()) {
+            self.position += text.len();
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error())
+        }
+    }
+
+    /// Parse array or object one level deeper. Fails when nesting limit is reached
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value, JsonError>) -> Result<Value, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        let mut members = Vec::new();
+        self.position += 1; // '{'
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Ok(Value::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error());
+            }
+            let name = self.string()?;
+
+            self.skip_whitespace();
+            self.expect(":")?;
+            members.push((name, self.value()?));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Value::Object(members));
+                },
+                _ => return Err(self.error())
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        let mut items = Vec::new();
+        self.position += 1; // '['
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Ok(Value::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Value::Array(items));
+                },
+                _ => return Err(self.error())
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.position;
+
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+
+        core::str::from_utf8(&self.bytes[start..self.position])
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .map(Value::Number)
+            .ok_or(JsonError { position: start })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        let mut text = String::new();
+        self.position += 1; // '"'
+
+        loop {
+            let start = self.position;
+
+            // Copy fragment without escapes at once
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.position += 1;
+            }
+            // Fragment is valid UTF-8 because it was cut from '&str' on ASCII characters boundaries
+            text.push_str(core::str::from_utf8(&self.bytes[start..self.position]).map_err(|_| self.error())?);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.position += 1;
+                    return Ok(text);
+                },
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escaped = self.peek().ok_or(self.error())?;
+                    self.position += 1;
+
+                    match escaped {
+                        b'"' => text.push('"'),
+                        b'\\' => text.push('\\'),
+                        b'/' => text.push('/'),
+                        b'b' => text.push('\u{8}'),
+                        b'f' => text.push('\u{c}'),
+                        b'n' => text.push('\n'),
+                        b'r' => text.push('\r'),
+                        b't' => text.push('\t'),
+                        b'u' => text.push(self.unicode_escape()?),
+                        _ => return Err(self.error())
+                    }
+                },
+                _ => return Err(self.error())
+            }
+        }
+    }
+
+    /// Read character written as '\uXXXX' (or pair of them for characters outside of basic plane). '\u' is already consumed
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let first = self.hex4()?;
+
+        let code = if (0xd800..0xdc00).contains(&first) {
+            // High surrogate must be followed by low surrogate
+            self.expect("\\u")?;
+            let second = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err(self.error());
+            }
+
+            0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00)
+        } else {
+            first
+        };
+
+        char::from_u32(code).ok_or(self.error())
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.bytes.get(self.position..self.position + 4).ok_or(self.error())?;
+        let value = core::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or(self.error())?;
+        self.position += 4;
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_document() {
+        let value = parse(r#" { "name": "hero\n\u00e9\ud83d\udd11", "speed": -1.5e1, "tags": [true, null, 3], "empty": {} } "#).unwrap();
+
+        assert_eq!(value.get("name").and_then(Value::as_str), Some("hero\né🔑"));
+        assert_eq!(value.get("speed").and_then(Value::as_f64), Some(-15.0));
+        assert_eq!(value.get("tags").and_then(Value::as_array), Some(&[Value::Bool(true), Value::Null, Value::Number(3.0)][..]));
+        assert_eq!(value.get("empty"), Some(&Value::Object(vec![])));
+    }
+
+    #[test]
+    fn write_and_parse_again() {
+        let value = Value::Object(vec![
+            (String::from("text"), Value::String(String::from("quote \" and \u{1}"))),
+            (String::from("list"), Value::Array(vec![Value::Number(2.0), Value::Number(0.25)])),
+        ]);
+
+        let mut text = String::new();
+        value.write(&mut text);
+        assert_eq!(text, r#"{"text":"quote \" and \u0001","list":[2,0.25]}"#);
+        assert_eq!(parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert_eq!(parse("[1, 2").unwrap_err().position, 5);
+        assert_eq!(parse("{\"a\" 1}").unwrap_err().position, 5);
+        assert!(parse("[] []").is_err());
+        assert!(parse("\"\\ud83d\"").is_err());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err());
+        // Deep document fails quickly instead of overflowing stack
+        assert!(parse(&"[".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use alloc::{vec::Vec, string::String};
+
+/// XML element with its attributes and child elements. Enough to read simple data files (no namespaces and DTD handling)
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    pub name: String,
+    /// (name, value) pairs in the same order as in document, entities in values are already replaced
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// All text placed directly inside element (text between child elements is joined)
+    pub text: String,
+}
+
+/// Document isn't valid XML. 'position' is byte offset where problem was found
+#[derive(Debug)]
+pub struct XmlError {
+    pub position: usize,
+}
+
+impl Element {
+    /// Value of attribute with specified name
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// First child element with specified name
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// All child elements with specified name
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+/// How deep elements can be nested. Parser is recursive, so deeper documents could overflow small stack of PSP thread
+const MAX_DEPTH: usize = 64;
+
+/// Parse document and return its root element
+pub fn parse(src: &str) -> Result<Element, XmlError> {
+    let mut parser = Parser { src, position: 0, depth: 0 };
+
+    parser.skip_misc()?;
+    let root = parser.element()?;
+    parser.skip_misc()?;
+
+    // Only one root element is allowed
+    if parser.position != src.len() {
+        return Err(parser.error());
+    }
+
+    Ok(root)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    position: usize,
+    /// Amount of elements which contain currently parsed one
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self) -> XmlError {
+        XmlError { position: self.position }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Move after the first occurrence of 'end'
+    fn skip_past(&mut self, end: &str) -> Result<(), XmlError> {
+        match self.rest().find(end) {
+            Some(offset) => {
+                self.position += offset + end.len();
+                Ok(())
+            },
+            None => Err(self.error())
+        }
+    }
+
+    /// Skip whitespaces, comments, declaration ('<?xml ... ?>') and doctype
+    fn skip_misc(&mut self) -> Result<(), XmlError> {
+        loop {
+            self.skip_whitespace();
+
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, XmlError> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/' || c == '>')
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error());
+        }
+
+        self.position += length;
+        Ok(String::from(&rest[..length]))
+    }
+
+    fn element(&mut self) -> Result<Element, XmlError> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error());
+        }
+        self.position += 1;
+
+        let mut element = Element { name: self.name()?, ..Default::default() };
+
+        // Attributes
+        loop {
+            self.skip_whitespace();
+
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error());
+            }
+            self.position += 1;
+            self.skip_whitespace();
+
+            let quote = self.rest().chars().next().filter(|c| *c == '"' || *c == '\'').ok_or(self.error())?;
+            self.position += 1;
+            let length = self.rest().find(quote).ok_or(self.error())?;
+            let value = decode_entities(&self.rest()[..length]).ok_or(self.error())?;
+            self.position += length + 1;
+
+            element.attributes.push((name, value));
+        }
+
+        // Content
+        loop {
+            let rest = self.rest();
+            let text_length = rest.find('<').ok_or(self.error())?;
+            element.text.push_str(&decode_entities(&rest[..text_length]).ok_or(self.error())?);
+            self.position += text_length;
+
+            if self.rest().starts_with("</") {
+                self.position += 2;
+                if self.name()? != element.name {
+                    return Err(self.error());
+                }
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.error());
+                }
+                self.position += 1;
+
+                return Ok(element);
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest().starts_with("<![CDATA[") {
+                self.position += 9;
+                let length = self.rest().find("]]>").ok_or(self.error())?;
+                element.text.push_str(&self.rest()[..length]);
+                self.position += length + 3;
+            } else {
+                element.children.push(self.child()?);
+            }
+        }
+    }
+
+    /// Parse child element one level deeper. Fails when nesting limit is reached
+    fn child(&mut self) -> Result<Element, XmlError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error());
+        }
+
+        self.depth += 1;
+        let child = self.element();
+        self.depth -= 1;
+
+        child
+    }
+}
+
+/// Replace predefined and numeric entities ('&amp;', '&#65;', '&#x41;'). Returns None for unknown entity
+fn decode_entities(text: &str) -> Option<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let end = rest.find(';')?;
+        let entity = &rest[..end];
+        rest = &rest[end + 1..];
+
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()?
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse().ok()?
+                } else {
+                    return None;
+                };
+
+                char::from_u32(code)?
+            }
+        };
+        result.push(c);
+    }
+    result.push_str(rest);
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_document() {
+        let src = r#"<?xml version="1.0"?>
+            <!-- map -->
+            <map width='2' name="a &amp; b">
+                <layer id="1">text &#65;&#x42;<![CDATA[<raw>]]></layer>
+                <layer id="2"/>
+            </map>
+        "#;
+        let root = parse(src).unwrap();
+
+        assert_eq!(root.name, "map");
+        assert_eq!(root.attribute("width"), Some("2"));
+        assert_eq!(root.attribute("name"), Some("a & b"));
+        assert_eq!(root.children_named("layer").count(), 2);
+
+        let layer = root.child("layer").unwrap();
+        assert_eq!(layer.attribute("id"), Some("1"));
+        assert_eq!(layer.text, "text AB<raw>");
+        assert!(root.children[1].children.is_empty());
+    }
+
+    #[test]
+    fn invalid_documents() {
+        assert!(parse("<a><b></a>").is_err());
+        assert!(parse("<a x=1/>").is_err());
+        assert!(parse("<a>&unknown;</a>").is_err());
+        assert!(parse("<a/><b/>").is_err());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth: usize| "<a>".repeat(depth) + &"</a>".repeat(depth);
+
+        // Root and MAX_DEPTH levels of children
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 2)).is_err());
+        // Deep document fails quickly instead of overflowing stack
+        assert!(parse(&"<a>".repeat(100_000)).is_err());
+    }
+}
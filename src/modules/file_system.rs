@@ -1,5 +1,6 @@
 use psp::{sys::*, dprintln};
 use core::ffi::c_void;
+use alloc::vec::Vec;
 
 static PATH_TO_FILE: &str = "./files/example.json";
 
@@ -62,3 +63,77 @@ pub unsafe fn dir_sys() {
     // Close directory descriptor after all
     sceIoDclose(dird);
 }
+
+/// Make copy of path with NUL byte at the end because 'sceIo' functions expect C language strings
+pub fn c_path(path: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(path.len() + 1);
+    bytes.extend_from_slice(path.as_bytes());
+    bytes.push(0);
+
+    bytes
+}
+
+/// Read whole file content. On failure returns error code obtained from 'sceIo' function
+pub unsafe fn read_file(path: &str) -> Result<Vec<u8>, i32> {
+    let path = self::c_path(path);
+    let fd = sceIoOpen(path.as_ptr(), IoOpenFlags::RD_ONLY, 0o777);
+    if fd.0 < 0 {
+        return Err(fd.0);
+    }
+
+    // Read file chunk by chunk until there is nothing more to read
+    let mut content = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        let readed = sceIoRead(fd, chunk.as_mut_ptr() as *mut c_void, chunk.len() as u32);
+
+        if readed < 0 {
+            sceIoClose(fd);
+            return Err(readed);
+        } else if readed == 0 {
+            break;
+        }
+
+        content.extend_from_slice(&chunk[..readed as usize]);
+    }
+
+    sceIoClose(fd);
+    Ok(content)
+}
+
+/// Create file (or replace existing one) with specified content. On failure returns error code obtained from 'sceIo' function
+pub unsafe fn write_file(path: &str, content: &[u8]) -> Result<(), i32> {
+    let path = self::c_path(path);
+    let fd = sceIoOpen(path.as_ptr(), IoOpenFlags::WR_ONLY | IoOpenFlags::CREAT | IoOpenFlags::TRUNC, 0o777);
+    if fd.0 < 0 {
+        return Err(fd.0);
+    }
+
+    // One call can write less bytes then requested so writing is repeated until everything is written
+    let mut written = 0;
+    while written < content.len() {
+        let result = sceIoWrite(fd, content[written..].as_ptr() as *const c_void, content.len() - written);
+
+        if result <= 0 {
+            sceIoClose(fd);
+            return Err(result);
+        }
+
+        written += result as usize;
+    }
+
+    sceIoClose(fd);
+    Ok(())
+}
+
+/// Create directory when it doesn't exist yet. On failure returns error code obtained from 'sceIoMkdir'
+pub unsafe fn ensure_dir(path: &str) -> Result<(), i32> {
+    // Error code returned by 'sceIoMkdir' when directory already exists
+    const ALREADY_EXISTS: i32 = 0x8001_0011u32 as i32;
+
+    let path = self::c_path(path);
+    match sceIoMkdir(path.as_ptr(), 0o777) {
+        result if result >= 0 || result == ALREADY_EXISTS => Ok(()),
+        result => Err(result)
+    }
+}
@@ -0,0 +1,189 @@
+use psp::sys::{sceKernelDelayThread, sceRtcGetTickResolution};
+use crate::time;
+
+/// Amount of last frames kept by 'FrameStats'
+pub const STATS_FRAMES: usize = 120;
+
+/// Durations of last 'STATS_FRAMES' frames kept in ring buffer
+pub struct FrameStats {
+    /// Frame durations in seconds
+    samples: [f32; STATS_FRAMES],
+    /// Amount of valid samples (smaller then 'STATS_FRAMES' only at the beginning)
+    len: usize,
+    /// Place where next sample will be written
+    next: usize,
+}
+
+impl FrameStats {
+    pub const fn new() -> Self {
+        Self { samples: [0.0; STATS_FRAMES], len: 0, next: 0 }
+    }
+
+    /// Remember duration of one frame (in seconds). The oldest sample is overwritten when buffer is full
+    pub fn push(&mut self, frame_time: f32) {
+        self.samples[self.next] = frame_time;
+        self.next = (self.next + 1) % STATS_FRAMES;
+        self.len = (self.len + 1).min(STATS_FRAMES);
+    }
+
+    /// Remembered frame durations in seconds, from the oldest to the newest
+    pub fn frame_times(&self) -> impl Iterator<Item = f32> + '_ {
+        let start = (self.next + STATS_FRAMES - self.len) % STATS_FRAMES;
+        (0..self.len).map(move |i| self.samples[(start + i) % STATS_FRAMES])
+    }
+
+    /// Average frame duration in seconds (0 when nothing was measured yet)
+    pub fn average(&self) -> f32 {
+        if self.len == 0 {
+            return 0.0;
+        }
+
+        self.frame_times().sum::<f32>() / self.len as f32
+    }
+
+    /// Average frames per second
+    pub fn fps(&self) -> f32 {
+        let average = self.average();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+
+    /// The shortest frame duration in seconds
+    pub fn min(&self) -> f32 {
+        self.frame_times().fold(None, |min: Option<f32>, time| Some(min.map_or(time, |min| min.min(time)))).unwrap_or(0.0)
+    }
+
+    /// The longest frame duration in seconds (the worst hitch)
+    pub fn max(&self) -> f32 {
+        self.frame_times().fold(0.0, f32::max)
+    }
+
+    /// Frame duration which isn't exceeded by 'percent' % of frames (e.g. 99.0 gives duration of the slowest 1% of frames)
+    pub fn percentile(&self, percent: f32) -> f32 {
+        if self.len == 0 {
+            return 0.0;
+        }
+
+        let mut sorted = [0.0f32; STATS_FRAMES];
+        for (slot, time) in sorted.iter_mut().zip(self.frame_times()) {
+            *slot = time;
+        }
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+
+        let rank = (percent.clamp(0.0, 100.0) / 100.0 * (self.len - 1) as f32 + 0.5) as usize;
+        sorted[rank]
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Game loop with fixed timestep updates and variable rendering. Game logic is always advanced by the same 'step',
+/// so movement and physics behave the same way no matter how many frames per second are drawn. Example:
+/// ```ignore
+/// let mut game_loop = GameLoop::new(60.0);
+/// loop {
+///     game_loop.begin_frame();
+///     while game_loop.update_due() {
+///         update(game_loop.step());
+///     }
+///     render(game_loop.alpha());
+///     game_loop.end_frame();
+/// }
+/// ```
+pub struct GameLoop {
+    /// Duration of one update in seconds
+    step: f32,
+    /// Time which wasn't consumed by updates yet
+    accumulator: f32,
+    /// Longer frames are cut to this duration, so after long hitch (e.g. loading) game doesn't try to catch up with hundreds of updates
+    max_frame_time: f32,
+    /// Minimal frame duration in seconds when frame limiter is enabled
+    frame_limit: Option<f32>,
+    /// Tick of current frame beginning
+    frame_start: u64,
+    /// Ticks per second
+    resolution: f32,
+    pub stats: FrameStats,
+}
+
+impl GameLoop {
+    /// Create loop which updates game 'updates_per_second' times per second
+    pub unsafe fn new(updates_per_second: f32) -> Self {
+        Self {
+            step: 1.0 / updates_per_second,
+            accumulator: 0.0,
+            max_frame_time: 0.25,
+            frame_limit: None,
+            frame_start: time::current_tick(),
+            resolution: sceRtcGetTickResolution() as f32,
+            stats: FrameStats::new(),
+        }
+    }
+
+    /// Don't draw more then 'fps' frames per second (remaining time of too short frame is slept in 'end_frame'). None disables limiter
+    pub fn with_frame_limit(mut self, fps: Option<f32>) -> Self {
+        self.frame_limit = fps.map(|fps| 1.0 / fps);
+        self
+    }
+
+    /// Duration of one update in seconds (value which should be passed to update logic)
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Measure previous frame and add its time for updates. Must be called at the beginning of each frame
+    pub unsafe fn begin_frame(&mut self) {
+        let now = time::current_tick();
+        let frame_time = now.saturating_sub(self.frame_start) as f32 / self.resolution;
+        self.frame_start = now;
+
+        self.stats.push(frame_time);
+        self.accumulator += frame_time.min(self.max_frame_time);
+    }
+
+    /// Whether one more update should be performed in this frame (call in loop until it returns false)
+    pub fn update_due(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How far (0.0..1.0) current moment is between the last update and the next one. Renderer should interpolate between previous and current state with it
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+
+    /// Finish frame. With frame limiter enabled thread sleeps until minimal frame duration elapses
+    pub unsafe fn end_frame(&mut self) {
+        if let Some(limit) = self.frame_limit {
+            let elapsed = time::current_tick().saturating_sub(self.frame_start) as f32 / self.resolution;
+
+            if elapsed < limit {
+                sceKernelDelayThread(((limit - elapsed) * 1_000_000.0) as u32);
+            }
+        }
+    }
+
+    /// Run loop until 'update' returns false. 'update' receives step duration, 'render' receives interpolation alpha and frame statistics
+    pub unsafe fn run(&mut self, mut update: impl FnMut(f32) -> bool, mut render: impl FnMut(f32, &FrameStats)) {
+        loop {
+            self.begin_frame();
+
+            while self.update_due() {
+                if !update(self.step) {
+                    return;
+                }
+            }
+
+            render(self.alpha(), &self.stats);
+            self.end_frame();
+        }
+    }
+}
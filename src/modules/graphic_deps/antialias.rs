@@ -0,0 +1,296 @@
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use core::marker::PhantomData;
+use psp::sys::sceGeEdramGetAddr;
+use psp::math::{sqrtf, floorf, fabsf, atan2f, sinf, cosf};
+use psp::{BUF_WIDTH, SCREEN_WIDTH, SCREEN_HEIGHT};
+use psp::embedded_graphics::Framebuffer;
+use embedded_graphics::{prelude::*, pixelcolor::Rgb888};
+
+/// Amount of sub-scanlines sampled per pixel row by 'fill_polygon' (horizontal coverage is computed exactly)
+const POLYGON_SUBSAMPLES: usize = 4;
+
+/// Anti-aliased shapes for 'Framebuffer' target ('embedded-graphics' draws only whole pixels, so edges looks jagged).
+/// Positions are in pixels with sub-pixel precision, integer position is center of pixel (the same as 'Point' in 'embedded-graphics').
+/// Every shape is blended over pixels which already are in frame buffer, partially covered pixels get only part of color
+pub struct AaPainter<'a> {
+    /// The same VRAM buffer to which 'Framebuffer' writes (readed and written through uncached address)
+    pixels: *mut u32,
+    /// Opacity of drawn shapes (0.0..=1.0), multiplied with coverage of each pixel
+    opacity: f32,
+    _display: PhantomData<&'a mut Framebuffer>,
+}
+
+impl<'a> AaPainter<'a> {
+    /// Create painter for display which was set by 'Framebuffer::new'. 'embedded-graphics' and anti-aliased shapes can be drawn in turns
+    pub fn new(_display: &'a mut Framebuffer) -> Self {
+        let pixels = unsafe { (0x4000_0000 | sceGeEdramGetAddr() as u32) as *mut u32 };
+        Self { pixels, opacity: 1.0, _display: PhantomData }
+    }
+
+    /// Set opacity of next drawn shapes (1.0 = opaque, 0.0 = invisible)
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity.clamp(0.0, 1.0);
+    }
+
+    /// Blend color into pixel. 'coverage' says how much of pixel is covered by shape (0.0..=1.0)
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Rgb888, coverage: f32) {
+        if x < 0 || y < 0 || x >= SCREEN_WIDTH as i32 || y >= SCREEN_HEIGHT as i32 {
+            return;
+        }
+        let alpha = coverage.clamp(0.0, 1.0) * self.opacity;
+        if alpha <= 0.0 {
+            return;
+        }
+
+        unsafe {
+            let pixel = self.pixels.add(y as usize * BUF_WIDTH as usize + x as usize);
+            let old = *pixel;
+
+            // Pixel keeps red channel in the lowest byte, the highest byte is left untouched
+            let mix = |shift: u32, new: u8| {
+                let old = ((old >> shift) & 0xff) as f32;
+                ((old + (new as f32 - old) * alpha + 0.5) as u32) << shift
+            };
+            *pixel = (old & 0xff00_0000) | mix(0, color.r()) | mix(8, color.g()) | mix(16, color.b());
+        }
+    }
+
+    /// One pixel wide line drawn with Xiaolin Wu algorithm
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), color: Rgb888) {
+        let (mut x0, mut y0, mut x1, mut y1) = (from.0, from.1, to.0, to.1);
+
+        // Algorithm walks along longer axis, so steep lines are drawn with swapped axes
+        let steep = fabsf(y1 - y0) > fabsf(x1 - x0);
+        if steep {
+            core::mem::swap(&mut x0, &mut y0);
+            core::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            core::mem::swap(&mut x0, &mut x1);
+            core::mem::swap(&mut y0, &mut y1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx == 0.0 { 1.0 } else { (y1 - y0) / dx };
+        let mut plot = |x: i32, y: i32, coverage: f32| {
+            if steep { self.blend_pixel(y, x, color, coverage) } else { self.blend_pixel(x, y, color, coverage) }
+        };
+
+        // Ends are weighted by the part of pixel covered by line along main axis
+        let x_start = round(x0);
+        let y_start = y0 + gradient * (x_start - x0);
+        let gap = 1.0 - fract(x0 + 0.5);
+        plot(x_start as i32, floorf(y_start) as i32, (1.0 - fract(y_start)) * gap);
+        plot(x_start as i32, floorf(y_start) as i32 + 1, fract(y_start) * gap);
+
+        let x_end = round(x1);
+        let y_end = y1 + gradient * (x_end - x1);
+        let gap = fract(x1 + 0.5);
+        plot(x_end as i32, floorf(y_end) as i32, (1.0 - fract(y_end)) * gap);
+        plot(x_end as i32, floorf(y_end) as i32 + 1, fract(y_end) * gap);
+
+        // Columns between ends are clipped to screen, so lines going far outside of it don't loop over invisible pixels
+        let limit = (if steep { SCREEN_HEIGHT } else { SCREEN_WIDTH }) as i32;
+        let first = (x_start as i32).saturating_add(1).max(0);
+        let last = (x_end as i32).min(limit);
+
+        // Each column between ends is shared by 2 pixels proportionally to distance from line
+        let mut y = y_start + gradient * (first as f32 - x_start);
+        for x in first..last {
+            plot(x, floorf(y) as i32, 1.0 - fract(y));
+            plot(x, floorf(y) as i32 + 1, fract(y));
+            y += gradient;
+        }
+    }
+
+    /// Line with specified width in pixels (drawn as filled quad, so it has flat ends)
+    pub fn thick_line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: Rgb888) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let length = sqrtf(dx * dx + dy * dy);
+        if length == 0.0 {
+            return;
+        }
+
+        // Half of width along line normal
+        let (nx, ny) = (-dy / length * width / 2.0, dx / length * width / 2.0);
+        self.fill_polygon(&[
+            (from.0 + nx, from.1 + ny),
+            (to.0 + nx, to.1 + ny),
+            (to.0 - nx, to.1 - ny),
+            (from.0 - nx, from.1 - ny),
+        ], color);
+    }
+
+    /// Circle outline with 'width' pixels thick stroke centered on 'radius'
+    pub fn circle(&mut self, center: (f32, f32), radius: f32, width: f32, color: Rgb888) {
+        let extent = radius + width / 2.0;
+        self.coverage_shape(center, (extent, extent), color, |x, y| fabsf(sqrtf(x * x + y * y) - radius) - width / 2.0);
+    }
+
+    pub fn fill_circle(&mut self, center: (f32, f32), radius: f32, color: Rgb888) {
+        self.coverage_shape(center, (radius, radius), color, |x, y| sqrtf(x * x + y * y) - radius);
+    }
+
+    /// Ellipse outline with 'width' pixels thick stroke. 'radii' are horizontal and vertical radius
+    pub fn ellipse(&mut self, center: (f32, f32), radii: (f32, f32), width: f32, color: Rgb888) {
+        let extent = (radii.0 + width / 2.0, radii.1 + width / 2.0);
+        self.coverage_shape(center, extent, color, |x, y| fabsf(ellipse_distance(x, y, radii)) - width / 2.0);
+    }
+
+    pub fn fill_ellipse(&mut self, center: (f32, f32), radii: (f32, f32), color: Rgb888) {
+        self.coverage_shape(center, radii, color, |x, y| ellipse_distance(x, y, radii));
+    }
+
+    /// Part of circle outline with round ends. Starts at 'start' angle and goes clockwise (on screen) by 'sweep' radians (both in radians, 0 points right)
+    pub fn arc(&mut self, center: (f32, f32), radius: f32, start: f32, sweep: f32, width: f32, color: Rgb888) {
+        let sweep = sweep.clamp(0.0, 2.0 * PI);
+        let (start_end, finish_end) = unsafe {
+            (
+                (cosf(start) * radius, sinf(start) * radius),
+                (cosf(start + sweep) * radius, sinf(start + sweep) * radius),
+            )
+        };
+
+        let extent = radius + width / 2.0;
+        self.coverage_shape(center, (extent, extent), color, |x, y| {
+            // Angle measured from arc start in 0..2PI range
+            let mut angle = atan2f(y, x) - start;
+            angle -= floorf(angle / (2.0 * PI)) * 2.0 * PI;
+
+            let distance = if angle <= sweep {
+                fabsf(sqrtf(x * x + y * y) - radius)
+            } else {
+                // Outside of arc, distance to the nearest end gives round cap
+                let to_start = sqrtf((x - start_end.0) * (x - start_end.0) + (y - start_end.1) * (y - start_end.1));
+                let to_finish = sqrtf((x - finish_end.0) * (x - finish_end.0) + (y - finish_end.1) * (y - finish_end.1));
+                to_start.min(to_finish)
+            };
+
+            distance - width / 2.0
+        });
+    }
+
+    /// Closed polygon outline made from 1 pixel wide lines
+    pub fn polygon(&mut self, points: &[(f32, f32)], color: Rgb888) {
+        for (i, from) in points.iter().enumerate() {
+            let to = points[(i + 1) % points.len()];
+            self.line(*from, to, color);
+        }
+    }
+
+    /// Filled polygon (also concave and self-intersecting, with even-odd rule) with edges smoothed by exact pixel coverage
+    pub fn fill_polygon(&mut self, points: &[(f32, f32)], color: Rgb888) {
+        if points.len() < 3 {
+            return;
+        }
+
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for (x, y) in points {
+            min_x = min_x.min(*x);
+            min_y = min_y.min(*y);
+            max_x = max_x.max(*x);
+            max_y = max_y.max(*y);
+        }
+
+        // Only pixels on screen are rasterized
+        let left = (round(min_x) as i32).max(0);
+        let right = (round(max_x) as i32).min(SCREEN_WIDTH as i32 - 1);
+        let top = (round(min_y) as i32).max(0);
+        let bottom = (round(max_y) as i32).min(SCREEN_HEIGHT as i32 - 1);
+        if left > right || top > bottom {
+            return;
+        }
+
+        let mut coverage = alloc::vec![0.0f32; (right - left + 1) as usize];
+        let mut crossings = Vec::new();
+        let weight = 1.0 / POLYGON_SUBSAMPLES as f32;
+
+        for y in top..=bottom {
+            coverage.iter_mut().for_each(|value| *value = 0.0);
+
+            for sample in 0..POLYGON_SUBSAMPLES {
+                let scan_y = y as f32 - 0.5 + (sample as f32 + 0.5) * weight;
+
+                // Places where sub-scanline crosses edges (edge includes its upper end and excludes lower one)
+                crossings.clear();
+                for (i, (x0, y0)) in points.iter().enumerate() {
+                    let (x1, y1) = points[(i + 1) % points.len()];
+                    if (*y0 <= scan_y && y1 > scan_y) || (y1 <= scan_y && *y0 > scan_y) {
+                        crossings.push(x0 + (scan_y - y0) / (y1 - y0) * (x1 - x0));
+                    }
+                }
+                crossings.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+
+                for span in crossings.chunks_exact(2) {
+                    add_span(&mut coverage, span[0] - left as f32 + 0.5, span[1] - left as f32 + 0.5, weight);
+                }
+            }
+
+            for (i, value) in coverage.iter().enumerate() {
+                if *value > 0.0 {
+                    self.blend_pixel(left + i as i32, y, color, *value);
+                }
+            }
+        }
+    }
+
+    /// Draw shape described by signed distance (negative inside) from its center. Pixel coverage is taken from distance of pixel center to shape edge.
+    /// 'extent' is half of shape size in both axes
+    fn coverage_shape(&mut self, center: (f32, f32), extent: (f32, f32), color: Rgb888, distance: impl Fn(f32, f32) -> f32) {
+        let left = (floorf(center.0 - extent.0) as i32).max(0);
+        let right = (floorf(center.0 + extent.0) as i32 + 1).min(SCREEN_WIDTH as i32 - 1);
+        let top = (floorf(center.1 - extent.1) as i32).max(0);
+        let bottom = (floorf(center.1 + extent.1) as i32 + 1).min(SCREEN_HEIGHT as i32 - 1);
+
+        for y in top..=bottom {
+            for x in left..=right {
+                let coverage = 0.5 - distance(x as f32 - center.0, y as f32 - center.1);
+                if coverage > 0.0 {
+                    self.blend_pixel(x, y, color, coverage);
+                }
+            }
+        }
+    }
+}
+
+/// Add horizontal span to row coverage. 'from' and 'to' are in units where pixel 'i' covers range 'i'..'i + 1'
+fn add_span(coverage: &mut [f32], from: f32, to: f32, weight: f32) {
+    let (from, to) = (from.clamp(0.0, coverage.len() as f32), to.clamp(0.0, coverage.len() as f32));
+    if to <= from {
+        return;
+    }
+
+    let (first, last) = (floorf(from) as usize, floorf(to) as usize);
+    if first == last {
+        coverage[first] += (to - from) * weight;
+        return;
+    }
+
+    // Partially covered pixels at span ends, fully covered ones between them
+    coverage[first] += (first as f32 + 1.0 - from) * weight;
+    for value in &mut coverage[first + 1..last] {
+        *value += weight;
+    }
+    if last < coverage.len() {
+        coverage[last] += (to - last as f32) * weight;
+    }
+}
+
+/// Approximated signed distance from ellipse edge (value of implicit function divided by length of its gradient)
+fn ellipse_distance(x: f32, y: f32, radii: (f32, f32)) -> f32 {
+    let (a, b) = radii;
+    let value = (x * x) / (a * a) + (y * y) / (b * b) - 1.0;
+    let gradient = 2.0 * sqrtf((x * x) / (a * a * a * a) + (y * y) / (b * b * b * b));
+
+    // Gradient is 0 only in center
+    if gradient == 0.0 { -a.min(b) } else { value / gradient }
+}
+
+fn round(value: f32) -> f32 {
+    floorf(value + 0.5)
+}
+
+fn fract(value: f32) -> f32 {
+    value - floorf(value)
+}
@@ -0,0 +1,136 @@
+use alloc::alloc::{alloc, dealloc, Layout};
+use core::ffi::c_void;
+use psp::sys::*;
+use crate::render_state::StateCache;
+
+/// Space reserved in every list for matrices which 'mark_matrices_dirty' makes the first 'sceGum' draw send (4 matrices, up to 17 commands each)
+/// and for commands which end list, so caller's capacity is left for its own commands
+const MATRICES_SIZE: usize = 320;
+
+/// Commands of static geometry (draws, transforms, render states) recorded once to own display list and next replayed every frame by 'sceGuCallList',
+/// so CPU doesn't build the same commands again. Example:
+/// ```ignore
+/// let mut static_shapes = CallList::new(16 * 1024).unwrap();
+/// loop {
+///     GMng::start_new_frame();
+///     static_shapes.call_or_record(&mut states, |states| draw_static_shapes(states));
+///     GMng::end_existing_frame();
+/// }
+/// ```
+/// When content changes (e.g. shapes moved) list must be recorded again, 'invalidate' makes next 'call_or_record' do this
+pub struct CallList {
+    /// Display list memory (16 bytes aligned as GE requires)
+    commands: *mut u8,
+    layout: Layout,
+    /// Size of recorded commands in bytes. None when list must be recorded (again)
+    recorded: Option<usize>,
+    /// Recording once wrote past the list. Such list is never recorded again, because every next recording would damage memory again
+    overflowed: bool,
+}
+
+impl CallList {
+    /// Create empty list which can keep up to 'capacity' bytes of commands. Commands and vertices taken by 'sceGuGetMemory' while recording are stored here too.
+    /// Space for matrices sent by list itself is added to it. None when 'capacity' is 0 or memory couldn't be allocated
+    pub fn new(capacity: usize) -> Option<Self> {
+        if capacity == 0 {
+            return None;
+        }
+
+        let layout = Layout::from_size_align(capacity.checked_add(MATRICES_SIZE)?, 16).ok()?;
+        let commands = unsafe { alloc(layout) };
+        if commands.is_null() {
+            return None;
+        }
+
+        Some(Self { commands, layout, recorded: None, overflowed: false })
+    }
+
+    /// Whether list has got valid recorded commands
+    pub fn is_recorded(&self) -> bool {
+        self.recorded.is_some()
+    }
+
+    /// Size of recorded commands in bytes
+    pub fn size(&self) -> Option<usize> {
+        self.recorded
+    }
+
+    /// Whether some recording didn't fit into list (after that list stays unrecorded forever)
+    pub fn is_overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Mark content as outdated, so it's recorded again by next 'call_or_record'
+    pub fn invalidate(&mut self) {
+        self.recorded = None;
+    }
+
+    /// Record commands sent by 'commands' closure. Closure gets empty state cache, so recorded list sets all render states it uses and doesn't depend
+    /// on states set before it's called. All 'sceGum' matrices are sent inside list as well. Can be called inside started frame (commands are written to this list, not to frame one)
+    /// or before it. Returns size of recorded commands. None when list overflowed now or before (see 'is_overflowed'), then closure isn't called at all.
+    ///
+    /// Closure MUST NOT send more then 'capacity' bytes (including 'sceGuGetMemory' blocks): GU writes commands without any bounds check, so sending more
+    /// is undefined behaviour - memory behind list is already damaged when size is checked. None returned after it doesn't mean that program can safely
+    /// continue, it only helps to notice too small capacity while developing. Capacity should be chosen with margin for the worst case content
+    /// (size returned for it can be printed once while developing)
+    pub unsafe fn record(&mut self, commands: impl FnOnce(&mut StateCache)) -> Option<usize> {
+        if self.overflowed {
+            return None;
+        }
+
+        // List can't be rewritten while GE may still execute it. Frames in this project wait for GE in 'GMng::end_existing_frame', so here it's already done
+        sceGuStart(GuContextType::Call, self.commands as *mut c_void);
+        mark_matrices_dirty();
+
+        let mut states = StateCache::new();
+        commands(&mut states);
+
+        let size = sceGuFinish() as usize;
+        // Matrices in GE are now these from the end of list, the same as CPU side matrices, but they're sent again to not depend on it
+        mark_matrices_dirty();
+
+        self.overflowed = size > self.layout.size();
+        self.recorded = (!self.overflowed).then_some(size);
+        self.recorded
+    }
+
+    /// Replay recorded commands in current frame. Render states and matrices set by list stay in GE after it, so 'states' is invalidated
+    /// and matrices are sent again with the next 'sceGum' draw. Does nothing when list isn't recorded
+    pub unsafe fn call(&self, states: &mut StateCache) {
+        if self.recorded.is_none() {
+            return;
+        }
+
+        sceGuCallList(self.commands as *const c_void);
+
+        states.invalidate();
+        mark_matrices_dirty();
+    }
+
+    /// Record list when it isn't recorded yet (first use or after 'invalidate') and call it. Overflowed list isn't recorded nor called
+    pub unsafe fn call_or_record(&mut self, states: &mut StateCache, commands: impl FnOnce(&mut StateCache)) {
+        if self.recorded.is_none() {
+            self.record(commands);
+        }
+
+        self.call(states);
+    }
+}
+
+impl Drop for CallList {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.commands, self.layout) };
+    }
+}
+
+/// Make 'sceGum' send all matrices with the next draw (it sends only changed ones, but it doesn't know what called list left in GE).
+/// 'Model' mode is active after it
+unsafe fn mark_matrices_dirty() {
+    let mut matrix = core::mem::zeroed::<ScePspFMatrix4>();
+
+    for mode in [MatrixMode::Projection, MatrixMode::View, MatrixMode::Texture, MatrixMode::Model] {
+        sceGumMatrixMode(mode);
+        sceGumStoreMatrix(&mut matrix);
+        sceGumLoadMatrix(&matrix); // loading marks matrix as changed
+    }
+}
@@ -0,0 +1,167 @@
+use psp::sys::*;
+use psp::math::{sinf, cosf};
+
+/// Perspective camera used to render real 3D scenes (instead of only flat shapes placed at z = -10)
+pub struct Camera {
+    /// Position of the camera "eye" in world space
+    pub eye: ScePspFVector3,
+    /// Point in world space where camera looks at
+    pub target: ScePspFVector3,
+    /// Which direction is treated as "up" for the camera (usually positive 'y' axis)
+    pub up: ScePspFVector3,
+    /// Vertical field of view in degrees (same unit as 'sceGumPerspective' expects)
+    pub fovy: f32,
+    /// Screen width divided by screen height
+    pub aspect: f32,
+    /// Distance to the near clipping plane (must be greater then 0)
+    pub near: f32,
+    /// Distance to the far clipping plane
+    pub far: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            eye: ScePspFVector3 { x: 0.0, y: 0.0, z: 3.0 },
+            target: ScePspFVector3 { x: 0.0, y: 0.0, z: 0.0 },
+            up: ScePspFVector3 { x: 0.0, y: 1.0, z: 0.0 },
+            fovy: 75.0,
+            aspect: 16.0 / 9.0,
+            near: 0.5,
+            far: 1000.0,
+        }
+    }
+}
+
+impl Camera {
+    /// Load perspective matrix to 'Projection' and look-at matrix to 'View'. Must be called inside started frame (between 'sceGuStart' and 'sceGuFinish')
+    pub unsafe fn apply(&self) {
+        // Projection matrix -> how 3D world is projected on the 2D PSP screen
+        sceGumMatrixMode(MatrixMode::Projection);
+        sceGumLoadIdentity();
+        sceGumPerspective(self.fovy, self.aspect, self.near, self.far);
+
+        // View matrix -> where camera is and where it looks at
+        sceGumMatrixMode(MatrixMode::View);
+        sceGumLoadIdentity();
+        sceGumLookAt(&self.eye, &self.target, &self.up);
+
+        // Leave 'Model' matrix selected with default values so next drawn models start from the world origin
+        sceGumMatrixMode(MatrixMode::Model);
+        sceGumLoadIdentity();
+    }
+}
+
+/// Camera controller which rotates camera around some point (like in 3D model viewers)
+pub struct OrbitController {
+    /// Point around which camera is rotating
+    pub center: ScePspFVector3,
+    /// Horizontal rotation angle in radians
+    pub yaw: f32,
+    /// Vertical rotation angle in radians (clamped to avoid flipping camera over the poles)
+    pub pitch: f32,
+    /// Distance between camera and 'center'
+    pub distance: f32,
+    /// Radians per frame for full stick deflection
+    pub speed: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        Self {
+            center: ScePspFVector3 { x: 0.0, y: 0.0, z: 0.0 },
+            yaw: 0.0,
+            pitch: 0.3,
+            distance: 3.0,
+            speed: 0.04,
+        }
+    }
+}
+
+impl OrbitController {
+    /// Rotate around 'center' using analog stick values (each in -1.0..=1.0 range) and write result to camera
+    pub unsafe fn update(&mut self, camera: &mut Camera, stick: (f32, f32)) {
+        let (sx, sy) = stick;
+
+        self.yaw += sx * self.speed;
+        self.pitch = clamp_pitch(self.pitch + sy * self.speed);
+
+        // Spherical coordinates -> cartesian coordinates
+        let horizontal = self.distance * cosf(self.pitch);
+        camera.eye = ScePspFVector3 {
+            x: self.center.x + horizontal * sinf(self.yaw),
+            y: self.center.y + self.distance * sinf(self.pitch),
+            z: self.center.z + horizontal * cosf(self.yaw),
+        };
+        camera.target = self.center;
+    }
+}
+
+/// Camera controller which moves camera like eyes of player (first person games)
+pub struct FirstPersonController {
+    /// Position of player eyes
+    pub position: ScePspFVector3,
+    /// Horizontal look angle in radians
+    pub yaw: f32,
+    /// Vertical look angle in radians
+    pub pitch: f32,
+    /// World units per frame for full stick deflection
+    pub move_speed: f32,
+    /// Radians per frame when look buttons are held
+    pub look_speed: f32,
+}
+
+impl Default for FirstPersonController {
+    fn default() -> Self {
+        Self {
+            position: ScePspFVector3 { x: 0.0, y: 0.0, z: 3.0 },
+            yaw: 0.0,
+            pitch: 0.0,
+            move_speed: 0.05,
+            look_speed: 0.03,
+        }
+    }
+}
+
+impl FirstPersonController {
+    /// Analog stick moves player (forward/backward and strafing), D-Pad buttons rotates the look direction
+    pub unsafe fn update(&mut self, camera: &mut Camera, stick: (f32, f32), buttons: CtrlButtons) {
+        let (sx, sy) = stick;
+
+        // Look around
+        if buttons.contains(CtrlButtons::LEFT) { self.yaw += self.look_speed }
+        if buttons.contains(CtrlButtons::RIGHT) { self.yaw -= self.look_speed }
+        if buttons.contains(CtrlButtons::UP) { self.pitch += self.look_speed }
+        if buttons.contains(CtrlButtons::DOWN) { self.pitch -= self.look_speed }
+        self.pitch = clamp_pitch(self.pitch);
+
+        // Direction where player looks at (camera looks at negative 'z' axis when both angles are 0)
+        let forward = ScePspFVector3 {
+            x: -sinf(self.yaw) * cosf(self.pitch),
+            y: sinf(self.pitch),
+            z: -cosf(self.yaw) * cosf(self.pitch),
+        };
+        // Vector perpendicular to walking direction which is used to strafe
+        let right = ScePspFVector3 { x: cosf(self.yaw), y: 0.0, z: -sinf(self.yaw) };
+
+        // Stick up gives negative 'y' value so it must be inverted to walk forward
+        let walk = -sy * self.move_speed;
+        let strafe = sx * self.move_speed;
+        self.position.x += forward.x * walk + right.x * strafe;
+        self.position.z += forward.z * walk + right.z * strafe;
+
+        camera.eye = self.position;
+        camera.target = ScePspFVector3 {
+            x: self.position.x + forward.x,
+            y: self.position.y + forward.y,
+            z: self.position.z + forward.z,
+        };
+    }
+}
+
+/// Keep pitch angle a bit below 90 degrees so look-at 'up' vector never becomes parallel to view direction
+fn clamp_pitch(pitch: f32) -> f32 {
+    const LIMIT: f32 = 1.5;
+
+    pitch.clamp(-LIMIT, LIMIT)
+}
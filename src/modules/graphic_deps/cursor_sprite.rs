@@ -0,0 +1,38 @@
+use psp::sys::TexturePixelFormat;
+use embedded_graphics::{prelude::*, primitives::*, pixelcolor::Rgb888};
+use crate::texture_canvas::TextureCanvas;
+use crate::render_state::StateCache;
+
+/// Arrow pointer drawn as sprite on top of frame (e.g. for 'Cursor' from 'input_deps/cursor.rs'). Arrow is drawn once into texture,
+/// so drawing it every frame costs one quad
+pub struct CursorSprite {
+    canvas: TextureCanvas,
+    /// Point of texture which shows cursor position (arrow tip)
+    hotspot: (f32, f32),
+}
+
+impl CursorSprite {
+    /// White arrow with black outline. Returns None when texture couldn't be allocated
+    pub fn arrow() -> Option<Self> {
+        let mut canvas = TextureCanvas::new(32, 32, TexturePixelFormat::Psm8888)?;
+
+        let outline = PrimitiveStyleBuilder::new().fill_color(Rgb888::WHITE).stroke_color(Rgb888::BLACK).stroke_width(1).build();
+        Line::new(Point::new(5, 12), Point::new(9, 20)).into_styled(PrimitiveStyle::with_stroke(Rgb888::BLACK, 4)).draw(&mut canvas).ok()?;
+        Line::new(Point::new(5, 12), Point::new(9, 20)).into_styled(PrimitiveStyle::with_stroke(Rgb888::WHITE, 2)).draw(&mut canvas).ok()?;
+        Triangle::new(Point::new(1, 1), Point::new(1, 17), Point::new(12, 12)).into_styled(outline).draw(&mut canvas).ok()?;
+
+        Some(Self { canvas, hotspot: (1.0, 1.0) })
+    }
+
+    /// Draw sprite with hotspot in 'position'. Pressed cursor is drawn a bit smaller (like pushed in). Must be called inside started frame, after UI
+    pub unsafe fn draw(&self, position: (f32, f32), pressed: bool, states: &mut StateCache) {
+        let scale = if pressed { 0.85 } else { 1.0 };
+        let size = self.canvas.size();
+        let center = (
+            position.0 + (size.width as f32 / 2.0 - self.hotspot.0) * scale,
+            position.1 + (size.height as f32 / 2.0 - self.hotspot.1) * scale,
+        );
+
+        self.canvas.draw(center, scale, 0.0, states);
+    }
+}
@@ -0,0 +1,306 @@
+//! Immediate mode debug drawing. Shapes can be queued from any place during a frame and they are drawn on top of everything by 'GMng::end_existing_frame'.
+//! Without 'debug-draw' cargo feature (e.g. 'cargo psp --release --no-default-features') all functions are empty and queue doesn't exist.
+//! Queue isn't synchronized, so (like the rest of GU code) functions are unsafe and must be called only from the thread which draws frames
+
+#![cfg_attr(not(feature = "debug-draw"), allow(unused_variables, dead_code))]
+
+use core::f32::consts::PI;
+use psp::sys::*;
+use psp::math::{sinf, cosf, sqrtf, tanf};
+use crate::camera::Camera;
+#[cfg(feature = "debug-draw")]
+use alloc::vec::Vec;
+#[cfg(feature = "debug-draw")]
+use core::ffi::c_void;
+#[cfg(feature = "debug-draw")]
+use core::ptr::addr_of_mut;
+#[cfg(feature = "debug-draw")]
+use crate::examples::types_def::Vertex;
+#[cfg(feature = "debug-draw")]
+use embedded_graphics::{prelude::*, pixelcolor::Rgb888, mono_font::{ascii::FONT_6X10, MonoTextStyle}, text::{Text, Baseline}};
+
+/// Shapes queued during current frame
+#[cfg(feature = "debug-draw")]
+struct Queue {
+    /// Pairs of line ends in world space
+    world_lines: Vec<Vertex>,
+    /// Pairs of line ends in screen pixels
+    screen_lines: Vec<Vertex>,
+    /// Pairs of sprite corners in screen pixels (text pixels)
+    screen_sprites: Vec<Vertex>,
+}
+
+#[cfg(feature = "debug-draw")]
+static mut QUEUE: Queue = Queue { world_lines: Vec::new(), screen_lines: Vec::new(), screen_sprites: Vec::new() };
+
+/// Amount of segments used to approximate circles
+const CIRCLE_SEGMENTS: usize = 24;
+
+/// Queue line between 2 points in world space (drawn with view and projection matrices which are set at the end of frame)
+pub unsafe fn line(from: ScePspFVector3, to: ScePspFVector3, color: u32) {
+    #[cfg(feature = "debug-draw")]
+    {
+        (*addr_of_mut!(QUEUE)).world_lines.push(Vertex { u: 0.0, v: 0.0, color, x: from.x, y: from.y, z: from.z });
+        (*addr_of_mut!(QUEUE)).world_lines.push(Vertex { u: 0.0, v: 0.0, color, x: to.x, y: to.y, z: to.z });
+    }
+}
+
+/// Queue line between 2 points on screen (in pixels)
+pub unsafe fn line_2d(from: (f32, f32), to: (f32, f32), color: u32) {
+    #[cfg(feature = "debug-draw")]
+    {
+        (*addr_of_mut!(QUEUE)).screen_lines.push(Vertex { u: 0.0, v: 0.0, color, x: from.0, y: from.1, z: 0.0 });
+        (*addr_of_mut!(QUEUE)).screen_lines.push(Vertex { u: 0.0, v: 0.0, color, x: to.0, y: to.1, z: 0.0 });
+    }
+}
+
+/// Queue text label with top-left corner in specified screen position (6x10 pixels font, ASCII only)
+pub unsafe fn text(position: (f32, f32), text: &str, color: u32) {
+    #[cfg(feature = "debug-draw")]
+    {
+        let mut target = SpriteCollector { color, sprites: &mut (*addr_of_mut!(QUEUE)).screen_sprites };
+        let style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
+        let _ = Text::with_baseline(text, Point::new(position.0 as i32, position.1 as i32), style, Baseline::Top).draw(&mut target);
+    }
+}
+
+/// Queue outline of rectangle on screen
+pub unsafe fn rect_2d(x: f32, y: f32, width: f32, height: f32, color: u32) {
+    line_2d((x, y), (x + width, y), color);
+    line_2d((x + width, y), (x + width, y + height), color);
+    line_2d((x + width, y + height), (x, y + height), color);
+    line_2d((x, y + height), (x, y), color);
+}
+
+/// Queue outline of circle on screen
+pub unsafe fn circle_2d(center: (f32, f32), radius: f32, color: u32) {
+    let point = |i: usize| {
+        let angle = i as f32 * 2.0 * PI / CIRCLE_SEGMENTS as f32;
+        (center.0 + cosf(angle) * radius, center.1 + sinf(angle) * radius)
+    };
+
+    for i in 0..CIRCLE_SEGMENTS {
+        line_2d(point(i), point(i + 1), color);
+    }
+}
+
+/// Queue arrow on screen pointing from 'from' to 'to'
+pub unsafe fn arrow_2d(from: (f32, f32), to: (f32, f32), color: u32) {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = sqrtf(dx * dx + dy * dy);
+    line_2d(from, to, color);
+    if length <= 0.0 {
+        return;
+    }
+
+    // Head lines are 8 pixels long and lean 30 degrees from shaft
+    let (dx, dy) = (dx / length * 8.0, dy / length * 8.0);
+    let (sin, cos) = (0.5, 0.866);
+    line_2d(to, (to.0 - (dx * cos - dy * sin), to.1 - (dx * sin + dy * cos)), color);
+    line_2d(to, (to.0 - (dx * cos + dy * sin), to.1 - (-dx * sin + dy * cos)), color);
+}
+
+/// Queue edges of axis aligned box in world space
+pub unsafe fn aabb(min: ScePspFVector3, max: ScePspFVector3, color: u32) {
+    let corner = |i: u32| ScePspFVector3 {
+        x: if i & 1 != 0 { max.x } else { min.x },
+        y: if i & 2 != 0 { max.y } else { min.y },
+        z: if i & 4 != 0 { max.z } else { min.z },
+    };
+
+    // Corners differing by exactly one bit are connected by edge
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                line(corner(i), corner(i | bit), color);
+            }
+        }
+    }
+}
+
+/// Queue circle lying on horizontal (XZ) plane in world space
+pub unsafe fn circle(center: ScePspFVector3, radius: f32, color: u32) {
+    let point = |i: usize| {
+        let angle = i as f32 * 2.0 * PI / CIRCLE_SEGMENTS as f32;
+        ScePspFVector3 { x: center.x + cosf(angle) * radius, y: center.y, z: center.z + sinf(angle) * radius }
+    };
+
+    for i in 0..CIRCLE_SEGMENTS {
+        line(point(i), point(i + 1), color);
+    }
+}
+
+/// Queue arrow in world space. Head is drawn in plane containing arrow and vertical axis (or X axis for vertical arrows)
+pub unsafe fn arrow(from: ScePspFVector3, to: ScePspFVector3, color: u32) {
+    line(from, to, color);
+
+    let direction = sub(to, from);
+    let length = length(direction);
+    if length <= 0.0 {
+        return;
+    }
+
+    let direction = scale(direction, 1.0 / length);
+    let helper = if direction.y * direction.y > 0.9 { ScePspFVector3 { x: 1.0, y: 0.0, z: 0.0 } } else { ScePspFVector3 { x: 0.0, y: 1.0, z: 0.0 } };
+    let side = normalize(cross(direction, helper));
+
+    // Head is 20% of arrow length
+    let head = length * 0.2;
+    let base = add(to, scale(direction, -head));
+    line(to, add(base, scale(side, head * 0.5)), color);
+    line(to, add(base, scale(side, -head * 0.5)), color);
+}
+
+/// Queue square grid lying on horizontal (XZ) plane. 'size' = length of grid side, 'cells' = amount of cells in one row
+pub unsafe fn grid(center: ScePspFVector3, size: f32, cells: u32, color: u32) {
+    let half = size / 2.0;
+    let cells = cells.max(1);
+
+    for i in 0..=cells {
+        let offset = -half + size * i as f32 / cells as f32;
+
+        line(ScePspFVector3 { x: center.x + offset, y: center.y, z: center.z - half }, ScePspFVector3 { x: center.x + offset, y: center.y, z: center.z + half }, color);
+        line(ScePspFVector3 { x: center.x - half, y: center.y, z: center.z + offset }, ScePspFVector3 { x: center.x + half, y: center.y, z: center.z + offset }, color);
+    }
+}
+
+/// Queue axes gizmo: X axis is red, Y axis is green and Z axis is blue
+pub unsafe fn axes(origin: ScePspFVector3, length: f32) {
+    arrow(origin, add(origin, ScePspFVector3 { x: length, y: 0.0, z: 0.0 }), rgba(255, 0, 0, 255));
+    arrow(origin, add(origin, ScePspFVector3 { x: 0.0, y: length, z: 0.0 }), rgba(0, 255, 0, 255));
+    arrow(origin, add(origin, ScePspFVector3 { x: 0.0, y: 0.0, z: length }), rgba(0, 0, 255, 255));
+}
+
+/// Queue edges of camera view frustum (volume between near and far plane visible by camera)
+pub unsafe fn frustum(camera: &Camera, color: u32) {
+    let forward = normalize(sub(camera.target, camera.eye));
+    let right = normalize(cross(forward, camera.up));
+    let up = cross(right, forward);
+
+    // Half of vertical field of view converted from degrees
+    let tangent = tanf(camera.fovy * PI / 360.0);
+    let plane_corners = |distance: f32| {
+        let center = add(camera.eye, scale(forward, distance));
+        let (half_height, half_width) = (tangent * distance, tangent * distance * camera.aspect);
+        let corner = |sx: f32, sy: f32| add(center, add(scale(right, half_width * sx), scale(up, half_height * sy)));
+
+        [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)]
+    };
+
+    let near = plane_corners(camera.near);
+    let far = plane_corners(camera.far);
+    for i in 0..4 {
+        let next = (i + 1) % 4;
+        line(near[i], near[next], color);
+        line(far[i], far[next], color);
+        line(near[i], far[i], color);
+    }
+}
+
+/// Draw and clear everything what was queued during this frame. Called by 'GMng::end_existing_frame' before display list is finished
+pub(crate) unsafe fn flush() {
+    #[cfg(feature = "debug-draw")]
+    {
+        let queue = &mut *addr_of_mut!(QUEUE);
+        if queue.world_lines.is_empty() && queue.screen_lines.is_empty() && queue.screen_sprites.is_empty() {
+            return;
+        }
+
+        // Overlay is drawn without depth test, blending, texture and culling. Enabled states are restored afterwards, so other code doesn't notice any change
+        let status = sceGuGetAllStatus();
+        for state in [GuState::DepthTest, GuState::Blend, GuState::Texture2D, GuState::CullFace, GuState::AlphaTest, GuState::Lighting] {
+            sceGuDisable(state);
+        }
+
+        let vertex_type = VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF;
+        if !queue.world_lines.is_empty() {
+            // Lines are given in world space, so model matrix must not move them
+            sceGumMatrixMode(MatrixMode::Model);
+            sceGumPushMatrix();
+            sceGumLoadIdentity();
+            sceGumDrawArray(GuPrimitive::Lines, vertex_type | VertexType::TRANSFORM_3D, queue.world_lines.len() as i32, core::ptr::null(), display_list_copy(&queue.world_lines));
+            sceGumPopMatrix();
+        }
+        if !queue.screen_lines.is_empty() {
+            sceGuDrawArray(GuPrimitive::Lines, vertex_type | VertexType::TRANSFORM_2D, queue.screen_lines.len() as i32, core::ptr::null(), display_list_copy(&queue.screen_lines));
+        }
+        if !queue.screen_sprites.is_empty() {
+            sceGuDrawArray(GuPrimitive::Sprites, vertex_type | VertexType::TRANSFORM_2D, queue.screen_sprites.len() as i32, core::ptr::null(), display_list_copy(&queue.screen_sprites));
+        }
+
+        sceGuSetAllStatus(status);
+
+        // Memory of vectors is kept for next frames
+        queue.world_lines.clear();
+        queue.screen_lines.clear();
+        queue.screen_sprites.clear();
+    }
+}
+
+/// Copy vertices into display list memory (which lives until frame is drawn and doesn't need cache flushing)
+#[cfg(feature = "debug-draw")]
+unsafe fn display_list_copy(vertices: &[Vertex]) -> *const c_void {
+    let memory = sceGuGetMemory(core::mem::size_of_val(vertices) as i32) as *mut Vertex;
+    core::ptr::copy_nonoverlapping(vertices.as_ptr(), memory, vertices.len());
+
+    memory as *const c_void
+}
+
+/// 'embedded-graphics' target which turns every drawn pixel into 1x1 sprite
+#[cfg(feature = "debug-draw")]
+struct SpriteCollector<'a> {
+    color: u32,
+    sprites: &'a mut Vec<Vertex>,
+}
+
+#[cfg(feature = "debug-draw")]
+impl OriginDimensions for SpriteCollector<'_> {
+    fn size(&self) -> Size {
+        Size::new(crate::graphics::PSP_SCR_WIDTH as u32, crate::graphics::PSP_SCR_HEIGHT as u32)
+    }
+}
+
+#[cfg(feature = "debug-draw")]
+impl DrawTarget for SpriteCollector<'_> {
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // Text is drawn only with foreground color, so color of pixel isn't needed
+        for Pixel(point, _) in pixels {
+            let (x, y) = (point.x as f32, point.y as f32);
+            self.sprites.push(Vertex { u: 0.0, v: 0.0, color: self.color, x, y, z: 0.0 });
+            self.sprites.push(Vertex { u: 0.0, v: 0.0, color: self.color, x: x + 1.0, y: y + 1.0, z: 0.0 });
+        }
+
+        Ok(())
+    }
+}
+
+fn add(a: ScePspFVector3, b: ScePspFVector3) -> ScePspFVector3 {
+    ScePspFVector3 { x: a.x + b.x, y: a.y + b.y, z: a.z + b.z }
+}
+
+fn sub(a: ScePspFVector3, b: ScePspFVector3) -> ScePspFVector3 {
+    ScePspFVector3 { x: a.x - b.x, y: a.y - b.y, z: a.z - b.z }
+}
+
+fn scale(a: ScePspFVector3, factor: f32) -> ScePspFVector3 {
+    ScePspFVector3 { x: a.x * factor, y: a.y * factor, z: a.z * factor }
+}
+
+fn cross(a: ScePspFVector3, b: ScePspFVector3) -> ScePspFVector3 {
+    ScePspFVector3 { x: a.y * b.z - a.z * b.y, y: a.z * b.x - a.x * b.z, z: a.x * b.y - a.y * b.x }
+}
+
+fn length(a: ScePspFVector3) -> f32 {
+    sqrtf(a.x * a.x + a.y * a.y + a.z * a.z)
+}
+
+fn normalize(a: ScePspFVector3) -> ScePspFVector3 {
+    let length = length(a);
+    if length > 0.0 { scale(a, 1.0 / length) } else { a }
+}
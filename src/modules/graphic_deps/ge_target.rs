@@ -0,0 +1,165 @@
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use psp::sys::*;
+use embedded_graphics::{prelude::*, pixelcolor::Rgb888, primitives::{Rectangle, Triangle}};
+use crate::examples::types_def::Vertex;
+use crate::graphics::{PSP_SCR_WIDTH, PSP_SCR_HEIGHT};
+use crate::render_state::{RenderState, StateCache};
+
+/// Which primitive is collected in current batch
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Batch {
+    Sprites,
+    Triangles,
+}
+
+/// 'embedded-graphics' draw target which sends everything to GE instead of writing pixels by CPU (sharper and much faster then 'Framebuffer').
+/// Areas and pixel runs become sprites. 'embedded-graphics' rasterizes styled primitives itself, so 'Triangle' drawn with '.draw()' arrives as one sprite
+/// per scanline, only 'fill_triangle' produces real GE triangle. Primitives are collected into batches and drawn when primitive kind changes,
+/// on 'flush' and when target is dropped. Must be used inside started frame, so it can be mixed with native 'sceGu' draws
+pub struct GeDrawTarget<'a> {
+    states: &'a mut StateCache,
+    /// Collected vertices (2 per sprite or 3 per triangle)
+    vertices: Vec<Vertex>,
+    batch: Batch,
+}
+
+impl<'a> GeDrawTarget<'a> {
+    /// # Safety
+    /// Must be created after 'GMng::start_new_frame' and dropped before 'GMng::end_existing_frame', because collected primitives are added to display list of started frame
+    pub unsafe fn new(states: &'a mut StateCache) -> Self {
+        Self { states, vertices: Vec::new(), batch: Batch::Sprites }
+    }
+
+    /// Draw everything collected so far. Must be called before native draws which should appear on top of 'embedded-graphics' ones
+    pub unsafe fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        self.states.apply(&RenderState::OVERLAY);
+
+        // Vertices are copied to display list memory which lives until frame is drawn
+        let memory = sceGuGetMemory(core::mem::size_of_val(self.vertices.as_slice()) as i32) as *mut Vertex;
+        core::ptr::copy_nonoverlapping(self.vertices.as_ptr(), memory, self.vertices.len());
+
+        let primitive = match self.batch {
+            Batch::Sprites => GuPrimitive::Sprites,
+            Batch::Triangles => GuPrimitive::Triangles,
+        };
+        sceGuDrawArray(primitive, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, self.vertices.len() as i32, core::ptr::null(), memory as *const c_void);
+
+        self.vertices.clear();
+    }
+
+    /// Fill triangle with one GE triangle (styled 'Triangle' drawn by 'embedded-graphics' becomes sprite per scanline instead)
+    pub fn fill_triangle(&mut self, triangle: &Triangle, color: Rgb888) {
+        self.switch_batch(Batch::Triangles);
+
+        let color = to_rgba(color);
+        for point in triangle.vertices {
+            self.vertices.push(Vertex { u: 0.0, v: 0.0, color, x: point.x as f32, y: point.y as f32, z: 0.0 });
+        }
+    }
+
+    /// Flush collected primitives when they are of other kind then next one
+    fn switch_batch(&mut self, batch: Batch) {
+        if self.batch != batch {
+            unsafe { self.flush() };
+            self.batch = batch;
+        }
+    }
+
+    /// Add sprite covering 'width' x 'height' pixels
+    fn push_sprite(&mut self, x: i32, y: i32, width: u32, height: u32, color: u32) {
+        self.switch_batch(Batch::Sprites);
+
+        let (x, y) = (x as f32, y as f32);
+        self.vertices.push(Vertex { u: 0.0, v: 0.0, color, x, y, z: 0.0 });
+        self.vertices.push(Vertex { u: 0.0, v: 0.0, color, x: x + width as f32, y: y + height as f32, z: 0.0 });
+    }
+
+    /// Try to make the last sprite 1 pixel wider instead of adding new one (works for pixels drawn left to right in the same row with the same color)
+    fn extend_last_sprite(&mut self, point: Point, color: u32) -> bool {
+        if self.batch != Batch::Sprites || self.vertices.len() < 2 {
+            return false;
+        }
+
+        let end = self.vertices.len() - 1;
+        let start_y = self.vertices[end - 1].y;
+        let last = &mut self.vertices[end];
+        let in_row = start_y == point.y as f32 && last.y == point.y as f32 + 1.0;
+
+        if in_row && last.x == point.x as f32 && last.color == color {
+            last.x += 1.0;
+            return true;
+        }
+
+        false
+    }
+}
+
+impl Drop for GeDrawTarget<'_> {
+    fn drop(&mut self) {
+        unsafe { self.flush() };
+    }
+}
+
+impl OriginDimensions for GeDrawTarget<'_> {
+    fn size(&self) -> Size {
+        Size::new(PSP_SCR_WIDTH as u32, PSP_SCR_HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for GeDrawTarget<'_> {
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // Neighbouring pixels with the same color are joined into one sprite
+        for Pixel(point, color) in pixels {
+            let color = to_rgba(color);
+
+            if !self.extend_last_sprite(point, color) {
+                self.push_sprite(point.x, point.y, 1, 1, color);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.draw_iter(area.points().zip(colors).map(|(point, color)| Pixel(point, color)))
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if area.size.width > 0 && area.size.height > 0 {
+            self.push_sprite(area.top_left.x, area.top_left.y, area.size.width, area.size.height, to_rgba(color));
+        }
+
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        // Everything collected before would be covered anyway
+        self.vertices.clear();
+
+        unsafe {
+            sceGuClearColor(to_rgba(color));
+            sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert 'embedded-graphics' color into form created by 'rgba' function
+fn to_rgba(color: Rgb888) -> u32 {
+    rgba(color.r(), color.g(), color.b(), 255)
+}
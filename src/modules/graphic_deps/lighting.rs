@@ -0,0 +1,195 @@
+use alloc::{vec, vec::Vec, collections::BTreeMap};
+use psp::sys::*;
+use psp::math::sqrtf;
+use crate::examples::types_def::NormalVertex;
+
+/// GE can compute only 4 lights at once
+pub const MAX_LIGHTS: usize = 4;
+
+/// Kind of light with properties specific for it. All colors are in 'rgba' format (alpha is ignored by GE)
+#[derive(Clone, Copy)]
+pub enum Light {
+    /// Light coming from infinitely far source (like sun). 'direction' points from scene to the light
+    Directional { direction: ScePspFVector3, diffuse: u32, specular: u32 },
+    /// Light shining in all directions from one point (like bulb). Attenuation is (constant, linear, quadratic) factor
+    Point { position: ScePspFVector3, diffuse: u32, specular: u32, attenuation: (f32, f32, f32) },
+    /// Light shining in a cone (like flashlight). 'cutoff' is cosine of cone half-angle, 'exponent' makes cone edge softer
+    Spot { position: ScePspFVector3, direction: ScePspFVector3, diffuse: u32, specular: u32, attenuation: (f32, f32, f32), exponent: f32, cutoff: f32 },
+}
+
+impl Light {
+    /// Send light settings to GE under specified index (0..MAX_LIGHTS)
+    unsafe fn apply(&self, index: i32) {
+        let components = LightComponent::DIFFUSE | LightComponent::SPECULAR;
+
+        match *self {
+            Light::Directional { direction, diffuse, specular } => {
+                sceGuLight(index, LightType::Directional, components, &direction);
+                sceGuLightColor(index, LightComponent::DIFFUSE, diffuse);
+                sceGuLightColor(index, LightComponent::SPECULAR, specular);
+            },
+            Light::Point { position, diffuse, specular, attenuation: (constant, linear, quadratic) } => {
+                sceGuLight(index, LightType::Pointlight, components, &position);
+                sceGuLightColor(index, LightComponent::DIFFUSE, diffuse);
+                sceGuLightColor(index, LightComponent::SPECULAR, specular);
+                sceGuLightAtt(index, constant, linear, quadratic);
+            },
+            Light::Spot { position, direction, diffuse, specular, attenuation: (constant, linear, quadratic), exponent, cutoff } => {
+                sceGuLight(index, LightType::Spotlight, components, &position);
+                sceGuLightColor(index, LightComponent::DIFFUSE, diffuse);
+                sceGuLightColor(index, LightComponent::SPECULAR, specular);
+                sceGuLightAtt(index, constant, linear, quadratic);
+                sceGuLightSpot(index, &direction, exponent, cutoff);
+            },
+        }
+    }
+}
+
+/// Set of lights which illuminates the scene
+pub struct Lighting {
+    /// Color of light which reaches every surface from every direction
+    pub ambient: u32,
+    /// Lights assigned to GE light slots (None = slot is turned off)
+    pub lights: [Option<Light>; MAX_LIGHTS],
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            ambient: rgba(40, 40, 40, 255),
+            lights: [None; MAX_LIGHTS],
+        }
+    }
+}
+
+impl Lighting {
+    /// Enable lighting and send all lights to GE. Must be called inside started frame, after camera is applied (lights positions are in world space)
+    pub unsafe fn apply(&self) {
+        sceGuEnable(GuState::Lighting);
+        // Specular highlights are added after texture color to not be darkened by texture
+        sceGuLightMode(LightMode::SeparateSpecularColor);
+        sceGuAmbient(self.ambient);
+
+        for (index, light) in self.lights.iter().enumerate() {
+            let state = light_state(index);
+
+            match light {
+                Some(light) => {
+                    sceGuEnable(state);
+                    light.apply(index as i32);
+                },
+                None => sceGuDisable(state)
+            }
+        }
+    }
+
+    /// Turn off lighting (next drawn primitives use only their colors)
+    pub unsafe fn disable() {
+        sceGuDisable(GuState::Lighting);
+    }
+}
+
+/// GE state which switches light under specified index
+fn light_state(index: usize) -> GuState {
+    match index {
+        0 => GuState::Light0,
+        1 => GuState::Light1,
+        2 => GuState::Light2,
+        _ => GuState::Light3,
+    }
+}
+
+/// How surface reacts on light
+pub struct Material {
+    /// Color of light scattered by surface
+    pub diffuse: u32,
+    /// Color of shiny highlights
+    pub specular: u32,
+    /// Color which surface emits by itself (visible even without any light)
+    pub emissive: u32,
+    /// Color reflected from ambient light
+    pub ambient: u32,
+    /// Greater value = smaller and sharper highlights
+    pub shininess: f32,
+    /// Take ambient and diffuse colors from vertices instead of this material (useful when meshes have got colors from '.mtl' files)
+    pub vertex_color: bool,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            diffuse: rgba(255, 255, 255, 255),
+            specular: rgba(0, 0, 0, 255),
+            emissive: rgba(0, 0, 0, 255),
+            ambient: rgba(255, 255, 255, 255),
+            shininess: 12.0,
+            vertex_color: true,
+        }
+    }
+}
+
+impl Material {
+    /// Set material for next drawn primitives
+    pub unsafe fn apply(&self) {
+        sceGuModelColor(self.emissive, self.ambient, self.diffuse, self.specular);
+        sceGuSpecular(self.shininess);
+
+        if self.vertex_color {
+            sceGuColorMaterial(LightComponent::AMBIENT | LightComponent::DIFFUSE);
+        } else {
+            sceGuColorMaterial(LightComponent::empty());
+        }
+    }
+}
+
+/// Compute smooth normals for mesh (each normal is average of normals of all triangles which share vertex position).
+/// Vertices which are placed in the same position (e.g. duplicated for different texture coordinates) get the same normal
+/// 'indices' = None means that every 3 next vertices create one triangle
+pub fn compute_smooth_normals(vertices: &mut [NormalVertex], indices: Option<&[u16]>) {
+    // Group vertices by exact position so normals are smooth also across texture seams
+    let mut groups: BTreeMap<[u32; 3], usize> = BTreeMap::new();
+    let group_of: Vec<usize> = vertices.iter()
+        .map(|v| {
+            let next = groups.len();
+            // Adding 0.0 turns '-0.0' into '0.0' so both are treated as the same position
+            *groups.entry([(v.x + 0.0).to_bits(), (v.y + 0.0).to_bits(), (v.z + 0.0).to_bits()]).or_insert(next)
+        })
+        .collect();
+    let mut sums = vec![[0f32; 3]; groups.len()];
+
+    let triangles: Vec<[usize; 3]> = match indices {
+        Some(indices) => indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize]).collect(),
+        None => (0..vertices.len() / 3).map(|t| [t * 3, t * 3 + 1, t * 3 + 2]).collect(),
+    };
+
+    for [ia, ib, ic] in triangles {
+        let (a, b, c) = (&vertices[ia], &vertices[ib], &vertices[ic]);
+        let ab = [b.x - a.x, b.y - a.y, b.z - a.z];
+        let ac = [c.x - a.x, c.y - a.y, c.z - a.z];
+
+        // Clockwise front faces (see 'init_graphic') -> 'ac x ab' points outside of model
+        // Normal isn't normalized so bigger triangles have got bigger impact on final normal
+        let face = [
+            ac[1] * ab[2] - ac[2] * ab[1],
+            ac[2] * ab[0] - ac[0] * ab[2],
+            ac[0] * ab[1] - ac[1] * ab[0],
+        ];
+
+        for index in [ia, ib, ic] {
+            for (sum, value) in sums[group_of[index]].iter_mut().zip(face) {
+                *sum += value;
+            }
+        }
+    }
+
+    for (vertex, group) in vertices.iter_mut().zip(group_of) {
+        let [nx, ny, nz] = sums[group];
+        let length = sqrtf(nx * nx + ny * ny + nz * nz);
+
+        if length > 0.0 {
+            vertex.nx = nx / length;
+            vertex.ny = ny / length;
+            vertex.nz = nz / length;
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use alloc::{vec::Vec, string::{String, ToString}, collections::BTreeMap};
+use core::str::SplitWhitespace;
+use psp::sys::{rgba, TexturePixelFormat};
+use embedded_graphics::{prelude::*, pixelcolor::Rgb888};
+use crate::examples::types_def::NormalVertex;
+use crate::texture_canvas::TextureCanvas;
+use crate::lighting;
+use crate::file_system;
+
+/// Things which can go wrong while loading '.obj' or '.mtl' file
+#[derive(Debug)]
+pub enum ObjError {
+    /// File couldn't be read (contains error code returned by 'sceIo' function)
+    Io(i32),
+    /// File isn't valid UTF-8 text
+    NotText,
+    /// Line has got wrong amount of values or value isn't a number
+    Parse { line: usize },
+    /// Face points to 'v'/'vt'/'vn' element which doesn't exist
+    IndexOutOfRange { line: usize },
+    /// Mesh has got more unique vertices then 16-bit index can point to
+    TooManyVertices,
+    /// Texture from 'map_Kd' isn't uncompressed 24/32-bit BMP with power of 2 size up to 512 (contains file name)
+    UnsupportedTexture(String),
+}
+
+/// Material readed from '.mtl' file
+#[derive(Debug, Clone)]
+pub struct Material {
+    /// Name used by 'usemtl' statement
+    pub name: String,
+    /// Diffuse color ('Kd' and 'd' statements) ready to be put inside 'NormalVertex'
+    pub diffuse: u32,
+    /// Path to diffuse texture ('map_Kd' statement) relative to 'files/' directory
+    pub texture: Option<String>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            diffuse: rgba(255, 255, 255, 255),
+            texture: None,
+        }
+    }
+}
+
+/// Part of model which uses one material. Ready to be drawn by 'sceGumDrawArray' with 'INDEX_16BIT' and 'NORMAL_32BITF' vertex type
+pub struct Mesh {
+    pub vertices: Vec<NormalVertex>,
+    pub indices: Vec<u16>,
+    /// Index of material from 'Model::materials' (None when mesh was defined without 'usemtl')
+    pub material: Option<usize>,
+}
+
+/// Whole model loaded from '.obj' file
+pub struct Model {
+    pub meshes: Vec<Mesh>,
+    pub materials: Vec<Material>,
+    /// Loaded diffuse textures, one for each material (None for material without 'map_Kd')
+    pub textures: Vec<Option<TextureCanvas>>,
+}
+
+impl Model {
+    /// Diffuse texture of mesh material (if any)
+    pub fn texture(&self, mesh: &Mesh) -> Option<&TextureCanvas> {
+        mesh.material.and_then(|material| self.textures.get(material)).and_then(|texture| texture.as_ref())
+    }
+}
+
+/// Directory where model assets are stored
+static ASSETS_DIR: &str = "./files/";
+
+/// Load '.obj' file (and '.mtl' files mentioned by it) from 'files/' directory. Example: 'load_obj("cube.obj")'
+pub unsafe fn load_obj(name: &str) -> Result<Model, ObjError> {
+    let obj = read_text(name)?;
+
+    // Materials must be known before faces are converted because faces take colors from them
+    let mut materials = Vec::new();
+    for line in obj.lines() {
+        let mut parts = line.split_whitespace();
+        if parts.next() == Some("mtllib") {
+            for mtl_name in parts {
+                materials.extend(parse_mtl(&read_text(mtl_name)?)?);
+            }
+        }
+    }
+
+    let mut model = parse_obj(&obj, materials)?;
+    for (material, texture) in model.materials.iter().zip(model.textures.iter_mut()) {
+        if let Some(file) = &material.texture {
+            *texture = Some(load_texture(file)?);
+        }
+    }
+
+    Ok(model)
+}
+
+/// Load diffuse texture from assets directory. Only uncompressed BMP files are supported ('.png'/'.jpg' can be converted to it)
+unsafe fn load_texture(name: &str) -> Result<TextureCanvas, ObjError> {
+    let mut path = String::from(ASSETS_DIR);
+    path.push_str(name);
+
+    let bytes = file_system::read_file(&path).map_err(ObjError::Io)?;
+    parse_bmp(&bytes).ok_or_else(|| ObjError::UnsupportedTexture(name.to_string()))
+}
+
+/// Decode uncompressed 24 or 32-bit BMP into texture. None for other formats or sizes which GE can't use
+pub fn parse_bmp(bytes: &[u8]) -> Option<TextureCanvas> {
+    let u16_at = |at: usize| Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?));
+    let u32_at = |at: usize| Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?));
+
+    if bytes.get(0..2)? != b"BM" {
+        return None;
+    }
+    let offset = u32_at(10)? as usize;
+    let (width, height) = (u32_at(18)? as i32, u32_at(22)? as i32);
+    let (bits, compression) = (u16_at(28)?, u32_at(30)?);
+    // 3 = bit fields, used by 32-bit files with standard BGRA masks
+    if (bits != 24 && bits != 32) || (compression != 0 && compression != 3) || width <= 0 || height == 0 {
+        return None;
+    }
+
+    let mut canvas = TextureCanvas::new(u16::try_from(width).ok()?, u16::try_from(height.unsigned_abs()).ok()?, TexturePixelFormat::Psm8888)?;
+    let bytes_per_pixel = bits as usize / 8;
+    // Rows are aligned to 4 bytes, positive height means that the first row is the bottom one
+    let row_size = width as usize * bytes_per_pixel;
+    let stride = (row_size + 3) & !3;
+
+    for row in 0..height.unsigned_abs() as usize {
+        // Offset comes from file, so row position is checked instead of wrapping around on 32-bit PSP
+        let start = row.checked_mul(stride)?.checked_add(offset)?;
+        let pixels = bytes.get(start..start.checked_add(row_size)?)?;
+        let y = if height > 0 { height as usize - 1 - row } else { row };
+
+        let row_pixels = pixels.chunks_exact(bytes_per_pixel).enumerate().map(|(x, bgr)| {
+            Pixel(Point::new(x as i32, y as i32), Rgb888::new(bgr[2], bgr[1], bgr[0]))
+        });
+        canvas.draw_iter(row_pixels).ok()?;
+    }
+
+    Some(canvas)
+}
+
+/// Read text file from assets directory
+unsafe fn read_text(name: &str) -> Result<String, ObjError> {
+    let mut path = String::from(ASSETS_DIR);
+    path.push_str(name);
+
+    let bytes = file_system::read_file(&path).map_err(ObjError::Io)?;
+    String::from_utf8(bytes).map_err(|_| ObjError::NotText)
+}
+
+/// Parse content of '.mtl' file. Only diffuse color, transparency and diffuse texture are used, rest of statements are skipped
+pub fn parse_mtl(src: &str) -> Result<Vec<Material>, ObjError> {
+    let mut materials: Vec<Material> = Vec::new();
+    // Diffuse color stored as floats until material is finished (alpha can be defined after color)
+    let mut color = [1.0f32; 4];
+
+    for (number, line) in src.lines().enumerate() {
+        let line_nr = number + 1;
+        let mut parts = line.split_whitespace();
+
+        match (parts.next(), materials.last_mut()) {
+            (Some("newmtl"), last) => {
+                if let Some(last) = last {
+                    last.diffuse = float_color(color);
+                }
+                color = [1.0; 4];
+
+                let name = parts.next().ok_or(ObjError::Parse { line: line_nr })?;
+                materials.push(Material { name: name.to_string(), ..Material::default() });
+            },
+            (Some("Kd"), Some(_)) => {
+                let [r, g, b] = parse_floats::<3>(&mut parts, line_nr)?;
+                color = [r, g, b, color[3]];
+            },
+            (Some("d"), Some(_)) => color[3] = parse_floats::<1>(&mut parts, line_nr)?[0],
+            // 'Tr' is inverted 'd' statement used by some exporters
+            (Some("Tr"), Some(_)) => color[3] = 1.0 - parse_floats::<1>(&mut parts, line_nr)?[0],
+            (Some("map_Kd"), Some(last)) => {
+                // Options like '-s 1 1 1' can be placed before file name so file name is always last part
+                let file = parts.last().ok_or(ObjError::Parse { line: line_nr })?;
+                last.texture = Some(file.to_string());
+            },
+            _ => ()
+        }
+    }
+
+    if let Some(last) = materials.last_mut() {
+        last.diffuse = float_color(color);
+    }
+
+    Ok(materials)
+}
+
+/// Parse content of '.obj' file. Every 'usemtl' statement starts new mesh, faces with more then 3 corners are splited into triangles fan
+pub fn parse_obj(src: &str, materials: Vec<Material>) -> Result<Model, ObjError> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut tex_coords: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+
+    let mut meshes: Vec<Mesh> = Vec::new();
+    let mut builder = MeshBuilder::new(None, &materials);
+
+    for (number, line) in src.lines().enumerate() {
+        let line_nr = number + 1;
+        let mut parts = line.split_whitespace();
+
+        match parts.next() {
+            Some("v") => positions.push(parse_floats::<3>(&mut parts, line_nr)?),
+            Some("vt") => {
+                // Third texture coordinate is optional and useless for 2D textures
+                let [u, v] = parse_floats::<2>(&mut parts, line_nr)?;
+                tex_coords.push([u, v]);
+            },
+            Some("vn") => normals.push(parse_floats::<3>(&mut parts, line_nr)?),
+            Some("usemtl") => {
+                let name = parts.next().ok_or(ObjError::Parse { line: line_nr })?;
+                let material = materials.iter().position(|m| m.name == name);
+
+                let finished = core::mem::replace(&mut builder, MeshBuilder::new(material, &materials));
+                finished.finish(&mut meshes);
+            },
+            Some("f") => {
+                // Resolve every corner of face to final vertex index
+                let mut corners: Vec<u16> = Vec::new();
+                for corner in parts {
+                    let key = parse_corner(corner, positions.len(), tex_coords.len(), normals.len(), line_nr)?;
+                    corners.push(builder.vertex(key, &positions, &tex_coords, &normals)?);
+                }
+
+                if corners.len() < 3 {
+                    return Err(ObjError::Parse { line: line_nr });
+                }
+
+                // Triangle fan: (0, 2, 1), (0, 3, 2), ... -> '.obj' front faces are counter-clockwise while 'init_graphic' treats clockwise ones as front faces
+                for i in 1..corners.len() - 1 {
+                    builder.indices.extend_from_slice(&[corners[0], corners[i + 1], corners[i]]);
+                }
+            },
+            _ => ()
+        }
+    }
+
+    builder.finish(&mut meshes);
+
+    // Textures are loaded by 'load_obj', because parsing doesn't read files
+    let textures = materials.iter().map(|_| None).collect();
+    Ok(Model { meshes, materials, textures })
+}
+
+/// Collects unique vertices for one mesh ('v/vt/vn' triples used more then once are stored only once)
+struct MeshBuilder {
+    material: Option<usize>,
+    color: u32,
+    vertices: Vec<NormalVertex>,
+    indices: Vec<u16>,
+    unique: BTreeMap<(usize, Option<usize>, Option<usize>), u16>,
+    /// Some face corner hasn't got 'vn' element so normals must be computed for vertices without it
+    missing_normals: bool,
+}
+
+impl MeshBuilder {
+    fn new(material: Option<usize>, materials: &[Material]) -> Self {
+        Self {
+            material,
+            color: material.map(|i| materials[i].diffuse).unwrap_or(Material::default().diffuse),
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            unique: BTreeMap::new(),
+            missing_normals: false,
+        }
+    }
+
+    /// Get index of vertex described by key, creating vertex when it is seen first time
+    fn vertex(&mut self, key: (usize, Option<usize>, Option<usize>), positions: &[[f32; 3]], tex_coords: &[[f32; 2]], normals: &[[f32; 3]]) -> Result<u16, ObjError> {
+        if let Some(index) = self.unique.get(&key) {
+            return Ok(*index);
+        }
+
+        let index = u16::try_from(self.vertices.len()).map_err(|_| ObjError::TooManyVertices)?;
+        let [x, y, z] = positions[key.0];
+        // '.obj' texture 'v' coordinate starts at the bottom of image while GE starts at the top
+        let [u, v] = key.1.map(|i| tex_coords[i]).map(|[u, v]| [u, 1.0 - v]).unwrap_or([0.0, 0.0]);
+
+        let [nx, ny, nz] = match key.2 {
+            Some(i) => normals[i],
+            None => {
+                self.missing_normals = true;
+                [0.0, 0.0, 0.0]
+            }
+        };
+
+        self.vertices.push(NormalVertex { u, v, color: self.color, nx, ny, nz, x, y, z });
+        self.unique.insert(key, index);
+
+        Ok(index)
+    }
+
+    /// Store mesh in list (empty meshes are skipped)
+    fn finish(mut self, meshes: &mut Vec<Mesh>) {
+        if self.missing_normals {
+            // Computing overwrites every normal, so normals given by 'vn' elements are put back afterwards and only missing ones are generated
+            let explicit: Vec<(usize, [f32; 3])> = self.unique.iter()
+                .filter(|(key, _)| key.2.is_some())
+                .map(|(_, index)| {
+                    let vertex = &self.vertices[*index as usize];
+                    (*index as usize, [vertex.nx, vertex.ny, vertex.nz])
+                })
+                .collect();
+
+            lighting::compute_smooth_normals(&mut self.vertices, Some(&self.indices));
+
+            for (index, [nx, ny, nz]) in explicit {
+                let vertex = &mut self.vertices[index];
+                (vertex.nx, vertex.ny, vertex.nz) = (nx, ny, nz);
+            }
+        }
+
+        if !self.indices.is_empty() {
+            meshes.push(Mesh { vertices: self.vertices, indices: self.indices, material: self.material });
+        }
+    }
+}
+
+/// Parse one face corner in one of forms: 'v', 'v/vt', 'v//vn', 'v/vt/vn'. Returns 0-based indices
+fn parse_corner(corner: &str, v_count: usize, vt_count: usize, vn_count: usize, line: usize) -> Result<(usize, Option<usize>, Option<usize>), ObjError> {
+    let mut parts = corner.split('/');
+
+    let v = parts.next().ok_or(ObjError::Parse { line })?;
+    let v = resolve_index(v, v_count, line)?;
+    let vt = match parts.next() {
+        Some(vt) if !vt.is_empty() => Some(resolve_index(vt, vt_count, line)?),
+        _ => None
+    };
+    let vn = match parts.next() {
+        Some(vn) if !vn.is_empty() => Some(resolve_index(vn, vn_count, line)?),
+        _ => None
+    };
+
+    Ok((v, vt, vn))
+}
+
+/// Convert 1-based (or negative = relative to the end) '.obj' index to 0-based index
+fn resolve_index(index: &str, count: usize, line: usize) -> Result<usize, ObjError> {
+    let index: i64 = index.parse().map_err(|_| ObjError::Parse { line })?;
+
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::IndexOutOfRange { line });
+    }
+
+    Ok(resolved as usize)
+}
+
+/// Parse exactly 'N' first floats from line parts
+fn parse_floats<const N: usize>(parts: &mut SplitWhitespace, line: usize) -> Result<[f32; N], ObjError> {
+    let mut values = [0.0; N];
+
+    for value in values.iter_mut() {
+        *value = parts.next()
+            .and_then(|part| part.parse().ok())
+            .ok_or(ObjError::Parse { line })?;
+    }
+
+    Ok(values)
+}
+
+/// Convert color with channels in 0.0..=1.0 range to color used by GE
+fn float_color(color: [f32; 4]) -> u32 {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0) as u8;
+
+    rgba(channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3]))
+}
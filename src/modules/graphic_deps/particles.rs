@@ -0,0 +1,261 @@
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::f32::consts::PI;
+use psp::sys::*;
+use psp::math::{sinf, cosf};
+use crate::examples::types_def::Vertex;
+use crate::render_state::{BlendMode, CullMode, RenderState, StateCache};
+
+/// Area where new particles are born. Particles are 2D (screen pixels) so every shape lies on the screen plane
+#[derive(Clone, Copy)]
+pub enum EmitterShape {
+    /// All particles start in emitter position
+    Point,
+    /// Random place inside rectangle centered on emitter position
+    Box { half_width: f32, half_height: f32 },
+    /// Random place inside circle centered on emitter position
+    Circle { radius: f32 },
+}
+
+/// Value which changes during particle life. Keys are (life fraction in 0.0..=1.0, value) sorted by life fraction, values between keys are interpolated linearly.
+/// Curve without keys gives default value (0 size, transparent black color)
+#[derive(Clone, Copy)]
+pub struct Curve<T: 'static> {
+    pub keys: &'static [(f32, T)],
+}
+
+impl<T: Lerp + Copy + Default> Curve<T> {
+    /// Value of curve for specified life fraction
+    pub fn sample(&self, t: f32) -> T {
+        let keys = self.keys;
+
+        for pair in keys.windows(2) {
+            let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+
+            if t <= t1 {
+                let local = if t1 > t0 { ((t - t0) / (t1 - t0)).clamp(0.0, 1.0) } else { 1.0 };
+                return v0.lerp(v1, local);
+            }
+        }
+
+        // After the last key (or curve with only 1 key) value stays constant
+        keys.last().map_or_else(T::default, |(_, value)| *value)
+    }
+}
+
+/// Linear interpolation between 2 values
+pub trait Lerp {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for u32 {
+    /// Interpolate each channel of color created by 'rgba' function
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let mut result = 0;
+
+        for shift in [0, 8, 16, 24] {
+            let a = ((self >> shift) & 0xff) as f32;
+            let b = ((other >> shift) & 0xff) as f32;
+            result |= ((a + (b - a) * t) as u32 & 0xff) << shift;
+        }
+
+        result
+    }
+}
+
+/// How emitter creates particles and how particles behave
+#[derive(Clone, Copy)]
+pub struct EmitterConfig {
+    pub shape: EmitterShape,
+    /// New particles per second (0 = particles are created only by 'burst')
+    pub rate: f32,
+    /// Random particle lifetime in seconds (min, max)
+    pub lifetime: (f32, f32),
+    /// Random start speed in pixels per second (min, max)
+    pub speed: (f32, f32),
+    /// Middle direction of start velocity in radians (0 = right, PI / 2 = down because screen 'y' axis goes down)
+    pub direction: f32,
+    /// Random deviation from 'direction' in radians (PI = all directions)
+    pub spread: f32,
+    /// Acceleration in pixels per second^2 (x, y)
+    pub gravity: (f32, f32),
+    /// Color over life
+    pub color: Curve<u32>,
+    /// Size (sprite side in pixels) over life
+    pub size: Curve<f32>,
+    /// Usually 'Additive' for lights (sparks, fire) and 'Alpha' for matter (smoke, snow)
+    pub blend: BlendMode,
+}
+
+/// One particle in pool
+#[derive(Clone, Copy, Default)]
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+/// Emitter together with pool of its particles. Pool is allocated once, when it is full new particles aren't created
+pub struct ParticleSystem {
+    pub config: EmitterConfig,
+    /// Emitter position in screen pixels
+    pub position: (f32, f32),
+    /// Alive particles (dead ones are swapped with the last one and removed, so memory is never reallocated)
+    particles: Vec<Particle>,
+    /// Part of particle which should be spawned but wasn't yet (rate * delta is rarely an integer)
+    spawn_debt: f32,
+    rng: Rng,
+}
+
+impl ParticleSystem {
+    /// Create system which can hold up to 'capacity' particles at once. Systems with different 'seed' produce different random patterns
+    /// (e.g. 'time::current_tick' can be used to make them different in every run)
+    pub fn new(config: EmitterConfig, position: (f32, f32), capacity: usize, seed: u32) -> Self {
+        Self {
+            config,
+            position,
+            particles: Vec::with_capacity(capacity),
+            spawn_debt: 0.0,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Amount of alive particles
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    /// Spawn 'count' particles at once (explosions, hits)
+    pub unsafe fn burst(&mut self, count: usize) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    /// Move particles by 'delta' seconds (from 'time::DeltaTimer'), remove dead ones and spawn new ones
+    pub unsafe fn update(&mut self, delta: f32) {
+        let (gx, gy) = self.config.gravity;
+
+        let mut i = 0;
+        while i < self.particles.len() {
+            let particle = &mut self.particles[i];
+            particle.age += delta;
+
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(i);
+                continue;
+            }
+
+            particle.vx += gx * delta;
+            particle.vy += gy * delta;
+            particle.x += particle.vx * delta;
+            particle.y += particle.vy * delta;
+            i += 1;
+        }
+
+        self.spawn_debt += self.config.rate * delta;
+        while self.spawn_debt >= 1.0 {
+            self.spawn();
+            self.spawn_debt -= 1.0;
+        }
+    }
+
+    /// Create one particle (does nothing when pool is full)
+    unsafe fn spawn(&mut self) {
+        if self.particles.len() == self.particles.capacity() {
+            return;
+        }
+
+        let config = &self.config;
+        let rng = &mut self.rng;
+        let (ex, ey) = self.position;
+
+        let (ox, oy) = match config.shape {
+            EmitterShape::Point => (0.0, 0.0),
+            EmitterShape::Box { half_width, half_height } => (rng.range(-half_width, half_width), rng.range(-half_height, half_height)),
+            EmitterShape::Circle { radius } => {
+                let angle = rng.range(0.0, 2.0 * PI);
+                // Square root gives uniform distribution over the whole circle area
+                let distance = radius * psp::math::sqrtf(rng.range(0.0, 1.0));
+                (cosf(angle) * distance, sinf(angle) * distance)
+            },
+        };
+
+        let angle = config.direction + rng.range(-config.spread, config.spread);
+        let speed = rng.range(config.speed.0, config.speed.1);
+        let lifetime = rng.range(config.lifetime.0, config.lifetime.1);
+
+        self.particles.push(Particle {
+            x: ex + ox,
+            y: ey + oy,
+            vx: cosf(angle) * speed,
+            vy: sinf(angle) * speed,
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    /// Draw all particles with one batched 'Sprites' draw call. Must be called inside started frame
+    pub unsafe fn draw(&self, states: &mut StateCache) {
+        if self.particles.is_empty() {
+            return;
+        }
+
+        states.apply(&RenderState { blend: self.config.blend, alpha_test: None, depth_test: false, depth_write: false, cull: CullMode::None, texture: false });
+
+        // Each sprite is described by 2 corners (top-left and bottom-right). Memory is taken from display list so it lives until frame is drawn
+        let count = self.particles.len() * 2;
+        let vertices = sceGuGetMemory((count * core::mem::size_of::<Vertex>()) as i32) as *mut Vertex;
+
+        for (i, particle) in self.particles.iter().enumerate() {
+            let life = particle.age / particle.lifetime;
+            let color = self.config.color.sample(life);
+            let half = self.config.size.sample(life) / 2.0;
+
+            *vertices.add(i * 2) = Vertex { u: 0.0, v: 0.0, color, x: particle.x - half, y: particle.y - half, z: 0.0 };
+            *vertices.add(i * 2 + 1) = Vertex { u: 0.0, v: 0.0, color, x: particle.x + half, y: particle.y + half, z: 0.0 };
+        }
+
+        sceGuDrawArray(GuPrimitive::Sprites, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, count as i32, core::ptr::null(), vertices as *const c_void);
+    }
+}
+
+/// Small and fast pseudo random numbers generator (xorshift), good enough for visual effects
+struct Rng(u32);
+
+impl Rng {
+    /// Xorshift state can't be 0 (it would stay 0 forever), so such seed is replaced with other constant
+    fn new(seed: u32) -> Self {
+        Self(if seed == 0 { 0x2545_f491 } else { seed })
+    }
+
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+
+        x
+    }
+
+    /// Random value in min..=max range
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next() >> 8) as f32 / (1u32 << 24) as f32;
+
+        min + (max - min) * unit
+    }
+}
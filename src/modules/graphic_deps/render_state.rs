@@ -0,0 +1,161 @@
+use psp::sys::*;
+
+/// How color of drawn pixel is mixed with color which is already in draw buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// No blending, drawn pixel replaces old one
+    Opaque,
+    /// Standard transparency: source * alpha + destination * (1 - alpha)
+    Alpha,
+    /// Light-like effects (sparks, fire): source * alpha + destination
+    Additive,
+    /// Darkening effects (shadows, tinting): source * destination
+    Multiply,
+    /// For colors which were already multiplied by alpha: source + destination * (1 - alpha)
+    Premultiplied,
+}
+
+/// Which triangles are removed before drawing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CullMode {
+    /// Draw both sides of triangles
+    None,
+    /// Remove triangles which are turned backward to camera (clockwise triangles are front ones, see 'init_graphic')
+    Back,
+    /// Remove triangles which are turned forward to camera
+    Front,
+}
+
+/// Description of render state which should be set before next draw calls
+#[derive(Clone, Copy, Debug)]
+pub struct RenderState {
+    pub blend: BlendMode,
+    /// Pixels which don't pass test (alpha compared with reference value) aren't drawn at all. None = test is disabled
+    pub alpha_test: Option<(AlphaFunc, u8)>,
+    /// Compare depth of drawn pixel with depth buffer
+    pub depth_test: bool,
+    /// Save depth of drawn pixel to depth buffer (usually disabled for translucent objects)
+    pub depth_write: bool,
+    pub cull: CullMode,
+    /// Color pixels with currently bound texture
+    pub texture: bool,
+}
+
+impl RenderState {
+    /// Opaque 3D geometry
+    pub const OPAQUE: Self = Self { blend: BlendMode::Opaque, alpha_test: None, depth_test: true, depth_write: true, cull: CullMode::Back, texture: false };
+    /// Translucent 3D geometry (tested against depth buffer but doesn't cover objects drawn after it)
+    pub const TRANSLUCENT: Self = Self { blend: BlendMode::Alpha, alpha_test: None, depth_test: true, depth_write: false, cull: CullMode::Back, texture: false };
+    /// Flat 2D elements drawn on top of everything (UI panels, sprites)
+    pub const OVERLAY: Self = Self { blend: BlendMode::Alpha, alpha_test: None, depth_test: false, depth_write: false, cull: CullMode::None, texture: false };
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self::OPAQUE
+    }
+}
+
+/// Remembers what GE currently has got set and sends only commands which change something.
+/// Each field is None until it was set for the first time (or after 'invalidate'), so first 'apply' sends everything
+#[derive(Default)]
+pub struct StateCache {
+    blend: Option<BlendMode>,
+    alpha_test: Option<Option<(AlphaFunc, u8)>>,
+    depth_test: Option<bool>,
+    depth_write: Option<bool>,
+    cull: Option<CullMode>,
+    texture: Option<bool>,
+}
+
+impl StateCache {
+    pub const fn new() -> Self {
+        Self { blend: None, alpha_test: None, depth_test: None, depth_write: None, cull: None, texture: None }
+    }
+
+    /// Forget tracked state. Must be called when state was changed outside of this cache (e.g. by raw 'sceGuEnable' call or 'init_graphic')
+    pub fn invalidate(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Set render state for next draw calls. Must be called inside started frame
+    pub unsafe fn apply(&mut self, state: &RenderState) {
+        if self.blend != Some(state.blend) {
+            set_blend(state.blend);
+            self.blend = Some(state.blend);
+        }
+
+        // 'AlphaFunc' can't be compared directly so it is compared as GE value
+        let alpha_test_key = |test: Option<(AlphaFunc, u8)>| test.map(|(func, reference)| (func as u32, reference));
+        if self.alpha_test.map(alpha_test_key) != Some(alpha_test_key(state.alpha_test)) {
+            match state.alpha_test {
+                Some((func, reference)) => {
+                    sceGuEnable(GuState::AlphaTest);
+                    sceGuAlphaFunc(func, reference as i32, 0xff);
+                },
+                None => sceGuDisable(GuState::AlphaTest)
+            }
+            self.alpha_test = Some(state.alpha_test);
+        }
+
+        if self.depth_test != Some(state.depth_test) {
+            if state.depth_test {
+                sceGuEnable(GuState::DepthTest);
+            } else {
+                sceGuDisable(GuState::DepthTest);
+            }
+            self.depth_test = Some(state.depth_test);
+        }
+
+        if self.depth_write != Some(state.depth_write) {
+            // Depth mask works in inversed way: 1 = don't write to depth buffer
+            sceGuDepthMask(if state.depth_write { 0 } else { 1 });
+            self.depth_write = Some(state.depth_write);
+        }
+
+        if self.cull != Some(state.cull) {
+            match state.cull {
+                CullMode::None => sceGuDisable(GuState::CullFace),
+                CullMode::Back => {
+                    sceGuEnable(GuState::CullFace);
+                    sceGuFrontFace(FrontFaceDirection::Clockwise);
+                },
+                CullMode::Front => {
+                    sceGuEnable(GuState::CullFace);
+                    sceGuFrontFace(FrontFaceDirection::CounterClockwise);
+                },
+            }
+            self.cull = Some(state.cull);
+        }
+
+        if self.texture != Some(state.texture) {
+            if state.texture {
+                sceGuEnable(GuState::Texture2D);
+            } else {
+                sceGuDisable(GuState::Texture2D);
+            }
+            self.texture = Some(state.texture);
+        }
+    }
+}
+
+/// Send blending commands for specified mode
+unsafe fn set_blend(mode: BlendMode) {
+    // GE hasn't got "one" and "zero" blending factors so they are emulated by fixed colors
+    const ONE: u32 = 0xffffff;
+    const ZERO: u32 = 0;
+
+    match mode {
+        BlendMode::Opaque => {
+            sceGuDisable(GuState::Blend);
+            return;
+        },
+        BlendMode::Alpha => sceGuBlendFunc(BlendOp::Add, BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha, ZERO, ZERO),
+        BlendMode::Additive => sceGuBlendFunc(BlendOp::Add, BlendFactor::SrcAlpha, BlendFactor::Fix, ZERO, ONE),
+        // Destination factor 'Color' means source color
+        BlendMode::Multiply => sceGuBlendFunc(BlendOp::Add, BlendFactor::Fix, BlendFactor::Color, ZERO, ZERO),
+        BlendMode::Premultiplied => sceGuBlendFunc(BlendOp::Add, BlendFactor::Fix, BlendFactor::OneMinusSrcAlpha, ONE, ZERO),
+    }
+
+    sceGuEnable(GuState::Blend);
+}
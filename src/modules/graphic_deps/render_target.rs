@@ -0,0 +1,86 @@
+use core::ffi::c_void;
+use psp::sys::*;
+use crate::examples::types_def::Texture;
+use crate::graphics::{self, PSP_BUF_WIDTH, PSP_SCR_HEIGHT};
+
+/// Offscreen color buffer placed in VRAM. Scene can be drawn into it and next the result can be used as a 'Texture' (minimaps, picture-in-picture, post effects)
+/// Render target shares depth buffer with the screen, so it can't be bigger then screen buffer
+pub struct RenderTarget {
+    /// VRAM pointer counted from 0 (form expected by 'sceGuDrawBuffer*' functions)
+    draw_ptr: *mut c_void,
+    /// Real memory address of the same buffer (form expected by 'sceGuTexImage')
+    texture_ptr: *const c_void,
+    width: u16,
+    height: u16,
+    psm: DisplayPixelFormat,
+}
+
+impl RenderTarget {
+    /// Allocate render target in VRAM ('init_graphic' must be called first). Width and height must be power of 2 (required by textures),
+    /// width can't be greater then 512 and height can't be greater then 256 (depth buffer size). Returns None for wrong size or when there isn't enough free VRAM
+    /// (VRAM of render target is never freed, so targets should be created once and reused)
+    pub unsafe fn new(width: u16, height: u16, psm: DisplayPixelFormat) -> Option<Self> {
+        if !width.is_power_of_two() || !height.is_power_of_two() || width > PSP_BUF_WIDTH || height > PSP_SCR_HEIGHT {
+            return None;
+        }
+
+        let chunk = graphics::alloc_vram(width as u32, height as u32, texture_format(psm))?;
+
+        Some(Self {
+            draw_ptr: chunk.as_mut_ptr_from_zero() as *mut c_void,
+            texture_ptr: chunk.as_mut_ptr_direct_to_vram() as *const c_void,
+            width,
+            height,
+            psm,
+        })
+    }
+
+    /// Redirect next draw commands into this render target. Must be called inside started frame, drawing must be finished by 'end'
+    pub unsafe fn begin(&self) {
+        sceGuDrawBufferList(self.psm, self.draw_ptr, self.width as i32);
+
+        // Virtual coordinates must be centered on render target instead of screen
+        sceGuOffset(2048 - (self.width as u32 / 2), 2048 - (self.height as u32 / 2));
+        sceGuViewport(2048, 2048, self.width as i32, self.height as i32);
+        sceGuScissor(0, 0, self.width as i32, self.height as i32);
+    }
+
+    /// Go back to drawing on the screen
+    pub unsafe fn end(&self) {
+        graphics::bind_screen_buffer();
+
+        // Wait until texture cache won't contain stale data of this render target
+        sceGuTexSync();
+    }
+
+    /// Describe render target content as texture (valid until render target is drawn again)
+    pub fn texture(&self) -> Texture {
+        Texture {
+            bytes: self.texture_ptr,
+            width: self.width as i32,
+            height: self.height as i32,
+            tbw: self.width as i32,
+        }
+    }
+
+    /// Use render target content as texture for next drawn textured primitives
+    pub unsafe fn bind(&self) {
+        let Texture { bytes, width, height, tbw } = self.texture();
+
+        sceGuTexMode(texture_format(self.psm), 0, 0, 0);
+        sceGuTexImage(MipmapLevel::None, width, height, tbw, bytes);
+        sceGuTexFunc(TextureEffect::Modulate, TextureColorComponent::Rgba);
+        sceGuTexFilter(TextureFilter::Linear, TextureFilter::Linear);
+        sceGuTexWrap(GuTexWrapMode::Clamp, GuTexWrapMode::Clamp);
+    }
+}
+
+/// Texture format which reads pixels in the same layout as they were drawn
+fn texture_format(psm: DisplayPixelFormat) -> TexturePixelFormat {
+    match psm {
+        DisplayPixelFormat::Psm5650 => TexturePixelFormat::Psm5650,
+        DisplayPixelFormat::Psm5551 => TexturePixelFormat::Psm5551,
+        DisplayPixelFormat::Psm4444 => TexturePixelFormat::Psm4444,
+        DisplayPixelFormat::Psm8888 => TexturePixelFormat::Psm8888,
+    }
+}
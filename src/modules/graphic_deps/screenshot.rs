@@ -0,0 +1,374 @@
+use alloc::vec::Vec;
+#[cfg(target_os = "psp")]
+use alloc::{string::String, format};
+#[cfg(target_os = "psp")]
+use core::ffi::c_void;
+#[cfg(target_os = "psp")]
+use psp::sys::*;
+#[cfg(target_os = "psp")]
+use crate::file_system;
+#[cfg(target_os = "psp")]
+use crate::time;
+use crate::checksum::crc32;
+
+/// File format of saved screenshot
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Uncompressed 24-bit bitmap
+    Bmp,
+    /// PNG with uncompressed ('stored') deflate blocks. Bigger then normal PNG but readable by every image viewer
+    Png,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+/// Copy of displayed screen with pixels converted to 8-bit RGB
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// 3 bytes (red, green, blue) per pixel, rows from top to bottom
+    pub pixels: Vec<u8>,
+}
+
+/// Directory where 'take_screenshot' saves files (the same one which is used by PSP system screenshots)
+#[cfg(target_os = "psp")]
+static SCREENSHOTS_DIR: &str = "ms0:/PICTURE";
+
+impl Screenshot {
+    /// Copy frame which is displayed right now (after 'sceGuSwapBuffers' it's buffer which was drawn in previous frame).
+    /// Pixel format of display buffer is readed from display, so every format set by 'sceGuDispBuffer' is supported. Returns None when display has got no frame buffer
+    #[cfg(target_os = "psp")]
+    pub unsafe fn capture() -> Option<Self> {
+        let mut top_addr: *mut c_void = core::ptr::null_mut();
+        let mut buffer_width = 0usize;
+        let mut psm = DisplayPixelFormat::Psm8888;
+        sceDisplayGetFrameBuf(&mut top_addr, &mut buffer_width, &mut psm, DisplaySetBufSync::Immediate);
+        if top_addr.is_null() {
+            return None;
+        }
+
+        let (mut mode, mut width, mut height) = (0, 0, 0);
+        sceDisplayGetMode(&mut mode, &mut width, &mut height);
+        let (width, height) = (width as usize, height as usize);
+
+        // Frame buffer is written by GE, so it is readed through uncached address to not get stale data from CPU cache
+        let base = (top_addr as usize | 0x4000_0000) as *const u8;
+        let mut pixels = Vec::with_capacity(width * height * 3);
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * buffer_width + x;
+
+                let (r, g, b) = match psm {
+                    DisplayPixelFormat::Psm8888 => {
+                        let pixel = base.add(index * 4);
+                        (*pixel, *pixel.add(1), *pixel.add(2))
+                    },
+                    _ => {
+                        let pixel = *(base as *const u16).add(index);
+                        unpack_16bit(pixel, psm)
+                    }
+                };
+
+                pixels.extend_from_slice(&[r, g, b]);
+            }
+        }
+
+        Some(Self { width: width as u32, height: height as u32, pixels })
+    }
+
+    /// Create content of image file in specified format
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Bmp => self.encode_bmp(),
+            ImageFormat::Png => self.encode_png(),
+        }
+    }
+
+    /// Save screenshot to file with specified path
+    #[cfg(target_os = "psp")]
+    pub unsafe fn save(&self, path: &str, format: ImageFormat) -> Result<(), i32> {
+        file_system::write_file(path, &self.encode(format))
+    }
+
+    fn encode_bmp(&self) -> Vec<u8> {
+        // Each row must be padded to multiple of 4 bytes
+        let row_size = (self.width as usize * 3 + 3) & !3;
+        let data_size = row_size * self.height as usize;
+        let mut file = Vec::with_capacity(54 + data_size);
+
+        // File header
+        file.extend_from_slice(b"BM");
+        file.extend_from_slice(&(54 + data_size as u32).to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&54u32.to_le_bytes());
+
+        // Info header
+        file.extend_from_slice(&40u32.to_le_bytes());
+        file.extend_from_slice(&self.width.to_le_bytes());
+        file.extend_from_slice(&self.height.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&24u16.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes()); // no compression
+        file.extend_from_slice(&(data_size as u32).to_le_bytes());
+        file.extend_from_slice(&2835u32.to_le_bytes()); // 72 DPI
+        file.extend_from_slice(&2835u32.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+
+        // Rows are stored from bottom to top and pixels as blue, green, red
+        for row in self.pixels.chunks_exact(self.width as usize * 3).rev() {
+            for pixel in row.chunks_exact(3) {
+                file.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+            }
+            file.resize(file.len() + row_size - row.len(), 0);
+        }
+
+        file
+    }
+
+    fn encode_png(&self) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGB, deflate compression, standard filtering, no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        png_chunk(&mut file, b"IHDR", &header);
+
+        // Each row starts with filter type (0 = none)
+        let mut raw = Vec::with_capacity(self.pixels.len() + self.height as usize);
+        for row in self.pixels.chunks_exact(self.width as usize * 3) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+        png_chunk(&mut file, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut file, b"IEND", &[]);
+
+        file
+    }
+}
+
+/// Capture screen and save it as 'ms0:/PICTURE/shot_<date>_<time>.<extension>' (date and time comes from RTC clock). Returns path of created file
+#[cfg(target_os = "psp")]
+pub unsafe fn take_screenshot(format: ImageFormat) -> Result<String, i32> {
+    // Error code used when display has got no frame buffer yet
+    const NO_FRAME_BUFFER: i32 = -1;
+
+    let screenshot = Screenshot::capture().ok_or(NO_FRAME_BUFFER)?;
+
+    let ScePspDateTime { year, month, day, hour, minutes, seconds, microseconds } = time::local_time();
+    // Milliseconds are added so few screenshots taken in the same second don't overwrite each other
+    let path = format!(
+        "{SCREENSHOTS_DIR}/shot_{year:04}{month:02}{day:02}_{hour:02}{minutes:02}{seconds:02}_{:03}.{}",
+        microseconds / 1000,
+        format.extension()
+    );
+
+    file_system::ensure_dir(SCREENSHOTS_DIR)?;
+    screenshot.save(&path, format)?;
+
+    Ok(path)
+}
+
+/// Expand 16-bit pixel into 8-bit channels. PSP keeps red channel in the lowest bits
+#[cfg(target_os = "psp")]
+fn unpack_16bit(pixel: u16, psm: DisplayPixelFormat) -> (u8, u8, u8) {
+    let pixel = pixel as u32;
+
+    // Scale n-bit channel to 0..=255 range
+    let expand = |value: u32, bits: u32| ((value * 255) / ((1 << bits) - 1)) as u8;
+
+    match psm {
+        DisplayPixelFormat::Psm5650 => (expand(pixel & 0x1f, 5), expand((pixel >> 5) & 0x3f, 6), expand((pixel >> 11) & 0x1f, 5)),
+        DisplayPixelFormat::Psm5551 => (expand(pixel & 0x1f, 5), expand((pixel >> 5) & 0x1f, 5), expand((pixel >> 10) & 0x1f, 5)),
+        DisplayPixelFormat::Psm4444 => (expand(pixel & 0xf, 4), expand((pixel >> 4) & 0xf, 4), expand((pixel >> 8) & 0xf, 4)),
+        // 32-bit pixels aren't handled here
+        DisplayPixelFormat::Psm8888 => (0, 0, 0),
+    }
+}
+
+/// Append PNG chunk (length, type, data and CRC of type and data)
+fn png_chunk(file: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    file.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = file.len();
+    file.extend_from_slice(kind);
+    file.extend_from_slice(data);
+    let crc = crc32(&file[start..]);
+
+    file.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data into zlib stream made from uncompressed deflate blocks (compression would be too slow on PSP CPU for a few MB of pixels anyway)
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Stored block can contain at most 65535 bytes
+    const BLOCK_SIZE: usize = 0xffff;
+
+    let mut stream = Vec::with_capacity(data.len() + data.len() / BLOCK_SIZE * 5 + 11);
+    // Deflate with 32K window, no preset dictionary (header must be divisible by 31)
+    stream.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(BLOCK_SIZE).peekable();
+    if blocks.peek().is_none() {
+        // Empty data still needs one final block
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+
+        stream.push(last as u8);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULO: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+
+    // Sums are reduced every 5552 bytes which is the most that can't overflow u32
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MODULO;
+        b %= MODULO;
+    }
+
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2x2 image: top row (1, 2, 3) (4, 5, 6), bottom row (7, 8, 9) (10, 11, 12)
+    fn screenshot() -> Screenshot {
+        Screenshot { width: 2, height: 2, pixels: (1..=12).collect() }
+    }
+
+    fn u32_le(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    fn u32_be(bytes: &[u8], at: usize) -> u32 {
+        u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+    }
+
+    /// Content of zlib stream made of stored blocks (checks block headers and Adler-32)
+    fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(u16::from_be_bytes([stream[0], stream[1]]) % 31, 0);
+
+        let mut data = Vec::new();
+        let mut position = 2;
+        loop {
+            let last = stream[position] == 1;
+            let length = u16::from_le_bytes([stream[position + 1], stream[position + 2]]);
+            assert_eq!(u16::from_le_bytes([stream[position + 3], stream[position + 4]]), !length);
+
+            position += 5;
+            data.extend_from_slice(&stream[position..position + length as usize]);
+            position += length as usize;
+            if last {
+                break;
+            }
+        }
+
+        assert_eq!(u32_be(stream, position), adler32(&data));
+        assert_eq!(stream.len(), position + 4);
+        data
+    }
+
+    #[test]
+    fn bmp_header_and_stride() {
+        let file = screenshot().encode(ImageFormat::Bmp);
+
+        // Rows of 6 bytes are padded to 8
+        assert_eq!(file.len(), 54 + 16);
+        assert_eq!(&file[0..2], b"BM");
+        assert_eq!(u32_le(&file, 2), 70);
+        assert_eq!(u32_le(&file, 10), 54);
+        assert_eq!(u32_le(&file, 14), 40);
+        assert_eq!((u32_le(&file, 18), u32_le(&file, 22)), (2, 2));
+        assert_eq!(&file[26..30], [1, 0, 24, 0]);
+        assert_eq!(u32_le(&file, 34), 16);
+
+        // Bottom row first, pixels as blue, green, red
+        assert_eq!(file[54..], [9, 8, 7, 12, 11, 10, 0, 0, 3, 2, 1, 6, 5, 4, 0, 0]);
+
+        // Row of 3 bytes is padded to 4
+        let narrow = Screenshot { width: 1, height: 2, pixels: alloc::vec![1, 2, 3, 4, 5, 6] }.encode(ImageFormat::Bmp);
+        assert_eq!(narrow[54..], [6, 5, 4, 0, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn png_chunks() {
+        let file = screenshot().encode(ImageFormat::Png);
+        assert_eq!(file[..8], [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']);
+
+        let mut chunks = Vec::new();
+        let mut position = 8;
+        while position < file.len() {
+            let length = u32_be(&file, position) as usize;
+            let body = &file[position + 4..position + 8 + length];
+            assert_eq!(u32_be(&file, position + 8 + length), crc32(body));
+
+            chunks.push((&body[..4], &body[4..]));
+            position += 12 + length;
+        }
+
+        let kinds: Vec<_> = chunks.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 2, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        // Each row starts with filter type 0
+        assert_eq!(inflate_stored(chunks[1].1), [0, 1, 2, 3, 4, 5, 6, 0, 7, 8, 9, 10, 11, 12]);
+        assert!(chunks[2].1.is_empty());
+    }
+
+    #[test]
+    fn zlib_stored_blocks() {
+        assert_eq!(zlib_stored(&[]), [0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1]);
+
+        // The biggest stored block fits in one block, one more byte needs second block
+        let data: Vec<u8> = (0..0xffff + 1).map(|i| i as u8).collect();
+        let single = zlib_stored(&data[..0xffff]);
+        assert_eq!(single[2..5], [1, 0xff, 0xff]);
+        assert_eq!(inflate_stored(&single), data[..0xffff]);
+
+        let split = zlib_stored(&data);
+        assert_eq!(split[2..5], [0, 0xff, 0xff]);
+        assert_eq!(split[2 + 5 + 0xffff..2 + 5 + 0xffff + 5], [1, 1, 0, 0xfe, 0xff]);
+        assert_eq!(inflate_stored(&split), data);
+    }
+
+    #[test]
+    fn adler32_values() {
+        assert_eq!(adler32(&[]), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        // Long data checks that reducing sums once per chunk gives the same result as reducing after every byte
+        let data = alloc::vec![0xff; 100_000];
+        let (mut a, mut b) = (1u32, 0u32);
+        for byte in &data {
+            a = (a + *byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(adler32(&data), (b << 16) | a);
+    }
+}
@@ -1,19 +1,23 @@
-use crate::examples::types_def::Vertex;
+use crate::examples::types_def::{Vertex, NormalVertex};
+use crate::lighting;
+use alloc::vec::Vec;
 use psp::sys::rgba;
+use psp::math::{sinf, cosf};
 use psp::Align16;
 use core::ffi::c_short;
+use core::f32::consts::PI;
 
 /// Define point of rendering for triangle
-pub static TRIANGLE: Align16<[Vertex; 3]> = Align16([ // Each 'color' of course can be different but 'z' point for all 'indices' must be the same value
-    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 0), x: 0.35, y: 0.0, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(210, 10, 238, 0), x: -0.35, y: 0.0, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 0), x: 0.0, y: 0.5, z: -10f32 }
+pub static TRIANGLE: Align16<[Vertex; 3]> = Align16([ // Each 'color' of course can be different but 'z' point for all 'indices' must be the same value. Alpha = 255 means fully opaque color
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: 0.35, y: 0.0, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 10, 238, 255), x: -0.35, y: 0.0, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: 0.0, y: 0.5, z: -10f32 }
 ]);
 
 pub static TRIANGLE_2: Align16<[Vertex; 3]> = Align16([ // right triangle version
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: 0.0, y: 0.0, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: -0.5, y: 0.0, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: 0.0, y: 0.5, z: -10f32 }
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.0, y: 0.0, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.5, y: 0.0, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.0, y: 0.5, z: -10f32 }
 ]);
 
 /// Define points of rendering for square
@@ -21,34 +25,164 @@ pub static TRIANGLE_2: Align16<[Vertex; 3]> = Align16([ // right triangle versio
 ///    |       |
 ///    4------0/5
 pub static SQUARE: Align16<[Vertex; 6]> = Align16([
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: -0.15, y: -0.15, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: -0.15, y: 0.15, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: 0.15, y: 0.15, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: 0.15, y: 0.15, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: 0.15, y: -0.15, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: -0.15, y: -0.15, z: -10f32 }
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.15, y: -0.15, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.15, y: 0.15, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: 0.15, y: 0.15, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: 0.15, y: 0.15, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: 0.15, y: -0.15, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.15, y: -0.15, z: -10f32 }
 ]);
 
 /// Same schema as SQUARE
 pub static RECTANGLE: Align16<[Vertex; 6]> = Align16([
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: -0.15, y: -0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: -0.15, y: 0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: 0.15, y: 0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: 0.15, y: 0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: 0.15, y: -0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: -0.15, y: -0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.15, y: -0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.15, y: 0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.15, y: 0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.15, y: 0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.15, y: -0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.15, y: -0.3, z: -10f32 },
 ]);
     
     
 /// Indexed version of rectangle
 pub static RECTANGLE_INDX: Align16<[Vertex; 4]> = Align16([ // without double '2' and '0' indexes from normal 'RECTANGLE' list
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: -0.15, y: -0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 0), x: -0.15, y: 0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 0), x: 0.15, y: 0.3, z: -10f32 },
-    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 0), x: 0.15, y: -0.3, z: -10f32 }
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.15, y: -0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.15, y: 0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.15, y: 0.3, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: 0.15, y: -0.3, z: -10f32 }
 ]);
 
 /// Indexes for 'RECTANGLE_INDX'
 pub static INDEXES_RECTANGLE: Align16<[c_short; 6]> = Align16([
     0, 1, 2, 2, 3, 0
 ]);
+
+/// Define points of rendering for 3D cube (12 triangles = 2 per each of 6 walls)
+/// Every wall is described in the same winding order so back walls can be removed by culling
+pub static CUBE: Align16<[Vertex; 36]> = Align16([
+    // Front wall (z = 0.5)
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: -0.5, y: -0.5, z: 0.5 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(210, 0, 238, 255), x: -0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(210, 0, 238, 255), x: 0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: -0.5, y: -0.5, z: 0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(210, 0, 238, 255), x: 0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(210, 0, 238, 255), x: 0.5, y: -0.5, z: 0.5 },
+    // Back wall (z = -0.5)
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: -0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(210, 0, 238, 255), x: 0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(210, 0, 238, 255), x: 0.5, y: 0.5, z: -0.5 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(210, 0, 238, 255), x: -0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(210, 0, 238, 255), x: 0.5, y: 0.5, z: -0.5 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(210, 0, 238, 255), x: -0.5, y: 0.5, z: -0.5 },
+    // Right wall (x = 0.5)
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: 0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(14, 212, 106, 255), x: 0.5, y: -0.5, z: 0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(14, 212, 106, 255), x: 0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: 0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(14, 212, 106, 255), x: 0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(14, 212, 106, 255), x: 0.5, y: 0.5, z: -0.5 },
+    // Left wall (x = -0.5)
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.5, y: 0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(14, 212, 106, 255), x: -0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(14, 212, 106, 255), x: -0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(14, 212, 106, 255), x: -0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(14, 212, 106, 255), x: -0.5, y: -0.5, z: 0.5 },
+    // Top wall (y = 0.5)
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.5, y: 0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(247, 190, 3, 255), x: 0.5, y: 0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(247, 190, 3, 255), x: 0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.5, y: 0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(247, 190, 3, 255), x: 0.5, y: 0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(247, 190, 3, 255), x: -0.5, y: 0.5, z: 0.5 },
+    // Bottom wall (y = -0.5)
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.5, y: -0.5, z: 0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(247, 190, 3, 255), x: 0.5, y: -0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(247, 190, 3, 255), x: -0.5, y: -0.5, z: -0.5 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(247, 190, 3, 255), x: 0.5, y: -0.5, z: 0.5 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(247, 190, 3, 255), x: 0.5, y: -0.5, z: -0.5 },
+]);
+
+/// Flat floor square lying on 'y = 0' plane which gives a reference point for moving camera in 3D scene
+pub static FLOOR: Align16<[Vertex; 6]> = Align16([
+    Vertex { u: 0f32, v: 0f32, color: rgba(60, 60, 60, 255), x: -5.0, y: 0.0, z: -5.0 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(60, 60, 60, 255), x: 5.0, y: 0.0, z: -5.0 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(90, 90, 90, 255), x: 5.0, y: 0.0, z: 5.0 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(60, 60, 60, 255), x: -5.0, y: 0.0, z: -5.0 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(90, 90, 90, 255), x: 5.0, y: 0.0, z: 5.0 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(90, 90, 90, 255), x: -5.0, y: 0.0, z: 5.0 },
+]);
+
+/// Generate sphere mesh with smooth normals (ready for hardware lighting). Returns vertices and 16-bit indexes for them
+/// 'rings' = amount of horizontal slices, 'segments' = amount of vertical slices. Returns None when one of them is 0 or when '(rings + 1) * (segments + 1)'
+/// vertices don't fit in 16-bit index (e.g. 200 x 300 is too much)
+pub fn generate_sphere(radius: f32, rings: u16, segments: u16, color: u32) -> Option<(Vec<NormalVertex>, Vec<u16>)> {
+    if rings == 0 || segments == 0 {
+        return None;
+    }
+    let (rings, segments) = (rings as usize, segments as usize);
+    let columns = segments + 1;
+    if (rings + 1) * columns > u16::MAX as usize + 1 {
+        return None;
+    }
+
+    let mut vertices = Vec::with_capacity((rings + 1) * columns);
+    let mut indices = Vec::with_capacity(rings * segments * 6);
+
+    // Vertices: from top pole (ring = 0) to bottom pole (ring = rings)
+    for ring in 0..=rings {
+        let theta = PI * ring as f32 / rings as f32;
+
+        for segment in 0..=segments {
+            // Last segment uses exactly the same angle as first one so seam vertices are placed in the same position
+            let phi = 2.0 * PI * (segment % segments) as f32 / segments as f32;
+            // VFPU functions are always available on PSP
+            let (sin_theta, cos_theta, sin_phi, cos_phi) = unsafe { (sinf(theta), cosf(theta), sinf(phi), cosf(phi)) };
+
+            vertices.push(NormalVertex {
+                u: segment as f32 / segments as f32,
+                v: ring as f32 / rings as f32,
+                color,
+                x: radius * sin_theta * cos_phi,
+                y: radius * cos_theta,
+                z: radius * sin_theta * sin_phi,
+                ..NormalVertex::default()
+            });
+        }
+    }
+
+    // Indexes: 2 triangles per each quad between 2 rings (clockwise order when seen from outside of sphere). All of them fit in 'u16' thanks to check above
+    for ring in 0..rings {
+        for segment in 0..segments {
+            let top = ring * columns + segment;
+            let bottom = top + columns;
+
+            indices.extend([top + 1, top, bottom, top + 1, bottom, bottom + 1].map(|index| index as u16));
+        }
+    }
+
+    lighting::compute_smooth_normals(&mut vertices, Some(&indices));
+
+    Some((vertices, indices))
+}
+
+/// Half transparent square (alpha = 128) which shows blending of colors with already drawn shapes. Same schema as SQUARE
+pub static TRANSLUCENT_PANEL: Align16<[Vertex; 6]> = Align16([
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 128), x: -0.4, y: -0.2, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 128), x: -0.4, y: 0.2, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 128), x: 0.4, y: 0.2, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 128), x: 0.4, y: 0.2, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 128), x: 0.4, y: -0.2, z: -10f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 128), x: -0.4, y: -0.2, z: -10f32 },
+]);
+
+/// Square with texture coordinates covering whole texture (white color keeps texture colors unchanged). Same schema as SQUARE
+pub static TEXTURED_SQUARE: Align16<[Vertex; 6]> = Align16([
+    Vertex { u: 0f32, v: 1f32, color: rgba(255, 255, 255, 255), x: -0.5, y: -0.5, z: 0f32 },
+    Vertex { u: 0f32, v: 0f32, color: rgba(255, 255, 255, 255), x: -0.5, y: 0.5, z: 0f32 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(255, 255, 255, 255), x: 0.5, y: 0.5, z: 0f32 },
+    Vertex { u: 1f32, v: 0f32, color: rgba(255, 255, 255, 255), x: 0.5, y: 0.5, z: 0f32 },
+    Vertex { u: 1f32, v: 1f32, color: rgba(255, 255, 255, 255), x: 0.5, y: -0.5, z: 0f32 },
+    Vertex { u: 0f32, v: 1f32, color: rgba(255, 255, 255, 255), x: -0.5, y: -0.5, z: 0f32 },
+]);
@@ -0,0 +1,160 @@
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
+use core::ffi::c_void;
+use psp::sys::*;
+use psp::math::{sinf, cosf};
+use embedded_graphics::{prelude::*, pixelcolor::Rgb888, primitives::Rectangle};
+use crate::examples::types_def::{Texture, Vertex};
+use crate::render_state::{RenderState, StateCache};
+
+/// Texture placed in RAM which can be drawn by 'embedded-graphics' (UI panels, charts, text) and next used many times as sprite in 'sceGu' pipeline.
+/// Pixels which weren't drawn stay fully transparent ('Psm5650' hasn't got alpha channel, so they are black there), drawn ones are opaque
+pub struct TextureCanvas {
+    pixels: *mut u8,
+    layout: Layout,
+    width: u16,
+    height: u16,
+    psm: TexturePixelFormat,
+}
+
+impl TextureCanvas {
+    /// Create transparent canvas. Width and height must be power of 2 not greater then 512 and format must be one of non-indexed formats
+    /// ('Psm5650', 'Psm5551', 'Psm4444', 'Psm8888'). Returns None for wrong arguments
+    pub fn new(width: u16, height: u16, psm: TexturePixelFormat) -> Option<Self> {
+        if !width.is_power_of_two() || !height.is_power_of_two() || width > 512 || height > 512 {
+            return None;
+        }
+        let bytes_per_pixel = match psm {
+            TexturePixelFormat::Psm8888 => 4,
+            TexturePixelFormat::Psm5650 | TexturePixelFormat::Psm5551 | TexturePixelFormat::Psm4444 => 2,
+            _ => return None
+        };
+
+        // GE expects textures aligned to 16 bytes
+        let layout = Layout::from_size_align(width as usize * height as usize * bytes_per_pixel, 16).ok()?;
+        let pixels = unsafe { alloc_zeroed(layout) };
+        if pixels.is_null() {
+            return None;
+        }
+
+        Some(Self { pixels, layout, width, height, psm })
+    }
+
+    /// Make all pixels fully transparent again
+    pub fn clear_transparent(&mut self) {
+        unsafe { core::ptr::write_bytes(self.pixels, 0, self.layout.size()) };
+    }
+
+    /// Write pixels from CPU cache to RAM and describe canvas as texture. Must be called after drawing and before canvas is used by GE
+    pub unsafe fn texture(&self) -> Texture {
+        sceKernelDcacheWritebackRange(self.pixels as *const c_void, self.layout.size() as u32);
+
+        Texture {
+            bytes: self.pixels as *const c_void,
+            width: self.width as i32,
+            height: self.height as i32,
+            tbw: self.width as i32,
+        }
+    }
+
+    /// Use canvas as texture for next drawn textured primitives (vertex colors tint it)
+    pub unsafe fn bind(&self) {
+        let Texture { bytes, width, height, tbw } = self.texture();
+
+        sceGuTexMode(self.psm, 0, 0, 0);
+        sceGuTexImage(MipmapLevel::None, width, height, tbw, bytes);
+        sceGuTexFunc(TextureEffect::Modulate, TextureColorComponent::Rgba);
+        sceGuTexFilter(TextureFilter::Linear, TextureFilter::Linear);
+        sceGuTexWrap(GuTexWrapMode::Clamp, GuTexWrapMode::Clamp);
+    }
+
+    /// Draw whole canvas as 2D quad with center in specified screen position, scaled by 'scale' and rotated by 'angle' radians (clockwise). Must be called inside started frame
+    pub unsafe fn draw(&self, center: (f32, f32), scale: f32, angle: f32, states: &mut StateCache) {
+        states.apply(&RenderState { texture: true, ..RenderState::OVERLAY });
+        self.bind();
+
+        let (half_w, half_h) = (self.width as f32 * scale / 2.0, self.height as f32 * scale / 2.0);
+        let (sin, cos) = (sinf(angle), cosf(angle));
+
+        // Corners in triangle strip order: top-left, top-right, bottom-left, bottom-right. Texture coordinates are in texels because quad is drawn in 2D mode
+        let vertices = sceGuGetMemory((4 * core::mem::size_of::<Vertex>()) as i32) as *mut Vertex;
+        let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)];
+        for (i, (sx, sy)) in corners.iter().enumerate() {
+            let (x, y) = (sx * half_w, sy * half_h);
+
+            *vertices.add(i) = Vertex {
+                u: if *sx > 0.0 { self.width as f32 } else { 0.0 },
+                v: if *sy > 0.0 { self.height as f32 } else { 0.0 },
+                color: 0xffff_ffff,
+                x: center.0 + x * cos - y * sin,
+                y: center.1 + x * sin + y * cos,
+                z: 0.0,
+            };
+        }
+
+        sceGuDrawArray(GuPrimitive::TriangleStrip, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, 4, core::ptr::null(), vertices as *const c_void);
+    }
+
+    /// Write one pixel (point must be inside canvas)
+    fn set_pixel(&mut self, x: u32, y: u32, color: Rgb888) {
+        let index = y as usize * self.width as usize + x as usize;
+        let (r, g, b) = (color.r() as u32, color.g() as u32, color.b() as u32);
+
+        unsafe {
+            match self.psm {
+                TexturePixelFormat::Psm8888 => *(self.pixels as *mut u32).add(index) = rgba(r as u8, g as u8, b as u8, 255),
+                // 16-bit formats keep red channel in the lowest bits
+                TexturePixelFormat::Psm5650 => *(self.pixels as *mut u16).add(index) = ((r >> 3) | ((g >> 2) << 5) | ((b >> 3) << 11)) as u16,
+                TexturePixelFormat::Psm5551 => *(self.pixels as *mut u16).add(index) = ((r >> 3) | ((g >> 3) << 5) | ((b >> 3) << 10) | (1 << 15)) as u16,
+                TexturePixelFormat::Psm4444 => *(self.pixels as *mut u16).add(index) = ((r >> 4) | ((g >> 4) << 4) | ((b >> 4) << 8) | (0xf << 12)) as u16,
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for TextureCanvas {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.pixels, self.layout) };
+    }
+}
+
+impl OriginDimensions for TextureCanvas {
+    fn size(&self) -> Size {
+        Size::new(self.width as u32, self.height as u32)
+    }
+}
+
+impl DrawTarget for TextureCanvas {
+    type Color = Rgb888;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (width, height) = (self.width as i32, self.height as i32);
+
+        // Pixels outside of canvas are skipped
+        for Pixel(point, color) in pixels {
+            if point.x >= 0 && point.y >= 0 && point.x < width && point.y < height {
+                self.set_pixel(point.x as u32, point.y as u32, color);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+
+        if let Some(bottom_right) = area.bottom_right() {
+            for y in area.top_left.y..=bottom_right.y {
+                for x in area.top_left.x..=bottom_right.x {
+                    self.set_pixel(x as u32, y as u32, color);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,767 @@
+use alloc::{vec::Vec, string::{String, ToString}, collections::BTreeMap};
+use psp::sys::rgba;
+#[cfg(target_os = "psp")]
+use crate::file_system;
+use crate::json::{self, JsonError, Value};
+use crate::xml::{self, XmlError, Element};
+use crate::tilemap::{TileLayer, TileAnimation};
+#[cfg(target_os = "psp")]
+use crate::tilemap::{Tilemap, Tileset};
+
+/// Things which can go wrong while loading map exported from Tiled editor
+#[derive(Debug)]
+pub enum TiledError {
+    /// File couldn't be read (contains error code returned by 'sceIo' function)
+    Io(i32),
+    /// File isn't valid UTF-8 text
+    NotText,
+    /// File extension isn't '.tmx', '.json' or '.tmj'
+    UnknownFormat,
+    Json(JsonError),
+    Xml(XmlError),
+    /// Required value is missing or has got wrong format. Contains name of value
+    Invalid(&'static str),
+    /// Layer data is compressed (zlib, gzip, zstd). Only uncompressed CSV and base64 data is supported
+    UnsupportedCompression,
+    /// Infinite maps (layer data stored in chunks) aren't supported
+    InfiniteMap,
+    /// Tile id doesn't fit into 16-bit layer cell
+    TileOutOfRange,
+}
+
+/// Value of custom property defined in Tiled
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+    /// Color converted to form created by 'rgba' function
+    Color(u32),
+    /// Path relative to map file
+    File(String),
+    /// Id of object from any object group
+    Object(u32),
+}
+
+/// Custom properties of map, layer or object in the same order as in file
+#[derive(Debug, Clone, Default)]
+pub struct Properties(pub Vec<(String, PropertyValue)>);
+
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&PropertyValue> {
+        self.0.iter().find(|(key, _)| key == name).map(|(_, value)| value)
+    }
+}
+
+/// Tileset used by map. Tiles of tileset have got global ids 'first_gid'..'first_gid + tile_count'
+#[derive(Debug, Clone)]
+pub struct TiledTileset {
+    pub first_gid: u32,
+    /// Path of external tileset file ('.tsx'/'.tsj') when tileset isn't embedded in map. External tilesets aren't loaded, so rest of fields stay empty
+    pub source: Option<String>,
+    pub name: String,
+    /// Path of tileset image relative to map file
+    pub image: Option<String>,
+    pub tile_width: u16,
+    pub tile_height: u16,
+    pub columns: u16,
+    pub tile_count: u32,
+    /// Tile animations: (local tile id, frames as (local tile id, duration in milliseconds))
+    pub animations: Vec<(u16, Vec<(u16, u32)>)>,
+}
+
+impl TiledTileset {
+    /// Animations in form used by 'Tileset'. Keys are local tile ids, so they match layer cells only for tileset with 'first_gid' = 1
+    pub fn tile_animations(&self) -> BTreeMap<u16, TileAnimation> {
+        self.animations
+            .iter()
+            .map(|(tile, frames)| {
+                let frames = frames.iter().map(|(frame, duration)| (*frame, *duration as f32 / 1000.0)).collect();
+                (*tile, TileAnimation { frames })
+            })
+            .collect()
+    }
+}
+
+/// Tile layer with its Tiled attributes. Cells contain global tile ids (flip flags are removed)
+pub struct TiledLayer {
+    pub name: String,
+    pub visible: bool,
+    pub tiles: TileLayer,
+    pub properties: Properties,
+}
+
+/// Form of map object
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectShape {
+    Rectangle,
+    Ellipse,
+    Point,
+    /// Points relative to object position
+    Polygon(Vec<(f32, f32)>),
+    /// Points relative to object position
+    Polyline(Vec<(f32, f32)>),
+    /// Object displayed as tile with specified global id (position is bottom-left corner of tile)
+    Tile(u32),
+}
+
+/// Object placed on object layer (spawn points, triggers, collision areas)
+#[derive(Debug, Clone)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    /// Object class ('type' in older Tiled versions)
+    pub kind: String,
+    /// Position in map pixels
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Rotation in degrees (clockwise)
+    pub rotation: f32,
+    pub shape: ObjectShape,
+    pub properties: Properties,
+}
+
+/// Object layer
+#[derive(Debug, Clone)]
+pub struct ObjectGroup {
+    pub name: String,
+    pub objects: Vec<MapObject>,
+    pub properties: Properties,
+}
+
+/// Whole map exported from Tiled. Layers from group layers are flattened (parallax of group is applied to its layers)
+pub struct TiledMap {
+    /// Size in tiles
+    pub width: u16,
+    pub height: u16,
+    /// Size of one tile in pixels
+    pub tile_width: u16,
+    pub tile_height: u16,
+    pub tilesets: Vec<TiledTileset>,
+    /// Tile layers from bottom to top
+    pub layers: Vec<TiledLayer>,
+    pub object_groups: Vec<ObjectGroup>,
+    pub properties: Properties,
+}
+
+impl TiledMap {
+    /// All objects from all object groups
+    pub fn objects(&self) -> impl Iterator<Item = &MapObject> {
+        self.object_groups.iter().flat_map(|group| group.objects.iter())
+    }
+
+    /// Object with specified name from any object group
+    pub fn object(&self, name: &str) -> Option<&MapObject> {
+        self.objects().find(|object| object.name == name)
+    }
+
+    /// Create 'Tilemap' from visible layers of map. Cells are used as they are, so map should use only one tileset with 'first_gid' = 1
+    #[cfg(target_os = "psp")]
+    pub fn into_tilemap(self, tileset: Tileset) -> Tilemap {
+        let mut map = Tilemap::new(tileset);
+        map.layers = self.layers.into_iter().filter(|layer| layer.visible).map(|layer| layer.tiles).collect();
+
+        map
+    }
+}
+
+/// Directory where map files are stored
+#[cfg(target_os = "psp")]
+static ASSETS_DIR: &str = "./files/";
+
+/// Tiled stores flip and rotation flags in the highest bits of global tile id
+const FLIP_FLAGS: u32 = 0xf000_0000;
+
+/// Load map from 'files/' directory. Format is choosen by extension: '.tmx' (XML) or '.json'/'.tmj' (JSON export). Example: 'load_map("level.tmx")'
+#[cfg(target_os = "psp")]
+pub unsafe fn load_map(name: &str) -> Result<TiledMap, TiledError> {
+    let mut path = String::from(ASSETS_DIR);
+    path.push_str(name);
+
+    let bytes = file_system::read_file(&path).map_err(TiledError::Io)?;
+    let src = String::from_utf8(bytes).map_err(|_| TiledError::NotText)?;
+
+    if name.ends_with(".tmx") {
+        parse_tmx(&src)
+    } else if name.ends_with(".json") || name.ends_with(".tmj") {
+        parse_json(&src)
+    } else {
+        Err(TiledError::UnknownFormat)
+    }
+}
+
+/// Decode layer data written as text ('encoding' is 'csv' or 'base64') into global tile ids
+fn decode_data(encoding: Option<&str>, compression: Option<&str>, text: &str) -> Result<Vec<u32>, TiledError> {
+    if compression.is_some_and(|compression| !compression.is_empty()) {
+        return Err(TiledError::UnsupportedCompression);
+    }
+
+    match encoding {
+        Some("csv") => text
+            .split(',')
+            .map(|id| id.trim().parse().map_err(|_| TiledError::Invalid("data")))
+            .collect(),
+        Some("base64") => {
+            let bytes = decode_base64(text).ok_or(TiledError::Invalid("data"))?;
+            if bytes.len() % 4 != 0 {
+                return Err(TiledError::Invalid("data"));
+            }
+
+            // Each id is stored as little endian 32-bit number
+            Ok(bytes.chunks_exact(4).map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]])).collect())
+        },
+        _ => Err(TiledError::Invalid("encoding"))
+    }
+}
+
+/// Decode base64 text (whitespaces are skipped)
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    // Bits which didn't form whole byte yet
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\t' | b'\n' | b'\r' => continue,
+            _ => return None
+        };
+
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Create layer from global tile ids (flip flags are removed because 'Tilemap' doesn't support flipped tiles)
+fn tile_layer(gids: &[u32], width: u16, height: u16, parallax: (f32, f32)) -> Result<TileLayer, TiledError> {
+    let cells = gids
+        .iter()
+        .map(|gid| u16::try_from(gid & !FLIP_FLAGS).map_err(|_| TiledError::TileOutOfRange))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut layer = TileLayer::from_cells(width, height, cells).ok_or(TiledError::Invalid("data"))?;
+    layer.parallax = parallax;
+
+    Ok(layer)
+}
+
+/// Convert Tiled color ('#RRGGBB' or '#AARRGGBB') into 'rgba' form
+fn parse_color(text: &str) -> Option<u32> {
+    let hex = text.trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16).ok()?;
+
+    let (a, rgb) = match hex.len() {
+        6 => (255, value),
+        8 => ((value >> 24) as u8, value & 0xff_ffff),
+        _ => return None
+    };
+
+    Some(rgba((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, a))
+}
+
+/// Convert property written as text into value of specified Tiled type
+fn property_value(kind: &str, text: &str) -> Result<PropertyValue, TiledError> {
+    Ok(match kind {
+        "int" => PropertyValue::Int(text.parse().map_err(|_| TiledError::Invalid("property"))?),
+        "float" => PropertyValue::Float(text.parse().map_err(|_| TiledError::Invalid("property"))?),
+        "bool" => PropertyValue::Bool(text == "true"),
+        // Empty color means "not set"
+        "color" => PropertyValue::Color(if text.is_empty() { 0 } else { parse_color(text).ok_or(TiledError::Invalid("property"))? }),
+        "file" => PropertyValue::File(text.to_string()),
+        "object" => PropertyValue::Object(text.parse().map_err(|_| TiledError::Invalid("property"))?),
+        _ => PropertyValue::String(text.to_string())
+    })
+}
+
+/// Parse '(x,y) (x,y)' points list used by TMX polygons
+fn parse_points(text: &str) -> Result<Vec<(f32, f32)>, TiledError> {
+    text.split_whitespace()
+        .map(|point| {
+            let (x, y) = point.split_once(',').ok_or(TiledError::Invalid("points"))?;
+            Ok((x.parse().map_err(|_| TiledError::Invalid("points"))?, y.parse().map_err(|_| TiledError::Invalid("points"))?))
+        })
+        .collect()
+}
+
+// ---------------- TMX (XML) ----------------
+
+/// Parse content of '.tmx' file
+pub fn parse_tmx(src: &str) -> Result<TiledMap, TiledError> {
+    let root = xml::parse(src).map_err(TiledError::Xml)?;
+    if root.name != "map" {
+        return Err(TiledError::Invalid("map"));
+    }
+    if root.attribute("infinite") == Some("1") {
+        return Err(TiledError::InfiniteMap);
+    }
+
+    let mut map = TiledMap {
+        width: attr(&root, "width")?,
+        height: attr(&root, "height")?,
+        tile_width: attr(&root, "tilewidth")?,
+        tile_height: attr(&root, "tileheight")?,
+        tilesets: Vec::new(),
+        layers: Vec::new(),
+        object_groups: Vec::new(),
+        properties: tmx_properties(&root)?,
+    };
+
+    for tileset in root.children_named("tileset") {
+        map.tilesets.push(tmx_tileset(tileset)?);
+    }
+    tmx_layers(&root, (1.0, 1.0), &mut map)?;
+
+    Ok(map)
+}
+
+/// Required attribute converted into number
+fn attr<T: core::str::FromStr>(element: &Element, name: &'static str) -> Result<T, TiledError> {
+    element.attribute(name).and_then(|value| value.parse().ok()).ok_or(TiledError::Invalid(name))
+}
+
+/// Optional attribute converted into number
+fn attr_or<T: core::str::FromStr>(element: &Element, name: &'static str, default: T) -> Result<T, TiledError> {
+    match element.attribute(name) {
+        Some(value) => value.parse().map_err(|_| TiledError::Invalid(name)),
+        None => Ok(default)
+    }
+}
+
+fn tmx_properties(element: &Element) -> Result<Properties, TiledError> {
+    let mut properties = Properties::default();
+
+    if let Some(list) = element.child("properties") {
+        for property in list.children_named("property") {
+            let name = property.attribute("name").ok_or(TiledError::Invalid("name"))?;
+            // Multiline strings are stored as element text instead of 'value' attribute
+            let text = property.attribute("value").unwrap_or(&property.text);
+            let value = property_value(property.attribute("type").unwrap_or("string"), text)?;
+
+            properties.0.push((name.to_string(), value));
+        }
+    }
+
+    Ok(properties)
+}
+
+fn tmx_tileset(element: &Element) -> Result<TiledTileset, TiledError> {
+    let mut tileset = TiledTileset {
+        first_gid: attr(element, "firstgid")?,
+        source: element.attribute("source").map(String::from),
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        image: element.child("image").and_then(|image| image.attribute("source")).map(String::from),
+        tile_width: attr_or(element, "tilewidth", 0)?,
+        tile_height: attr_or(element, "tileheight", 0)?,
+        columns: attr_or(element, "columns", 0)?,
+        tile_count: attr_or(element, "tilecount", 0)?,
+        animations: Vec::new(),
+    };
+
+    for tile in element.children_named("tile") {
+        if let Some(animation) = tile.child("animation") {
+            let frames = animation
+                .children_named("frame")
+                .map(|frame| Ok((attr(frame, "tileid")?, attr(frame, "duration")?)))
+                .collect::<Result<Vec<_>, TiledError>>()?;
+
+            tileset.animations.push((attr(tile, "id")?, frames));
+        }
+    }
+
+    Ok(tileset)
+}
+
+/// Read layers of map or group layer. 'parallax' is parallax of parent group
+fn tmx_layers(parent: &Element, parallax: (f32, f32), map: &mut TiledMap) -> Result<(), TiledError> {
+    for element in parent.children.iter() {
+        let parallax = (parallax.0 * attr_or(element, "parallaxx", 1.0)?, parallax.1 * attr_or(element, "parallaxy", 1.0)?);
+
+        match element.name.as_str() {
+            "layer" => {
+                let data = element.child("data").ok_or(TiledError::Invalid("data"))?;
+                if data.child("chunk").is_some() {
+                    return Err(TiledError::InfiniteMap);
+                }
+
+                let gids = match data.attribute("encoding") {
+                    // Deprecated XML format: one '<tile gid="..."/>' element per cell
+                    None => data.children_named("tile").map(|tile| attr_or(tile, "gid", 0)).collect::<Result<Vec<_>, _>>()?,
+                    encoding => decode_data(encoding, data.attribute("compression"), &data.text)?,
+                };
+
+                map.layers.push(TiledLayer {
+                    name: element.attribute("name").unwrap_or_default().to_string(),
+                    visible: element.attribute("visible") != Some("0"),
+                    tiles: tile_layer(&gids, attr(element, "width")?, attr(element, "height")?, parallax)?,
+                    properties: tmx_properties(element)?,
+                });
+            },
+            "objectgroup" => {
+                map.object_groups.push(ObjectGroup {
+                    name: element.attribute("name").unwrap_or_default().to_string(),
+                    objects: element.children_named("object").map(tmx_object).collect::<Result<_, _>>()?,
+                    properties: tmx_properties(element)?,
+                });
+            },
+            "group" => tmx_layers(element, parallax, map)?,
+            // Image layers and other elements aren't used
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn tmx_object(element: &Element) -> Result<MapObject, TiledError> {
+    let shape = if let Some(gid) = element.attribute("gid") {
+        ObjectShape::Tile(gid.parse::<u32>().map_err(|_| TiledError::Invalid("gid"))? & !FLIP_FLAGS)
+    } else if element.child("ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if element.child("point").is_some() {
+        ObjectShape::Point
+    } else if let Some(polygon) = element.child("polygon") {
+        ObjectShape::Polygon(parse_points(polygon.attribute("points").unwrap_or_default())?)
+    } else if let Some(polyline) = element.child("polyline") {
+        ObjectShape::Polyline(parse_points(polyline.attribute("points").unwrap_or_default())?)
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    Ok(MapObject {
+        id: attr_or(element, "id", 0)?,
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        kind: element.attribute("class").or(element.attribute("type")).unwrap_or_default().to_string(),
+        x: attr_or(element, "x", 0.0)?,
+        y: attr_or(element, "y", 0.0)?,
+        width: attr_or(element, "width", 0.0)?,
+        height: attr_or(element, "height", 0.0)?,
+        rotation: attr_or(element, "rotation", 0.0)?,
+        shape,
+        properties: tmx_properties(element)?,
+    })
+}
+
+// ---------------- JSON export ----------------
+
+/// Parse content of map exported as JSON ('.json' or '.tmj' file)
+pub fn parse_json(src: &str) -> Result<TiledMap, TiledError> {
+    let root = json::parse(src).map_err(TiledError::Json)?;
+    if root.get("infinite").and_then(Value::as_bool) == Some(true) {
+        return Err(TiledError::InfiniteMap);
+    }
+
+    let mut map = TiledMap {
+        width: integer(&root, "width")?,
+        height: integer(&root, "height")?,
+        tile_width: integer(&root, "tilewidth")?,
+        tile_height: integer(&root, "tileheight")?,
+        tilesets: Vec::new(),
+        layers: Vec::new(),
+        object_groups: Vec::new(),
+        properties: json_properties(&root)?,
+    };
+
+    for tileset in root.get("tilesets").and_then(Value::as_array).unwrap_or_default() {
+        map.tilesets.push(json_tileset(tileset)?);
+    }
+    json_layers(&root, (1.0, 1.0), &mut map)?;
+
+    Ok(map)
+}
+
+/// Required number member
+fn number(value: &Value, key: &'static str) -> Result<f64, TiledError> {
+    value.get(key).and_then(Value::as_f64).ok_or(TiledError::Invalid(key))
+}
+
+/// Optional number member
+fn number_or(value: &Value, key: &'static str, default: f64) -> f64 {
+    value.get(key).and_then(Value::as_f64).unwrap_or(default)
+}
+
+/// Convert JSON number into integer type. Negative, fractional and too big numbers are rejected
+fn to_integer<T: TryFrom<u64>>(number: f64, key: &'static str) -> Result<T, TiledError> {
+    // 'fract' of infinity is NaN, so infinite numbers are rejected too
+    if number < 0.0 || number.fract() != 0.0 || number >= u64::MAX as f64 {
+        return Err(TiledError::Invalid(key));
+    }
+
+    T::try_from(number as u64).map_err(|_| TiledError::Invalid(key))
+}
+
+/// Required integer member
+fn integer<T: TryFrom<u64>>(value: &Value, key: &'static str) -> Result<T, TiledError> {
+    to_integer(number(value, key)?, key)
+}
+
+/// Optional integer member
+fn integer_or<T: TryFrom<u64>>(value: &Value, key: &'static str, default: T) -> Result<T, TiledError> {
+    match value.get(key).and_then(Value::as_f64) {
+        Some(number) => to_integer(number, key),
+        None => Ok(default)
+    }
+}
+
+/// Optional string member ("" when missing)
+fn text<'a>(value: &'a Value, key: &str) -> &'a str {
+    value.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn json_properties(value: &Value) -> Result<Properties, TiledError> {
+    let mut properties = Properties::default();
+
+    for property in value.get("properties").and_then(Value::as_array).unwrap_or_default() {
+        let name = property.get("name").and_then(Value::as_str).ok_or(TiledError::Invalid("name"))?;
+        let kind = property.get("type").and_then(Value::as_str).unwrap_or("string");
+
+        // Values have got JSON types, so they are converted into text and parsed the same way as TMX values
+        let text = match property.get("value") {
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Bool(value)) => value.to_string(),
+            // Fractional or too big numbers of integer types fail to parse instead of being truncated
+            Some(Value::Number(number)) => number.to_string(),
+            // Custom class properties (JSON objects) aren't supported
+            _ => continue
+        };
+
+        properties.0.push((name.to_string(), property_value(kind, &text)?));
+    }
+
+    Ok(properties)
+}
+
+fn json_tileset(value: &Value) -> Result<TiledTileset, TiledError> {
+    let mut tileset = TiledTileset {
+        first_gid: integer(value, "firstgid")?,
+        source: value.get("source").and_then(Value::as_str).map(String::from),
+        name: text(value, "name").to_string(),
+        image: value.get("image").and_then(Value::as_str).map(String::from),
+        tile_width: integer_or(value, "tilewidth", 0)?,
+        tile_height: integer_or(value, "tileheight", 0)?,
+        columns: integer_or(value, "columns", 0)?,
+        tile_count: integer_or(value, "tilecount", 0)?,
+        animations: Vec::new(),
+    };
+
+    for tile in value.get("tiles").and_then(Value::as_array).unwrap_or_default() {
+        if let Some(animation) = tile.get("animation").and_then(Value::as_array) {
+            let frames = animation
+                .iter()
+                .map(|frame| Ok((integer(frame, "tileid")?, integer(frame, "duration")?)))
+                .collect::<Result<Vec<_>, TiledError>>()?;
+
+            tileset.animations.push((integer(tile, "id")?, frames));
+        }
+    }
+
+    Ok(tileset)
+}
+
+/// Read layers of map or group layer. 'parallax' is parallax of parent group
+fn json_layers(parent: &Value, parallax: (f32, f32), map: &mut TiledMap) -> Result<(), TiledError> {
+    for layer in parent.get("layers").and_then(Value::as_array).unwrap_or_default() {
+        let parallax = (parallax.0 * number_or(layer, "parallaxx", 1.0) as f32, parallax.1 * number_or(layer, "parallaxy", 1.0) as f32);
+
+        match text(layer, "type") {
+            "tilelayer" => {
+                if layer.get("chunks").is_some() {
+                    return Err(TiledError::InfiniteMap);
+                }
+
+                let gids = match layer.get("data") {
+                    // CSV encoding is exported as array of numbers
+                    Some(Value::Array(ids)) => ids.iter().map(|id| to_integer(id.as_f64().ok_or(TiledError::Invalid("data"))?, "data")).collect::<Result<Vec<_>, _>>()?,
+                    Some(Value::String(data)) => decode_data(layer.get("encoding").and_then(Value::as_str), layer.get("compression").and_then(Value::as_str), data)?,
+                    _ => return Err(TiledError::Invalid("data"))
+                };
+
+                map.layers.push(TiledLayer {
+                    name: text(layer, "name").to_string(),
+                    visible: layer.get("visible").and_then(Value::as_bool).unwrap_or(true),
+                    tiles: tile_layer(&gids, integer(layer, "width")?, integer(layer, "height")?, parallax)?,
+                    properties: json_properties(layer)?,
+                });
+            },
+            "objectgroup" => {
+                map.object_groups.push(ObjectGroup {
+                    name: text(layer, "name").to_string(),
+                    objects: layer.get("objects").and_then(Value::as_array).unwrap_or_default().iter().map(json_object).collect::<Result<_, _>>()?,
+                    properties: json_properties(layer)?,
+                });
+            },
+            "group" => json_layers(layer, parallax, map)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn json_points(value: &Value) -> Result<Vec<(f32, f32)>, TiledError> {
+    value
+        .as_array()
+        .unwrap_or_default()
+        .iter()
+        .map(|point| Ok((number(point, "x")? as f32, number(point, "y")? as f32)))
+        .collect()
+}
+
+fn json_object(value: &Value) -> Result<MapObject, TiledError> {
+    let shape = if let Some(gid) = value.get("gid").and_then(Value::as_f64) {
+        ObjectShape::Tile(to_integer::<u32>(gid, "gid")? & !FLIP_FLAGS)
+    } else if value.get("ellipse").and_then(Value::as_bool) == Some(true) {
+        ObjectShape::Ellipse
+    } else if value.get("point").and_then(Value::as_bool) == Some(true) {
+        ObjectShape::Point
+    } else if let Some(polygon) = value.get("polygon") {
+        ObjectShape::Polygon(json_points(polygon)?)
+    } else if let Some(polyline) = value.get("polyline") {
+        ObjectShape::Polyline(json_points(polyline)?)
+    } else {
+        ObjectShape::Rectangle
+    };
+
+    // Newer Tiled versions write 'class', older ones 'type'
+    let kind = value.get("class").or(value.get("type")).and_then(Value::as_str).unwrap_or_default();
+
+    Ok(MapObject {
+        id: integer_or(value, "id", 0)?,
+        name: text(value, "name").to_string(),
+        kind: kind.to_string(),
+        x: number_or(value, "x", 0.0) as f32,
+        y: number_or(value, "y", 0.0) as f32,
+        width: number_or(value, "width", 0.0) as f32,
+        height: number_or(value, "height", 0.0) as f32,
+        rotation: number_or(value, "rotation", 0.0) as f32,
+        shape,
+        properties: json_properties(value)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal JSON map with one 2x1 layer. 'extra' is inserted into the root object
+    fn json_map(extra: &str) -> String {
+        alloc::format!(r#"{{"width": 2, "height": 1, "tilewidth": 16, "tileheight": 16, {extra}
+            "layers": [{{"type": "tilelayer", "width": 2, "height": 1, "data": [1, 2]}}]}}"#)
+    }
+
+    #[test]
+    fn decode_layer_data() {
+        assert_eq!(decode_data(Some("csv"), None, "1, 2,\n0,3").unwrap(), [1, 2, 0, 3]);
+        assert_eq!(decode_data(Some("base64"), Some(""), " AQAAAAIAAIA=\n").unwrap(), [1, 0x8000_0002]);
+
+        assert!(matches!(decode_data(Some("csv"), None, "1,x"), Err(TiledError::Invalid("data"))));
+        // Length which isn't multiple of 4 bytes
+        assert!(matches!(decode_data(Some("base64"), None, "AQAA"), Err(TiledError::Invalid("data"))));
+        assert!(matches!(decode_data(Some("base64"), Some("zlib"), "AQAAAA=="), Err(TiledError::UnsupportedCompression)));
+        assert!(matches!(decode_data(Some("hex"), None, "01"), Err(TiledError::Invalid("encoding"))));
+    }
+
+    #[test]
+    fn base64() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("aGVs\n bG8h").unwrap(), b"hello!");
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert!(decode_base64("aGV*").is_none());
+    }
+
+    #[test]
+    fn parse_tmx_sample() {
+        let map = parse_tmx(include_str!("../../../files/level.tmx")).unwrap();
+
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (12, 6, 16, 16));
+        assert_eq!(map.properties.get("title"), Some(&PropertyValue::String("Example level".to_string())));
+        assert_eq!(map.properties.get("gravity"), Some(&PropertyValue::Float(9.81)));
+
+        let tileset = &map.tilesets[0];
+        assert_eq!((tileset.first_gid, tileset.columns, tileset.tile_count), (1, 4, 16));
+        assert_eq!(tileset.image.as_deref(), Some("tileset.png"));
+        assert_eq!(tileset.animations, [(12, alloc::vec![(12, 250), (13, 250), (14, 250), (15, 250)])]);
+
+        // Second layer comes from group and it's base64 encoded
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(map.layers[0].tiles.parallax, (0.5, 0.5));
+        assert_eq!(map.layers[0].tiles.cells()[..3], [1, 2, 1]);
+        assert_eq!(map.layers[1].name, "ground");
+        assert_eq!(map.layers[1].tiles.cells()[28..31], [9, 9, 9]);
+
+        let player = map.object("player").unwrap();
+        assert_eq!((player.kind.as_str(), player.x, player.y, &player.shape), ("spawn", 24.0, 64.0, &ObjectShape::Point));
+        let exit = map.object("exit").unwrap();
+        assert_eq!(exit.kind, "trigger");
+        assert_eq!(exit.properties.get("locked"), Some(&PropertyValue::Bool(false)));
+        assert_eq!(map.object("slope").unwrap().shape, ObjectShape::Polygon(alloc::vec![(0.0, 0.0), (32.0, -16.0), (32.0, 0.0)]));
+    }
+
+    #[test]
+    fn json_sample_matches_tmx() {
+        let tmx = parse_tmx(include_str!("../../../files/level.tmx")).unwrap();
+        let json = parse_json(include_str!("../../../files/level.json")).unwrap();
+
+        assert_eq!((json.width, json.height, json.tile_width, json.tile_height), (tmx.width, tmx.height, tmx.tile_width, tmx.tile_height));
+        assert_eq!(json.tilesets[0].animations, tmx.tilesets[0].animations);
+        assert_eq!(json.layers.len(), tmx.layers.len());
+        for (json, tmx) in json.layers.iter().zip(tmx.layers.iter()) {
+            assert_eq!(json.name, tmx.name);
+            assert_eq!(json.tiles.cells(), tmx.tiles.cells());
+            assert_eq!(json.tiles.parallax, tmx.tiles.parallax);
+        }
+        assert_eq!(json.objects().count(), tmx.objects().count());
+        for (json, tmx) in json.objects().zip(tmx.objects()) {
+            assert_eq!((json.id, &json.name, &json.kind, &json.shape), (tmx.id, &tmx.name, &tmx.kind, &tmx.shape));
+        }
+    }
+
+    #[test]
+    fn tmx_errors() {
+        assert!(matches!(parse_tmx("<tileset/>"), Err(TiledError::Invalid("map"))));
+        assert!(matches!(parse_tmx(r#"<map width="2" height="1" tilewidth="16" tileheight="16" infinite="1"/>"#), Err(TiledError::InfiniteMap)));
+        assert!(matches!(parse_tmx(r#"<map width="70000" height="1" tilewidth="16" tileheight="16"/>"#), Err(TiledError::Invalid("width"))));
+
+        // Layer size doesn't match amount of ids and gid doesn't fit into cell
+        let layer = |data: &str| alloc::format!(r#"<map width="2" height="1" tilewidth="16" tileheight="16"><layer width="2" height="1"><data encoding="csv">{data}</data></layer></map>"#);
+        assert!(parse_tmx(&layer("1,2")).is_ok());
+        assert!(matches!(parse_tmx(&layer("1,2,3")), Err(TiledError::Invalid("data"))));
+        assert!(matches!(parse_tmx(&layer("1,70000")), Err(TiledError::TileOutOfRange)));
+    }
+
+    #[test]
+    fn json_numbers_are_checked() {
+        let map = parse_json(&json_map(r#""properties": [{"name": "lives", "type": "int", "value": 3}],"#)).unwrap();
+        assert_eq!(map.layers[0].tiles.cells(), [1, 2]);
+        assert_eq!(map.properties.get("lives"), Some(&PropertyValue::Int(3)));
+
+        assert!(matches!(parse_json(r#"{"width": -1, "height": 1, "tilewidth": 16, "tileheight": 16}"#), Err(TiledError::Invalid("width"))));
+        assert!(matches!(parse_json(r#"{"width": 2.5, "height": 1, "tilewidth": 16, "tileheight": 16}"#), Err(TiledError::Invalid("width"))));
+        assert!(matches!(parse_json(r#"{"width": 65536, "height": 1, "tilewidth": 16, "tileheight": 16}"#), Err(TiledError::Invalid("width"))));
+        assert!(matches!(parse_json(&json_map(r#""tilesets": [{"firstgid": 1, "columns": -4}],"#)), Err(TiledError::Invalid("columns"))));
+        assert!(matches!(parse_json(&json_map(r#""tilesets": [{"firstgid": 1e10}],"#)), Err(TiledError::Invalid("firstgid"))));
+        assert!(matches!(parse_json(&json_map(r#""properties": [{"name": "lives", "type": "int", "value": 2.5}],"#)), Err(TiledError::Invalid("property"))));
+
+        let data = |data: &str| alloc::format!(r#"{{"width": 2, "height": 1, "tilewidth": 16, "tileheight": 16,
+            "layers": [{{"type": "tilelayer", "width": 2, "height": 1, "data": {data}}}]}}"#);
+        assert!(matches!(parse_json(&data("[1, -2]")), Err(TiledError::Invalid("data"))));
+        assert!(matches!(parse_json(&data("[1, 4294967296]")), Err(TiledError::Invalid("data"))));
+        assert!(matches!(parse_json(&data("[1, 70000]")), Err(TiledError::TileOutOfRange)));
+        // Flip flag of second id is removed
+        assert_eq!(parse_json(&data(r#""AQAAAAIAAIA=", "encoding": "base64""#)).unwrap().layers[0].tiles.cells(), [1, 2]);
+    }
+}
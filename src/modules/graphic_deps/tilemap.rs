@@ -0,0 +1,275 @@
+use alloc::vec::Vec;
+#[cfg(target_os = "psp")]
+use alloc::collections::BTreeMap;
+#[cfg(target_os = "psp")]
+use core::ffi::c_void;
+#[cfg(target_os = "psp")]
+use psp::sys::*;
+#[cfg(target_os = "psp")]
+use crate::examples::types_def::{Texture, Vertex};
+#[cfg(target_os = "psp")]
+use crate::render_state::{RenderState, StateCache, CullMode, BlendMode};
+
+/// Texture cut into tiles of the same size. Tiles are numbered from 0, row by row (left to right, top to bottom)
+#[cfg(target_os = "psp")]
+pub struct Tileset {
+    pub texture: Texture,
+    pub tile_width: u16,
+    pub tile_height: u16,
+    /// Amount of tiles in one row of texture. Tileset with 0 columns (or 0 tile size) has got no tiles, map using it isn't drawn
+    pub columns: u16,
+    /// Animations assigned to tiles. Every layer cell with key tile shows current animation frame instead
+    pub animations: BTreeMap<u16, TileAnimation>,
+}
+
+/// Tile which changes in time (water, torches)
+pub struct TileAnimation {
+    /// (tile, duration in seconds) shown one after another in a loop
+    pub frames: Vec<(u16, f32)>,
+}
+
+impl TileAnimation {
+    /// Tile shown in specified moment
+    fn frame_at(&self, time: f32) -> u16 {
+        let total: f32 = self.frames.iter().map(|(_, duration)| duration).sum();
+        if total <= 0.0 {
+            return self.frames.first().map(|(tile, _)| *tile).unwrap_or(0);
+        }
+
+        let mut moment = time % total;
+        for (tile, duration) in self.frames.iter() {
+            if moment < *duration {
+                return *tile;
+            }
+            moment -= duration;
+        }
+
+        self.frames[self.frames.len() - 1].0
+    }
+}
+
+/// Grid of tiles. Cell value 0 means empty cell, other values are tile number + 1 (same convention as Tiled editor uses).
+/// Size and cells are private, so amount of cells always matches size
+pub struct TileLayer {
+    /// Width in tiles
+    width: u16,
+    /// Height in tiles
+    height: u16,
+    /// Cells row by row
+    cells: Vec<u16>,
+    /// How fast layer scrolls compared to camera (1.0 = normal, 0.5 = twice slower which makes layer look further away)
+    pub parallax: (f32, f32),
+}
+
+impl TileLayer {
+    /// Layer filled with empty cells
+    pub fn new(width: u16, height: u16) -> Self {
+        Self { width, height, cells: alloc::vec![0; width as usize * height as usize], parallax: (1.0, 1.0) }
+    }
+
+    /// Layer with specified cells (row by row). None when amount of cells doesn't match size
+    pub fn from_cells(width: u16, height: u16, cells: Vec<u16>) -> Option<Self> {
+        if cells.len() != width as usize * height as usize {
+            return None;
+        }
+
+        Some(Self { width, height, cells, parallax: (1.0, 1.0) })
+    }
+
+    /// Width in tiles
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Height in tiles
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Cells row by row
+    pub fn cells(&self) -> &[u16] {
+        &self.cells
+    }
+
+    /// Cell value, None for cells outside of layer
+    pub fn get(&self, x: u16, y: u16) -> Option<u16> {
+        if x < self.width && y < self.height {
+            Some(self.cells[y as usize * self.width as usize + x as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Set cell value (tile number + 1 or 0 to clear cell). Cells outside of layer are ignored
+    pub fn set(&mut self, x: u16, y: u16, cell: u16) {
+        if x < self.width && y < self.height {
+            self.cells[y as usize * self.width as usize + x as usize] = cell;
+        }
+    }
+}
+
+/// 2D camera which smoothly follows target point. Position is the top-left corner of visible area in world pixels
+pub struct Camera2D {
+    pub x: f32,
+    pub y: f32,
+    /// Size of visible area in pixels
+    pub view_width: f32,
+    pub view_height: f32,
+    /// How fast camera reaches target (greater = faster, 0 = camera doesn't move)
+    pub smoothing: f32,
+}
+
+impl Camera2D {
+    pub fn new(view_width: f32, view_height: f32) -> Self {
+        Self { x: 0.0, y: 0.0, view_width, view_height, smoothing: 6.0 }
+    }
+
+    /// Move camera towards position where target point is in the middle of view. 'delta' = seconds since previous frame
+    pub fn follow(&mut self, target: (f32, f32), delta: f32) {
+        let goal_x = target.0 - self.view_width / 2.0;
+        let goal_y = target.1 - self.view_height / 2.0;
+        // Part of remaining distance which is passed during this frame
+        let step = (self.smoothing * delta).clamp(0.0, 1.0);
+
+        self.x += (goal_x - self.x) * step;
+        self.y += (goal_y - self.y) * step;
+    }
+
+    /// Don't show anything outside of area with specified size (in pixels)
+    pub fn clamp_to(&mut self, width: f32, height: f32) {
+        self.x = self.x.clamp(0.0, (width - self.view_width).max(0.0));
+        self.y = self.y.clamp(0.0, (height - self.view_height).max(0.0));
+    }
+}
+
+/// Layers sharing one tileset
+#[cfg(target_os = "psp")]
+pub struct Tilemap {
+    pub tileset: Tileset,
+    /// Layers drawn from first (bottom) to last (top)
+    pub layers: Vec<TileLayer>,
+    /// Time used by tile animations
+    time: f32,
+}
+
+#[cfg(target_os = "psp")]
+impl Tilemap {
+    pub fn new(tileset: Tileset) -> Self {
+        Self { tileset, layers: Vec::new(), time: 0.0 }
+    }
+
+    /// Map size in pixels (size of the biggest layer)
+    pub fn pixel_size(&self) -> (f32, f32) {
+        let width = self.layers.iter().map(|layer| layer.width).max().unwrap_or(0);
+        let height = self.layers.iter().map(|layer| layer.height).max().unwrap_or(0);
+
+        (width as f32 * self.tileset.tile_width as f32, height as f32 * self.tileset.tile_height as f32)
+    }
+
+    /// Advance tile animations by 'delta' seconds
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+    }
+
+    /// Draw visible part of all layers. Each layer is drawn with one 'Sprites' draw call. Must be called inside started frame
+    pub unsafe fn draw(&self, camera: &Camera2D, states: &mut StateCache) {
+        let Texture { bytes, width, height, tbw } = self.tileset.texture;
+        if self.tileset.columns == 0 || self.tileset.tile_width == 0 || self.tileset.tile_height == 0 {
+            return;
+        }
+
+        // Tiles with transparent pixels are cut by alpha test, so no blending is required
+        states.apply(&RenderState { blend: BlendMode::Opaque, alpha_test: Some((AlphaFunc::Greater, 0)), depth_test: false, depth_write: false, cull: CullMode::None, texture: true });
+        sceGuTexMode(TexturePixelFormat::Psm8888, 0, 0, 0);
+        sceGuTexImage(MipmapLevel::None, width, height, tbw, bytes);
+        sceGuTexFunc(TextureEffect::Replace, TextureColorComponent::Rgba);
+        // Nearest filter keeps pixel art sharp and doesn't mix pixels of neighbouring tiles
+        sceGuTexFilter(TextureFilter::Nearest, TextureFilter::Nearest);
+
+        for layer in self.layers.iter() {
+            self.draw_layer(layer, camera);
+        }
+    }
+
+    unsafe fn draw_layer(&self, layer: &TileLayer, camera: &Camera2D) {
+        let tile_w = self.tileset.tile_width as f32;
+        let tile_h = self.tileset.tile_height as f32;
+
+        // Layer scroll position with parallax applied
+        let scroll_x = camera.x * layer.parallax.0;
+        let scroll_y = camera.y * layer.parallax.1;
+
+        // Range of visible cells (one more cell in each direction for partially visible tiles)
+        let first_x = ((scroll_x / tile_w) as i32).max(0);
+        let first_y = ((scroll_y / tile_h) as i32).max(0);
+        let last_x = (((scroll_x + camera.view_width) / tile_w) as i32 + 1).min(layer.width as i32);
+        let last_y = (((scroll_y + camera.view_height) / tile_h) as i32 + 1).min(layer.height as i32);
+        if first_x >= last_x || first_y >= last_y {
+            return;
+        }
+
+        // Memory for the worst case (all visible cells aren't empty) is taken from display list
+        let max_sprites = ((last_x - first_x) * (last_y - first_y)) as usize;
+        let vertices = sceGuGetMemory((max_sprites * 2 * core::mem::size_of::<Vertex>()) as i32) as *mut Vertex;
+        let mut count = 0;
+
+        for cell_y in first_y..last_y {
+            for cell_x in first_x..last_x {
+                let cell = layer.cells[cell_y as usize * layer.width as usize + cell_x as usize];
+                if cell == 0 {
+                    continue;
+                }
+
+                let tile = match self.tileset.animations.get(&(cell - 1)) {
+                    Some(animation) => animation.frame_at(self.time),
+                    None => cell - 1
+                };
+
+                // Texture coordinates are in texels because sprites are drawn in 2D mode
+                let u = (tile % self.tileset.columns) as f32 * tile_w;
+                let v = (tile / self.tileset.columns) as f32 * tile_h;
+                let x = cell_x as f32 * tile_w - scroll_x;
+                let y = cell_y as f32 * tile_h - scroll_y;
+
+                *vertices.add(count) = Vertex { u, v, color: 0xffff_ffff, x, y, z: 0.0 };
+                *vertices.add(count + 1) = Vertex { u: u + tile_w, v: v + tile_h, color: 0xffff_ffff, x: x + tile_w, y: y + tile_h, z: 0.0 };
+                count += 2;
+            }
+        }
+
+        if count > 0 {
+            sceGuDrawArray(GuPrimitive::Sprites, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, count as i32, core::ptr::null(), vertices as *const c_void);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_match_size() {
+        assert!(TileLayer::from_cells(2, 2, alloc::vec![1, 2, 3]).is_none());
+
+        let mut layer = TileLayer::from_cells(2, 2, alloc::vec![1, 2, 3, 4]).unwrap();
+        assert_eq!((layer.width(), layer.height()), (2, 2));
+        assert_eq!(layer.get(1, 1), Some(4));
+        assert_eq!(layer.get(2, 0), None);
+
+        // Cells outside of layer are ignored
+        layer.set(0, 1, 7);
+        layer.set(0, 2, 8);
+        assert_eq!(layer.cells(), [1, 2, 7, 4]);
+        assert_eq!(TileLayer::new(3, 2).cells(), [0; 6]);
+    }
+
+    #[test]
+    fn animation_loops() {
+        let animation = TileAnimation { frames: alloc::vec![(4, 0.25), (5, 0.5)] };
+
+        assert_eq!(animation.frame_at(0.0), 4);
+        assert_eq!(animation.frame_at(0.3), 5);
+        assert_eq!(animation.frame_at(0.8), 4);
+        assert_eq!(TileAnimation { frames: alloc::vec![(6, 0.0)] }.frame_at(1.0), 6);
+    }
+}
@@ -1,23 +1,55 @@
 use core::ffi::{c_void, c_short};
+use alloc::string::String;
 use psp::sys::*;
-use psp::{vram_alloc::get_vram_allocator, Align16};
-use embedded_graphics::{prelude::*, primitives::*, pixelcolor::Rgb888};
+use psp::{vram_alloc::{get_vram_allocator, SimpleVramAllocator, VramMemChunk}, Align16};
+use embedded_graphics::{prelude::*, primitives::*, pixelcolor::Rgb888, mono_font::{ascii::FONT_6X10, MonoTextStyle}, text::Text};
 use psp::embedded_graphics::Framebuffer;
 use crate::shapes::*;
-use crate::examples::types_def::Texture;
+use crate::camera::{Camera, OrbitController, FirstPersonController};
+use crate::obj_loader;
+use crate::lighting::{Lighting, Light, Material};
+use crate::render_state::{RenderState, StateCache, CullMode};
+use crate::render_target::RenderTarget;
+use crate::particles::{ParticleSystem, EmitterConfig, EmitterShape, Curve};
+use crate::render_state::BlendMode;
+use crate::time;
+use crate::game_loop::{self, GameLoop};
+use crate::tilemap::{Tilemap, Tileset, TileLayer, TileAnimation, Camera2D};
+use crate::tiled;
+use crate::screenshot::{self, ImageFormat};
+use crate::debug_draw;
+use crate::ge_target::GeDrawTarget;
+use crate::texture_canvas::TextureCanvas;
+use crate::antialias::AaPainter;
+use crate::call_list::CallList;
+use crate::cursor_sprite::CursorSprite;
+use crate::input_manager::{InputManager, PspSampler};
+use crate::osk::{Osk, OskConfig, OskResult};
+use crate::cursor::{Cursor, CursorEvent, Widget};
+use crate::user_inputs;
+use crate::examples::types_def::{Texture, Vertex};
 
 use super::types_def::{Dimension};
 
 /// Width for PSP Buffer (must accumulate the nearest 'double' amount greater then 'PSP_SCR_WIDTH' to swear all guarantes)
-const PSP_BUF_WIDTH: u16 = 512;
+pub(crate) const PSP_BUF_WIDTH: u16 = 512;
 /// PSP Screen width in pixels
-const PSP_SCR_WIDTH: u16 = 490;
+pub(crate) const PSP_SCR_WIDTH: u16 = 490;
 /// PSP Screen Height in pixels
-const PSP_SCR_HEIGHT: u16 = 272;
+pub(crate) const PSP_SCR_HEIGHT: u16 = 272;
 
 // List which stores graphic command which next will be send to GPU to be executed in determined by itself direction
 static mut GRP_LIST: Align16<[u8; 0x40000]> = Align16([0; 0x40000]);
 
+// VRAM allocator taken by 'init_graphic'. It is kept to allocate more VRAM buffers later (e.g. for render targets) because it can be obtained only once
+static mut VRAM: Option<SimpleVramAllocator> = None;
+
+// Pixels of tileset generated by 'draw_tilemap' example (64x64 pixels = 4x4 tiles with 16x16 pixels each)
+static mut TILESET_PIXELS: Align16<[u32; 64 * 64]> = Align16([0; 64 * 64]);
+
+// Draw buffer which currently receives screen drawing (it changes after each 'sceGuSwapBuffers' call)
+static mut DRAW_BUFFER: *mut c_void = core::ptr::null_mut();
+
 /// Only as example: Calculated required memory size (MB = Megabytes) for VRAM (Video Ram = Graphic card Ram) Buffer size
 #[allow(dead_code)]
 fn get_memory_size(width: u16, height: u16, psm: TexturePixelFormat) -> u32 {
@@ -67,12 +99,17 @@ unsafe fn init_graphic() {
     let allocator = get_vram_allocator().unwrap();
 
     // This is draw buffer (to draw something to the end before it will be displaying to user PSP screen)
-    let buf0 = allocator.alloc_texture_pixels(PSP_BUF_WIDTH as u32, PSP_SCR_HEIGHT as u32, TexturePixelFormat::PsmT4).as_mut_ptr_from_zero();
+    // Size must match 'Psm8888' format used by 'sceGuDrawBuffer' otherwise next buffers would overlap this one
+    let buf0 = allocator.alloc_texture_pixels(PSP_BUF_WIDTH as u32, PSP_SCR_HEIGHT as u32, TexturePixelFormat::Psm8888).as_mut_ptr_from_zero();
     // This is displaying buffer (to display result of drawing to the screen of PSP). Displaying is performing by swaping 'buf1' content with contented graphic stored actualy in 'buf0'
-    let buf1 = allocator.alloc_texture_pixels(PSP_BUF_WIDTH as u32, PSP_SCR_HEIGHT as u32, TexturePixelFormat::PsmT4).as_mut_ptr_from_zero();
+    let buf1 = allocator.alloc_texture_pixels(PSP_BUF_WIDTH as u32, PSP_SCR_HEIGHT as u32, TexturePixelFormat::Psm8888).as_mut_ptr_from_zero();
     // This is to obtain static VRAM Buffer
     let zbuf = allocator.alloc_texture_pixels(PSP_BUF_WIDTH as u32, PSP_SCR_HEIGHT as u32, TexturePixelFormat::Psm4444).as_mut_ptr_from_zero();
 
+    // Keep allocator and current draw buffer for later usage
+    DRAW_BUFFER = buf0 as *mut c_void;
+    VRAM = Some(allocator);
+
     // Init graphic as first step of graphic creation and displaying it
     sceGuInit();
 
@@ -101,11 +138,11 @@ unsafe fn init_graphic() {
     sceGuEnable(GuState::ScissorTest);
     sceGuScissor(0, 0, PSP_SCR_WIDTH.into(), PSP_SCR_HEIGHT.into());
 
-    // Enable and Select depth-test function
+    // Enable and Select depth-test function. Due to inversed depth range closer pixels have got greater depth value so they should pass over further ones
     sceGuEnable(GuState::DepthTest);
-    sceGuDepthFunc(DepthFunc::Equal);
+    sceGuDepthFunc(DepthFunc::GreaterOrEqual);
 
-    // Setup current front-face order
+    // Setup current front-face order. Triangles defined clockwise (when seen from outside of model) are front faces, other ones are removed by culling
     sceGuEnable(GuState::CullFace);
     sceGuFrontFace(FrontFaceDirection::Clockwise);
 
@@ -123,6 +160,35 @@ unsafe fn init_graphic() {
     sceGuDisplay(true); // enable PSP display to show graphic rendering status
 }
 
+/// Allocate VRAM buffer for pixels after buffers allocated by 'init_graphic'. Returns None when 'init_graphic' wasn't called yet or when VRAM
+/// doesn't have enough free space (allocator never frees memory and it would panic on overflow, so remaining space is checked first)
+pub(crate) unsafe fn alloc_vram(width: u32, height: u32, psm: TexturePixelFormat) -> Option<VramMemChunk<'static>> {
+    let allocator = VRAM.as_ref()?;
+
+    let bytes_per_pixel = match psm {
+        TexturePixelFormat::Psm5650 | TexturePixelFormat::Psm5551 | TexturePixelFormat::Psm4444 => 2,
+        TexturePixelFormat::Psm8888 => 4,
+        _ => return None,
+    };
+    let size = width.checked_mul(height)?.checked_mul(bytes_per_pixel)?;
+
+    // Empty allocation tells where free part of VRAM starts
+    let used = allocator.alloc(0).as_mut_ptr_from_zero() as u32;
+    if size > sceGeEdramGetSize().saturating_sub(used) {
+        return None;
+    }
+
+    Some(allocator.alloc(size))
+}
+
+/// Redirect drawing back to the screen draw buffer (and restore screen sized viewport)
+pub(crate) unsafe fn bind_screen_buffer() {
+    sceGuDrawBufferList(DisplayPixelFormat::Psm8888, DRAW_BUFFER, PSP_BUF_WIDTH as i32);
+    sceGuOffset((2048 - (PSP_SCR_WIDTH / 2)) as u32, (2048  - (PSP_SCR_HEIGHT / 2)) as u32);
+    sceGuViewport(2048, 2048, PSP_SCR_WIDTH as i32, PSP_SCR_HEIGHT as i32);
+    sceGuScissor(0, 0, PSP_SCR_WIDTH.into(), PSP_SCR_HEIGHT.into());
+}
+
 /// To manage over **graphic rendering**
 struct GMng;
 impl GMng {
@@ -138,10 +204,17 @@ impl GMng {
 
     /// End existing frame by displaying it on PSP screen
     unsafe fn end_existing_frame() {
+        Self::end_frame_with_dialog(|| {});
+    }
+
+    /// End existing frame like 'end_existing_frame', but let system dialog (e.g. on-screen keyboard) draw itself over finished frame before it's displayed
+    unsafe fn end_frame_with_dialog(update_dialog: impl FnOnce()) {
+        debug_draw::flush(); // draw debug shapes queued during frame on top of everything
         sceGuFinish(); // finish current display list
         sceGuSync(GuSyncMode::Finish, GuSyncBehavior::Wait); // wait until GPU executes all commands list before send to execute new list with commands
+        update_dialog(); // dialogs draw directly into draw buffer, so GPU must be done with frame
         sceDisplayWaitVblank(); // wait until next avaiable screen Vsync frame
-        sceGuSwapBuffers(); // swap draw buffer with display buffer to show graphic rendering result on PSP screen
+        DRAW_BUFFER = sceGuSwapBuffers(); // swap draw buffer with display buffer to show graphic rendering result on PSP screen
     }
 
     // Loading texture and returning it as 'struct **Texture**' data type 'structure'
@@ -268,7 +341,267 @@ pub unsafe fn draw_shapes() -> () {
         .unwrap();
 }
 
-/// Draw shapes in Graphic context using raw 'sceGu' library for this
+/// The same triangle as in 'draw_shapes' drawn by 'embedded-graphics' (left) and with anti-aliased edges (right), plus other anti-aliased shapes blended over background
+pub unsafe fn draw_shapes_aa() {
+    let mut display = Framebuffer::new();
+    display.fill_solid(&Rectangle::new(Point::new(0, 0), Size::new(PSP_SCR_WIDTH.into(), PSP_SCR_HEIGHT.into())), Rgb888::new(23, 165, 85)).unwrap();
+
+    let jagged = [Point::new(40, 130), Point::new(140, 130), Point::new(90, 100)];
+    Triangle::new(jagged[0], jagged[1], jagged[2]).into_styled(PrimitiveStyle::with_fill(Rgb888::RED)).draw(&mut display).unwrap();
+
+    let mut painter = AaPainter::new(&mut display);
+    painter.fill_polygon(&[(170.0, 130.0), (270.0, 130.0), (220.0, 100.0)], Rgb888::RED);
+
+    // Lines moved by quarter of pixel show sub-pixel positioning
+    for i in 0..8 {
+        let offset = i as f32 * 0.25;
+        painter.line((300.0, 100.0 + i as f32 * 4.0 + offset), (460.0, 112.0 + i as f32 * 4.0 + offset), Rgb888::WHITE);
+    }
+    painter.thick_line((300.0, 150.0), (460.0, 170.0), 3.5, Rgb888::BLUE);
+
+    painter.circle((70.0, 200.0), 30.0, 2.0, Rgb888::YELLOW);
+    painter.fill_ellipse((170.0, 200.0), (50.0, 25.0), Rgb888::new(60, 60, 200));
+    painter.ellipse((170.0, 200.0), (50.0, 25.0), 1.5, Rgb888::WHITE);
+    painter.arc((280.0, 205.0), 30.0, -core::f32::consts::PI, core::f32::consts::PI * 1.25, 5.0, Rgb888::new(255, 140, 0));
+    painter.polygon(&[(340.0, 190.0), (380.0, 180.0), (420.0, 210.0), (370.0, 240.0), (350.0, 215.0)], Rgb888::BLACK);
+
+    // Half transparent circle blended over ellipse and triangle
+    painter.set_opacity(0.5);
+    painter.fill_circle((205.0, 150.0), 35.5, Rgb888::WHITE);
+}
+
+/// The same shapes as in 'draw_shapes' (plus circle and text) drawn by 'embedded-graphics' with GE backed draw target, together with native 'sceGu' sprite in the same frame
+pub unsafe fn draw_shapes_ge() {
+    init_graphic();
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+
+    let rectangle_basis_width = 100;
+    let start_point_x = (PSP_SCR_WIDTH / 2 - rectangle_basis_width / 2) as i32;
+    let end_point_x = start_point_x + rectangle_basis_width as i32;
+    let middle_point_x = start_point_x + rectangle_basis_width as i32 / 2;
+    let triangle = Triangle::new(Point::new(start_point_x, 130), Point::new(end_point_x, 130), Point::new(middle_point_x, 100));
+
+    while draw {
+        GMng::start_new_frame();
+
+        {
+            let mut display = GeDrawTarget::new(&mut states);
+            display.clear(Rgb888::new(23, 165, 85)).unwrap();
+
+            // Styled primitives are turned into sprites (one per scanline)
+            triangle.into_styled(PrimitiveStyle::with_fill(Rgb888::RED)).draw(&mut display).unwrap();
+            Circle::new(Point::new(60, 60), 80)
+                .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb888::YELLOW).stroke_color(Rgb888::BLACK).stroke_width(3).build())
+                .draw(&mut display)
+                .unwrap();
+
+            // The same triangle filled with single GE triangle
+            display.fill_triangle(&triangle.translate(Point::new(0, 60)), Rgb888::BLUE);
+
+            let text_style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
+            Text::new("embedded-graphics drawn by GE", Point::new(10, 200), text_style).draw(&mut display).unwrap();
+        } // target is dropped here, so everything is flushed before native draw
+
+        // Native half transparent sprite over 'embedded-graphics' shapes
+        states.apply(&RenderState::OVERLAY);
+        let vertices = sceGuGetMemory((2 * core::mem::size_of::<Vertex>()) as i32) as *mut Vertex;
+        *vertices = Vertex { u: 0.0, v: 0.0, color: rgba(255, 255, 255, 100), x: 300.0, y: 40.0, z: 0.0 };
+        *vertices.add(1) = Vertex { u: 0.0, v: 0.0, color: rgba(255, 255, 255, 100), x: 440.0, y: 160.0, z: 0.0 };
+        sceGuDrawArray(GuPrimitive::Sprites, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, 2, core::ptr::null(), vertices as *const c_void);
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// UI panel (frame, text and bar chart) drawn once by 'embedded-graphics' into texture and next drawn every frame as scaled and rotated sprite
+pub unsafe fn draw_texture_canvas() {
+    init_graphic();
+
+    let mut canvas = match TextureCanvas::new(256, 128, TexturePixelFormat::Psm8888) {
+        Some(canvas) => canvas,
+        None => {
+            psp::dprintln!("Wrong canvas size");
+            return;
+        }
+    };
+
+    // Compose panel only once
+    RoundedRectangle::with_equal_corners(Rectangle::new(Point::new(0, 0), Size::new(256, 128)), Size::new(12, 12))
+        .into_styled(PrimitiveStyleBuilder::new().fill_color(Rgb888::new(30, 40, 70)).stroke_color(Rgb888::WHITE).stroke_width(2).build())
+        .draw(&mut canvas)
+        .unwrap();
+    Text::new("Frame times", Point::new(12, 18), MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE)).draw(&mut canvas).unwrap();
+    for (i, value) in [30, 52, 41, 70, 65, 88, 47, 60, 95, 72].iter().enumerate() {
+        Rectangle::new(Point::new(16 + i as i32 * 23, 116 - value), Size::new(16, *value as u32))
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(80, 200 - *value as u8, 120)))
+            .draw(&mut canvas)
+            .unwrap();
+    }
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut timer = time::DeltaTimer::new();
+    let mut elapsed = 0.0f32;
+
+    while draw {
+        elapsed += timer.delta();
+
+        GMng::start_new_frame();
+
+        sceGuClearColor(rgba(9, 15, 40, 0));
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+
+        // The same texture drawn twice: still one and pulsing + rotating one
+        canvas.draw((140.0, 80.0), 0.75, 0.0, &mut states);
+        canvas.draw((320.0, 170.0), 0.8 + psp::math::sinf(elapsed * 2.0) * 0.2, elapsed * 0.5, &mut states);
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Text entry with system on-screen keyboard drawn over running frame loop. CROSS opens keyboard, entered text is shown on screen
+pub unsafe fn draw_text_input() {
+    init_graphic();
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut inputs = InputManager::new(PspSampler::new());
+    let mut keyboard: Option<Osk> = None;
+    let mut save_name = String::from("Player");
+    let mut message = String::from("Press CROSS to change save name");
+
+    while draw {
+        // Keyboard takes controller while it's opened
+        if keyboard.is_none() {
+            inputs.update();
+
+            if inputs.just_pressed(CtrlButtons::CROSS) {
+                let config = OskConfig::new("Save name").initial_text(&save_name).max_length(16);
+                match Osk::open(&config) {
+                    Ok(osk) => keyboard = Some(osk),
+                    Err(error) => message = alloc::format!("Keyboard couldn't be opened ({:#x})", error),
+                }
+            }
+        }
+
+        GMng::start_new_frame();
+
+        sceGuClearColor(rgba(9, 15, 40, 0));
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+
+        {
+            let mut display = GeDrawTarget::new(&mut states);
+            let style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
+            Text::new(&alloc::format!("Save name: {}", save_name), Point::new(20, 40), style).draw(&mut display).unwrap();
+            Text::new(&message, Point::new(20, 60), style).draw(&mut display).unwrap();
+        }
+
+        GMng::end_frame_with_dialog(|| {
+            if let Some(result) = keyboard.as_mut().and_then(|osk| osk.update()) {
+                match result {
+                    OskResult::Entered(text) => {
+                        message = String::from("Save name changed");
+                        save_name = text;
+                    },
+                    OskResult::Cancelled => message = String::from("Cancelled"),
+                }
+                keyboard = None;
+            }
+        });
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Pointer style UI: stick moves cursor (it snaps to near buttons), CROSS clicks buttons and drags tile. START ends example
+pub unsafe fn draw_pointer_ui() {
+    init_graphic();
+
+    let sprite = match CursorSprite::arrow() {
+        Some(sprite) => sprite,
+        None => {
+            psp::dprintln!("Cursor sprite couldn't be created");
+            return;
+        }
+    };
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut timer = time::DeltaTimer::new();
+    let mut inputs = InputManager::new(PspSampler::non_blocking());
+    let mut cursor = Cursor::new((PSP_SCR_WIDTH as f32, PSP_SCR_HEIGHT as f32));
+
+    let labels = ["Open", "Save", "Close"];
+    // Id of draggable tile (buttons have got ids of their labels)
+    const TILE: u32 = 10;
+    let mut tile = (200.0, 120.0);
+    let mut dragging_tile = false;
+    let mut message = String::from("Move cursor with stick, click with CROSS");
+
+    while draw {
+        inputs.update();
+        let delta = timer.delta();
+        if inputs.just_pressed(CtrlButtons::START) {
+            draw = false;
+        }
+
+        let mut widgets: [Widget; 4] = core::array::from_fn(|i| Widget::new(i as u32, 20.0 + i as f32 * 90.0, 20.0, 80.0, 24.0));
+        widgets[3] = Widget::new(TILE, tile.0, tile.1, 64.0, 64.0);
+
+        for event in cursor.update(&inputs, delta, &widgets) {
+            match *event {
+                CursorEvent::Click { widget: Some(id), .. } if (id as usize) < labels.len() => message = alloc::format!("{} clicked", labels[id as usize]),
+                CursorEvent::DragStarted { widget: Some(TILE), .. } => dragging_tile = true,
+                CursorEvent::Dragged { delta: (dx, dy), .. } if dragging_tile => tile = (tile.0 + dx, tile.1 + dy),
+                CursorEvent::DragEnded { to, .. } if dragging_tile => {
+                    dragging_tile = false;
+                    message = alloc::format!("Tile dropped at {:.0}, {:.0}", to.0, to.1);
+                },
+                _ => {},
+            }
+        }
+
+        GMng::start_new_frame();
+
+        {
+            let mut display = GeDrawTarget::new(&mut states);
+            display.clear(Rgb888::new(9, 15, 40)).unwrap();
+
+            let text_style = MonoTextStyle::new(&FONT_6X10, Rgb888::WHITE);
+            for widget in widgets.iter() {
+                let color = match (cursor.hovered() == Some(widget.id), widget.id == TILE) {
+                    (true, _) => Rgb888::new(90, 140, 230),
+                    (false, true) => Rgb888::new(200, 120, 60),
+                    (false, false) => Rgb888::new(50, 70, 120),
+                };
+                Rectangle::new(Point::new(widget.x as i32, widget.y as i32), Size::new(widget.width as u32, widget.height as u32))
+                    .into_styled(PrimitiveStyle::with_fill(color))
+                    .draw(&mut display)
+                    .unwrap();
+
+                if let Some(label) = labels.get(widget.id as usize) {
+                    Text::new(label, Point::new(widget.x as i32 + 8, widget.y as i32 + 15), text_style).draw(&mut display).unwrap();
+                }
+            }
+            Text::new(&message, Point::new(20, 250), text_style).draw(&mut display).unwrap();
+        }
+
+        // Cursor is drawn the last, over whole UI
+        sprite.draw(cursor.position, cursor.pressed().is_some(), &mut states);
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Draw shapes in Graphic context using raw 'sceGu' library for this. Static shapes are recorded once into call list, CROSS moves textured square (list is recorded again)
 #[allow(unused_mut)]
 pub unsafe fn draw_shapes_native() {
     init_graphic();
@@ -299,50 +632,676 @@ pub unsafe fn draw_shapes_native() {
     let tex_bytes = include_bytes!("../../files/texture.jpg");
     let Texture { bytes, width, height, tbw } = Texture::tex_load(tex_bytes, Dimension { w: 1299, h: 1300 });
 
+    // Render states used by shapes: flat colored ones, textured one and translucent panel drawn over everything
+    let mut states = StateCache::new();
+    let flat = RenderState { depth_test: false, ..RenderState::OPAQUE }; // texture must be disabled to show colors for objects rendered on screen
+    let textured = RenderState { texture: true, ..flat };
+
+    // All shapes are static, so their commands are recorded once and only called every frame. CROSS button moves textured square, so list must be recorded again.
+    // Shapes take under 2 KB of commands, so 8 KB capacity leaves enough margin
+    let mut static_shapes = CallList::new(8 * 1024).unwrap();
+    let mut textured_square_y = -0.45;
+    let mut previous_buttons = CtrlButtons::empty();
+
     while draw {
-        GMng::start_new_frame();
+        let (buttons, _) = user_inputs::read_controller();
+        if buttons.contains(CtrlButtons::CROSS) && !previous_buttons.contains(CtrlButtons::CROSS) {
+            textured_square_y = if textured_square_y < -0.4 { -0.35 } else { -0.45 };
+            static_shapes.invalidate();
+        }
+        previous_buttons = buttons;
 
-        // Disable some unnsessary things // Must be enabled for texture loading
-        sceGuDisable(GuState::DepthTest);
-        sceGuDisable(GuState::Texture2D); // This must be disabled to show colors for 3D objects rendered on screen
+        GMng::start_new_frame();
 
         // Apply color
         sceGuClearColor(rgba(9, 15, 129, 0));
         sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
 
-        // Draw shape of triangle
-        change_translate(-0.75, 0.15, 0f32);
-        sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 3, core::ptr::null(), &TRIANGLE as *const _ as *const c_void); // 2. attribure specifies what is using for rendering the whole graphic shape (drawning points with Vertex type)
-        
-        // Draw shape of straight triangle
-        change_translate(-0.75 + 0.35, -0.5, 0.0);
-        sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 3, core::ptr::null(), &TRIANGLE_2 as *const _ as *const c_void);
+        static_shapes.call_or_record(&mut states, |states| {
+            states.apply(&flat);
 
-        // Draw shape of square
-        change_translate(0.0, 0.3, 0f32);
-        sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &SQUARE as *const _ as *const c_void);
+            // Draw shape of triangle
+            change_translate(-0.75, 0.15, 0f32);
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 3, core::ptr::null(), &TRIANGLE as *const _ as *const c_void); // 2. attribure specifies what is using for rendering the whole graphic shape (drawning points with Vertex type)
 
-        // Draw shape of rectangle
-        change_translate(0.55, 0.45, 0f32);
-        sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &RECTANGLE as *const _ as *const c_void);
-        
-        // Draw shape of indexed rectangle
-        change_translate(0.55, -0.5 + 0.3 / 2f32, 0f32);
-        sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::INDEX_16BIT | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, &INDEXES_RECTANGLE as *const _ as *const c_void, &RECTANGLE_INDX as *const _ as *const c_void);
-        
-        // Draw square with assigned texture
-        sceGuEnable(GuState::Texture2D);
-        change_translate(0.0, -0.45, 0f32);
-        sceGuTexMode(TexturePixelFormat::Psm8888, 0, 0, 0);
-        sceGuTexImage(MipmapLevel::None, width, height, tbw, bytes);
-        sceGuTexFunc(TextureEffect::Replace, TextureColorComponent::Rgba);
-        sceGuTexFilter(TextureFilter::Linear, TextureFilter::Linear);
+            // Draw shape of straight triangle
+            change_translate(-0.75 + 0.35, -0.5, 0.0);
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 3, core::ptr::null(), &TRIANGLE_2 as *const _ as *const c_void);
+
+            // Draw shape of square
+            change_translate(0.0, 0.3, 0f32);
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &SQUARE as *const _ as *const c_void);
+
+            // Draw shape of rectangle
+            change_translate(0.55, 0.45, 0f32);
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &RECTANGLE as *const _ as *const c_void);
+
+            // Draw shape of indexed rectangle
+            change_translate(0.55, -0.5 + 0.3 / 2f32, 0f32);
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::INDEX_16BIT | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, &INDEXES_RECTANGLE as *const _ as *const c_void, &RECTANGLE_INDX as *const _ as *const c_void);
+
+            // Draw square with assigned texture
+            states.apply(&textured);
+            change_translate(0.0, textured_square_y, 0f32);
+            sceGuTexMode(TexturePixelFormat::Psm8888, 0, 0, 0);
+            sceGuTexImage(MipmapLevel::None, width, height, tbw, bytes);
+            sceGuTexFunc(TextureEffect::Replace, TextureColorComponent::Rgba);
+            sceGuTexFilter(TextureFilter::Linear, TextureFilter::Linear);
+
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TEXTURE_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &SQUARE as *const _ as *const c_void);
+
+            // Draw half transparent panel which blends with shapes under it
+            states.apply(&RenderState::OVERLAY);
+            change_translate(0.0, 0.0, 0f32);
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &TRANSLUCENT_PANEL as *const _ as *const c_void);
+        });
 
-        sceGumDrawArray(GuPrimitive::Triangles, VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TEXTURE_32BITF | VertexType::TRANSFORM_3D, 6, core::ptr::null(), &SQUARE as *const _ as *const c_void);
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Draw real 3D scene using perspective camera with depth testing and back-face culling. Bounding boxes, axes and camera mode are shown by debug overlay
+/// Analog stick moves camera, TRIANGLE button switches between orbit and first person camera controller
+pub unsafe fn draw_scene_3d() {
+    init_graphic();
+    user_inputs::setup_analog();
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut camera = Camera::default();
+    let mut orbit = OrbitController::default();
+    let mut first_person = FirstPersonController::default();
+    let mut orbit_mode = true;
+    let mut previous_buttons = CtrlButtons::empty();
+
+    // Position model in the world and draw it
+    let draw_model = |x: f32, y: f32, z: f32, vertices: &[Vertex]| {
+        sceGumMatrixMode(MatrixMode::Model);
+        sceGumLoadIdentity();
+        sceGumTranslate(&ScePspFVector3 { x, y, z });
+        sceGumDrawArray(GuPrimitive::Triangles, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, vertices.len() as i32, core::ptr::null(), vertices.as_ptr() as *const c_void);
+    };
+
+    while draw {
+        // Obtain input and update camera position with selected controller
+        let (buttons, stick) = user_inputs::read_controller();
+        if buttons.contains(CtrlButtons::TRIANGLE) && !previous_buttons.contains(CtrlButtons::TRIANGLE) {
+            orbit_mode = !orbit_mode;
+        }
+        previous_buttons = buttons;
+
+        if orbit_mode {
+            orbit.update(&mut camera, stick);
+        } else {
+            first_person.update(&mut camera, stick, buttons);
+        }
+
+        GMng::start_new_frame();
+
+        // 3D scene requires depth test, culling and no textures (colors are taken from vertices)
+        states.apply(&RenderState::OPAQUE);
+
+        // Clear color and depth (0 is the furthest depth value in inversed depth range)
+        sceGuClearColor(rgba(9, 15, 129, 0));
+        sceGuClearDepth(0);
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT | ClearBuffer::DEPTH_BUFFER_BIT);
+
+        camera.apply();
+
+        // Draw scene
+        draw_model(0.0, -0.5, 0.0, &FLOOR.0);
+        draw_model(0.0, 0.0, 0.0, &CUBE.0);
+        draw_model(1.5, 0.0, -1.5, &CUBE.0);
+        draw_model(-1.5, 0.0, -2.5, &CUBE.0);
 
+        // Debug overlay (drawn by 'end_existing_frame', disappears without 'debug-draw' feature)
+        debug_draw::axes(ScePspFVector3 { x: 0.0, y: 0.0, z: 0.0 }, 1.0);
+        for (x, z) in [(0.0, 0.0), (1.5, -1.5), (-1.5, -2.5)] {
+            debug_draw::aabb(ScePspFVector3 { x: x - 0.55, y: -0.55, z: z - 0.55 }, ScePspFVector3 { x: x + 0.55, y: 0.55, z: z + 0.55 }, rgba(255, 255, 0, 255));
+        }
+        debug_draw::text((4.0, 4.0), if orbit_mode { "Orbit camera" } else { "First person camera" }, rgba(255, 255, 255, 255));
 
         GMng::end_existing_frame();
     }
 
     GMng::terminate_graphics();
 }
+
+/// Draw 3D model loaded from Wavefront '.obj' file (stored in 'files/' directory) with orbit camera
+pub unsafe fn draw_obj_model() {
+    init_graphic();
+    user_inputs::setup_analog();
+
+    let model = match obj_loader::load_obj("cube.obj") {
+        Ok(model) => model,
+        Err(e) => {
+            psp::dprintln!("Couldn't load model: {e:?}");
+            return;
+        }
+    };
+    // Vertices were written by CPU to cached memory so they must be flushed before GE reads them
+    sceKernelDcacheWritebackAll();
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut camera = Camera::default();
+    let mut orbit = OrbitController::default();
+
+    while draw {
+        let (_, stick) = user_inputs::read_controller();
+        orbit.update(&mut camera, stick);
+
+        GMng::start_new_frame();
+
+        sceGuClearColor(rgba(9, 15, 129, 0));
+        sceGuClearDepth(0);
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT | ClearBuffer::DEPTH_BUFFER_BIT);
+
+        camera.apply();
+
+        // Each mesh has got own vertex list and indexes pointing to it. Textured materials are tinted by diffuse color from vertices
+        for mesh in model.meshes.iter() {
+            match model.texture(mesh) {
+                Some(texture) => {
+                    states.apply(&RenderState { texture: true, ..RenderState::OPAQUE });
+                    texture.bind();
+                },
+                None => states.apply(&RenderState::OPAQUE),
+            }
+
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::NORMAL_32BITF | VertexType::INDEX_16BIT | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, mesh.indices.len() as i32, mesh.indices.as_ptr() as *const c_void, mesh.vertices.as_ptr() as *const c_void);
+        }
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Draw 3D scene illuminated by hardware lights (directional, point and spot light) with different materials
+pub unsafe fn draw_lit_scene() {
+    init_graphic();
+    user_inputs::setup_analog();
+
+    // Generated and loaded meshes
+    let (sphere_vertices, sphere_indices) = match generate_sphere(0.6, 16, 24, rgba(210, 0, 238, 255)) {
+        Some(sphere) => sphere,
+        None => {
+            psp::dprintln!("Wrong sphere size");
+            return;
+        }
+    };
+    let model = match obj_loader::load_obj("cube.obj") {
+        Ok(model) => model,
+        Err(e) => {
+            psp::dprintln!("Couldn't load model: {e:?}");
+            return;
+        }
+    };
+    sceKernelDcacheWritebackAll();
+
+    // Setup lights
+    let mut lighting = Lighting::default();
+    lighting.lights[0] = Some(Light::Directional {
+        direction: ScePspFVector3 { x: 1.0, y: 1.0, z: 1.0 },
+        diffuse: rgba(180, 180, 180, 255),
+        specular: rgba(255, 255, 255, 255),
+    });
+    lighting.lights[1] = Some(Light::Point {
+        position: ScePspFVector3 { x: -2.0, y: 1.0, z: 0.0 },
+        diffuse: rgba(255, 60, 60, 255),
+        specular: rgba(255, 60, 60, 255),
+        attenuation: (1.0, 0.2, 0.0),
+    });
+    lighting.lights[2] = Some(Light::Spot {
+        position: ScePspFVector3 { x: 0.0, y: 3.0, z: 0.0 },
+        direction: ScePspFVector3 { x: 0.0, y: -1.0, z: 0.0 },
+        diffuse: rgba(60, 60, 255, 255),
+        specular: rgba(0, 0, 0, 255),
+        attenuation: (1.0, 0.0, 0.0),
+        exponent: 10.0,
+        cutoff: 0.8,
+    });
+
+    // Setup materials
+    let shiny = Material { specular: rgba(255, 255, 255, 255), shininess: 30.0, ..Material::default() };
+    let glowing = Material { emissive: rgba(40, 80, 40, 255), ..Material::default() };
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut camera = Camera::default();
+    let mut orbit = OrbitController::default();
+
+    while draw {
+        let (_, stick) = user_inputs::read_controller();
+        orbit.update(&mut camera, stick);
+
+        GMng::start_new_frame();
+
+        states.apply(&RenderState::OPAQUE);
+
+        sceGuClearColor(rgba(9, 15, 129, 0));
+        sceGuClearDepth(0);
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT | ClearBuffer::DEPTH_BUFFER_BIT);
+
+        camera.apply();
+        lighting.apply();
+
+        // Shiny generated sphere
+        shiny.apply();
+        sceGumMatrixMode(MatrixMode::Model);
+        sceGumLoadIdentity();
+        sceGumTranslate(&ScePspFVector3 { x: -0.8, y: 0.0, z: 0.0 });
+        sceGumDrawArray(GuPrimitive::Triangles, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::NORMAL_32BITF | VertexType::INDEX_16BIT | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, sphere_indices.len() as i32, sphere_indices.as_ptr() as *const c_void, sphere_vertices.as_ptr() as *const c_void);
+
+        // Glowing loaded model
+        glowing.apply();
+        sceGumLoadIdentity();
+        sceGumTranslate(&ScePspFVector3 { x: 0.8, y: 0.0, z: 0.0 });
+        for mesh in model.meshes.iter() {
+            sceGumDrawArray(GuPrimitive::Triangles, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::NORMAL_32BITF | VertexType::INDEX_16BIT | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D, mesh.indices.len() as i32, mesh.indices.as_ptr() as *const c_void, mesh.vertices.as_ptr() as *const c_void);
+        }
+
+        Lighting::disable();
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Draw shapes into offscreen render target and next use its content as texture of rotating squares on the screen (picture-in-picture)
+pub unsafe fn draw_render_to_texture() {
+    init_graphic();
+
+    let target = match RenderTarget::new(128, 128, DisplayPixelFormat::Psm8888) {
+        Some(target) => target,
+        None => {
+            psp::dprintln!("Render target couldn't be created (wrong size or not enough VRAM)");
+            return;
+        }
+    };
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut camera = Camera::default();
+    let mut angle = 0f32;
+    let flat = RenderState { depth_test: false, cull: CullMode::None, ..RenderState::OPAQUE };
+    let textured = RenderState { texture: true, cull: CullMode::None, ..RenderState::OPAQUE }; // both sides of rotating squares are visible
+    let vertex_type = VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_3D;
+
+    while draw {
+        angle += 0.02;
+
+        GMng::start_new_frame();
+
+        // 1. Draw scene into render target
+        target.begin();
+        states.apply(&flat);
+        sceGuClearColor(rgba(40, 40, 40, 255));
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+
+        sceGumMatrixMode(MatrixMode::Projection);
+        sceGumLoadIdentity();
+        sceGumOrtho(-1.0, 1.0, -1.0, 1.0, -10.0, 10.0);
+        sceGumMatrixMode(MatrixMode::View);
+        sceGumLoadIdentity();
+        sceGumMatrixMode(MatrixMode::Model);
+        sceGumLoadIdentity();
+        sceGumRotateZ(angle);
+        sceGumDrawArray(GuPrimitive::Triangles, vertex_type, 3, core::ptr::null(), &TRIANGLE as *const _ as *const c_void);
+        sceGumDrawArray(GuPrimitive::Triangles, vertex_type, 6, core::ptr::null(), &SQUARE as *const _ as *const c_void);
+        target.end();
+
+        // 2. Draw screen using render target content as texture
+        states.apply(&textured);
+        sceGuClearColor(rgba(9, 15, 129, 0));
+        sceGuClearDepth(0);
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT | ClearBuffer::DEPTH_BUFFER_BIT);
+
+        camera.apply();
+        target.bind();
+
+        for (x, scale) in [(-1.2f32, 1.0f32), (1.0, 1.6)] {
+            sceGumLoadIdentity();
+            sceGumTranslate(&ScePspFVector3 { x, y: 0.0, z: 0.0 });
+            sceGumRotateY(angle);
+            sceGumScale(&ScePspFVector3 { x: scale, y: scale, z: 1.0 });
+            sceGumDrawArray(GuPrimitive::Triangles, vertex_type, 6, core::ptr::null(), &TEXTURED_SQUARE as *const _ as *const c_void);
+        }
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Draw 3 particle effects: sparks (pressing CROSS makes explosion), smoke and falling snow
+pub unsafe fn draw_particles() {
+    use core::f32::consts::PI;
+
+    // Colors over particle life ('rgba' calls must be placed in constants to live for the whole program)
+    const SPARK_COLORS: &[(f32, u32)] = &[(0.0, rgba(255, 255, 200, 255)), (0.5, rgba(255, 160, 20, 255)), (1.0, rgba(255, 0, 0, 0))];
+    const SMOKE_COLORS: &[(f32, u32)] = &[(0.0, rgba(120, 120, 120, 160)), (1.0, rgba(200, 200, 200, 0))];
+    const SNOW_COLORS: &[(f32, u32)] = &[(0.0, rgba(255, 255, 255, 220))];
+
+    init_graphic();
+    user_inputs::setup_analog();
+
+    // Effects definitions
+    let sparks = EmitterConfig {
+        shape: EmitterShape::Point,
+        rate: 60.0,
+        lifetime: (0.4, 0.9),
+        speed: (60.0, 160.0),
+        direction: -PI / 2.0,
+        spread: PI / 4.0,
+        gravity: (0.0, 250.0),
+        color: Curve { keys: SPARK_COLORS },
+        size: Curve { keys: &[(0.0, 3.0), (1.0, 1.0)] },
+        blend: BlendMode::Additive,
+    };
+    let smoke = EmitterConfig {
+        shape: EmitterShape::Circle { radius: 8.0 },
+        rate: 20.0,
+        lifetime: (1.5, 2.5),
+        speed: (10.0, 25.0),
+        direction: -PI / 2.0,
+        spread: PI / 8.0,
+        gravity: (5.0, -10.0),
+        color: Curve { keys: SMOKE_COLORS },
+        size: Curve { keys: &[(0.0, 6.0), (1.0, 24.0)] },
+        blend: BlendMode::Alpha,
+    };
+    let snow = EmitterConfig {
+        shape: EmitterShape::Box { half_width: PSP_SCR_WIDTH as f32 / 2.0, half_height: 2.0 },
+        rate: 40.0,
+        lifetime: (5.0, 7.0),
+        speed: (20.0, 40.0),
+        direction: PI / 2.0,
+        spread: PI / 10.0,
+        gravity: (0.0, 0.0),
+        color: Curve { keys: SNOW_COLORS },
+        size: Curve { keys: &[(0.0, 2.0)] },
+        blend: BlendMode::Alpha,
+    };
+
+    // Every system gets own seed, so effects look different in every run and don't repeat each other
+    let seed = time::current_tick() as u32;
+    let mut systems = [
+        ParticleSystem::new(sparks, (120.0, 200.0), 256, seed),
+        ParticleSystem::new(smoke, (360.0, 220.0), 128, seed.wrapping_add(1)),
+        ParticleSystem::new(snow, (PSP_SCR_WIDTH as f32 / 2.0, -4.0), 512, seed.wrapping_add(2)),
+    ];
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut timer = time::DeltaTimer::new();
+    let mut previous_buttons = CtrlButtons::empty();
+
+    while draw {
+        let delta = timer.delta();
+
+        let (buttons, _) = user_inputs::read_controller();
+        if buttons.contains(CtrlButtons::CROSS) && !previous_buttons.contains(CtrlButtons::CROSS) {
+            systems[0].burst(100);
+        }
+        previous_buttons = buttons;
+
+        for system in systems.iter_mut() {
+            system.update(delta);
+        }
+
+        GMng::start_new_frame();
+
+        sceGuClearColor(rgba(9, 15, 40, 0));
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+
+        for system in systems.iter() {
+            system.draw(&mut states);
+        }
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Generate tileset used by tilemap examples
+unsafe fn example_tileset() -> Tileset {
+    // Generate tileset: each tile has got own color and darker border, tiles 12..=15 are water animation frames
+    for tile in 0..16u32 {
+        let color = rgba((tile * 16) as u8, (255 - tile * 12) as u8, (80 + tile * 10) as u8, 255);
+        let border = rgba((tile * 8) as u8, (120 - tile * 6) as u8, (40 + tile * 5) as u8, 255);
+        let (tile_x, tile_y) = ((tile % 4) * 16, (tile / 4) * 16);
+
+        for y in 0..16 {
+            for x in 0..16 {
+                let edge = x == 0 || y == 0 || x == 15 || y == 15;
+                TILESET_PIXELS.0[((tile_y + y) * 64 + tile_x + x) as usize] = if edge { border } else { color };
+            }
+        }
+    }
+    sceKernelDcacheWritebackAll();
+
+    let mut animations = alloc::collections::BTreeMap::new();
+    animations.insert(12, TileAnimation { frames: alloc::vec![(12, 0.25), (13, 0.25), (14, 0.25), (15, 0.25)] });
+    Tileset {
+        texture: Texture { bytes: &TILESET_PIXELS as *const _ as *const c_void, width: 64, height: 64, tbw: 64 },
+        tile_width: 16,
+        tile_height: 16,
+        columns: 4,
+        animations,
+    }
+}
+
+/// Draw scrolling tilemap with 2 layers (far background with parallax and main layer with animated water). Analog stick moves point followed by camera, SELECT takes screenshot
+pub unsafe fn draw_tilemap() {
+    init_graphic();
+    user_inputs::setup_analog();
+
+    let tileset = example_tileset();
+
+    // Far background: checkerboard scrolling twice slower then camera
+    let mut background = TileLayer::new(40, 24);
+    background.parallax = (0.5, 0.5);
+    for y in 0..24 {
+        for x in 0..40 {
+            background.set(x, y, if (x + y) % 2 == 0 { 1 } else { 2 });
+        }
+    }
+
+    // Main layer: ground, platforms and water pool
+    let mut main_layer = TileLayer::new(80, 34);
+    for x in 0..80 {
+        main_layer.set(x, 33, 5);
+        main_layer.set(x, 32, 6);
+        if x % 12 < 5 {
+            main_layer.set(x, 20 + x % 7, 9);
+        }
+    }
+    for x in 30..40 {
+        main_layer.set(x, 32, 13);
+    }
+
+    let mut map = Tilemap::new(tileset);
+    map.layers.push(background);
+    map.layers.push(main_layer);
+    let (map_width, map_height) = map.pixel_size();
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut timer = time::DeltaTimer::new();
+    let mut camera = Camera2D::new(PSP_SCR_WIDTH as f32, PSP_SCR_HEIGHT as f32);
+    let mut target = (PSP_SCR_WIDTH as f32 / 2.0, map_height - PSP_SCR_HEIGHT as f32 / 2.0);
+    let mut previous_buttons = CtrlButtons::empty();
+
+    while draw {
+        let delta = timer.delta();
+
+        // Move followed point with 200 pixels per second for full stick deflection
+        let (buttons, (sx, sy)) = user_inputs::read_controller();
+        target.0 = (target.0 + sx * 200.0 * delta).clamp(0.0, map_width);
+        target.1 = (target.1 + sy * 200.0 * delta).clamp(0.0, map_height);
+
+        // SELECT saves currently displayed frame (only once per press)
+        if buttons.contains(CtrlButtons::SELECT) && !previous_buttons.contains(CtrlButtons::SELECT) {
+            let _ = screenshot::take_screenshot(ImageFormat::Png);
+        }
+        previous_buttons = buttons;
+
+        camera.follow(target, delta);
+        camera.clamp_to(map_width, map_height);
+        map.update(delta);
+
+        GMng::start_new_frame();
+
+        sceGuClearColor(rgba(9, 15, 40, 0));
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+
+        map.draw(&camera, &mut states);
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Draw map exported from Tiled editor ('files/level.tmx', the same map is also in 'files/level.json'). Camera starts on "player" object, objects are shown as translucent rectangles
+pub unsafe fn draw_tiled_map() {
+    let level = match tiled::load_map("level.tmx") {
+        Ok(level) => level,
+        Err(e) => {
+            psp::dprintln!("Couldn't load map: {e:?}");
+            return;
+        }
+    };
+
+    init_graphic();
+    user_inputs::setup_analog();
+
+    // Tileset image isn't loaded from file (only raw pixels are supported), so generated tileset with the same layout is used instead
+    let mut tileset = example_tileset();
+    if let Some(map_tileset) = level.tilesets.first() {
+        tileset.animations = map_tileset.tile_animations();
+    }
+
+    let start = level.object("player").map(|player| (player.x, player.y)).unwrap_or((0.0, 0.0));
+    // Objects must be copied out before map is converted into 'Tilemap'
+    let objects: alloc::vec::Vec<_> = level.objects().map(|object| (object.x, object.y, object.width, object.height)).collect();
+    let mut map = level.into_tilemap(tileset);
+    let (map_width, map_height) = map.pixel_size();
+
+    let mut draw = true;
+    let mut states = StateCache::new();
+    let mut timer = time::DeltaTimer::new();
+    let mut camera = Camera2D::new(PSP_SCR_WIDTH as f32, PSP_SCR_HEIGHT as f32);
+    let mut target = start;
+
+    while draw {
+        let delta = timer.delta();
+
+        let (_, (sx, sy)) = user_inputs::read_controller();
+        target.0 = (target.0 + sx * 100.0 * delta).clamp(0.0, map_width);
+        target.1 = (target.1 + sy * 100.0 * delta).clamp(0.0, map_height);
+
+        camera.follow(target, delta);
+        camera.clamp_to(map_width, map_height);
+        map.update(delta);
+
+        GMng::start_new_frame();
+
+        sceGuClearColor(rgba(9, 15, 40, 0));
+        sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+
+        map.draw(&camera, &mut states);
+
+        // Objects without size (points) are shown as 4x4 pixels squares
+        states.apply(&RenderState::OVERLAY);
+        let vertices = sceGuGetMemory((objects.len() * 2 * core::mem::size_of::<Vertex>()) as i32) as *mut Vertex;
+        for (i, (x, y, width, height)) in objects.iter().enumerate() {
+            let (width, height) = (width.max(4.0), height.max(4.0));
+            let (x, y) = (x - camera.x, y - camera.y);
+
+            *vertices.add(i * 2) = Vertex { u: 0.0, v: 0.0, color: rgba(255, 80, 80, 120), x, y, z: 0.0 };
+            *vertices.add(i * 2 + 1) = Vertex { u: 0.0, v: 0.0, color: rgba(255, 80, 80, 120), x: x + width, y: y + height, z: 0.0 };
+        }
+        sceGuDrawArray(GuPrimitive::Sprites, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, (objects.len() * 2) as i32, core::ptr::null(), vertices as *const c_void);
+
+        GMng::end_existing_frame();
+    }
+
+    GMng::terminate_graphics();
+}
+
+/// Square moved by fixed timestep updates (30 per second) and drawn with interpolation (frames are limited to 60 per second).
+/// Bottom of screen shows frame time graph of last frames (line = 16.6 ms, the longest frame is marked red)
+pub unsafe fn draw_game_loop() {
+    init_graphic();
+
+    let mut game_loop = GameLoop::new(30.0).with_frame_limit(Some(60.0));
+    let mut states = StateCache::new();
+
+    // Square position in previous and current update (renderer interpolates between them). 'Cell' lets both closures access it
+    let square = core::cell::Cell::new((0.0f32, 0.0f32));
+    let mut velocity = 150.0f32;
+    let max_position = PSP_SCR_WIDTH as f32 - 32.0;
+
+    game_loop.run(
+        |step| {
+            let (_, mut position) = square.get();
+            let previous = position;
+            position += velocity * step;
+
+            // Bounce from screen edges
+            if position < 0.0 || position > max_position {
+                velocity = -velocity;
+                position = position.clamp(0.0, max_position);
+            }
+            square.set((previous, position));
+
+            true
+        },
+        |alpha, stats| {
+            GMng::start_new_frame();
+
+            sceGuClearColor(rgba(9, 15, 40, 0));
+            sceGuClear(ClearBuffer::COLOR_BUFFER_BIT);
+            states.apply(&RenderState::OVERLAY);
+
+            // Square + frame time bars + 16.6 ms line
+            let bars = game_loop::STATS_FRAMES;
+            let vertices = sceGuGetMemory(((bars + 2) * 2 * core::mem::size_of::<Vertex>()) as i32) as *mut Vertex;
+            let sprite = |index: usize, x: f32, y: f32, width: f32, height: f32, color: u32| {
+                *vertices.add(index * 2) = Vertex { u: 0.0, v: 0.0, color, x, y, z: 0.0 };
+                *vertices.add(index * 2 + 1) = Vertex { u: 0.0, v: 0.0, color, x: x + width, y: y + height, z: 0.0 };
+            };
+
+            let (previous, position) = square.get();
+            let x = previous + (position - previous) * alpha;
+            sprite(0, x, 100.0, 32.0, 32.0, rgba(255, 200, 40, 255));
+
+            // 4 pixels of bar height per millisecond
+            let bottom = PSP_SCR_HEIGHT as f32;
+            let longest = stats.max();
+            for (i, time) in stats.frame_times().enumerate() {
+                let height = time * 1000.0 * 4.0;
+                let color = if time >= longest { rgba(255, 60, 60, 200) } else { rgba(80, 220, 120, 200) };
+                sprite(i + 1, i as f32 * 4.0, bottom - height, 3.0, height, color);
+            }
+            // Bars of frames which weren't measured yet stay empty
+            for i in stats.frame_times().count()..bars {
+                sprite(i + 1, 0.0, 0.0, 0.0, 0.0, 0);
+            }
+            sprite(bars + 1, 0.0, bottom - 16.6 * 4.0, bars as f32 * 4.0, 1.0, rgba(255, 255, 255, 160));
+
+            sceGuDrawArray(GuPrimitive::Sprites, VertexType::TEXTURE_32BITF | VertexType::COLOR_8888 | VertexType::VERTEX_32BITF | VertexType::TRANSFORM_2D, ((bars + 2) * 2) as i32, core::ptr::null(), vertices as *const c_void);
+
+            GMng::end_existing_frame();
+        },
+    );
+
+    GMng::terminate_graphics();
+}
@@ -0,0 +1,62 @@
+//! Stand-ins for 'psp' crate items used by platform independent logic. 'psp' crate can be built only for PSP, so host build
+//! (which runs unit tests) gets these instead. They behave the same as originals
+
+pub mod sys {
+    bitflags::bitflags! {
+        /// Buttons of PSP controller with the same bits as 'psp::sys::CtrlButtons'
+        #[derive(Default)]
+        #[repr(transparent)]
+        pub struct CtrlButtons: u32 {
+            const SELECT = 0x000001;
+            const START = 0x000008;
+            const UP = 0x000010;
+            const RIGHT = 0x000020;
+            const DOWN = 0x000040;
+            const LEFT = 0x000080;
+            const LTRIGGER = 0x000100;
+            const RTRIGGER = 0x000200;
+            const TRIANGLE = 0x001000;
+            const CIRCLE = 0x002000;
+            const CROSS = 0x004000;
+            const SQUARE = 0x008000;
+            const HOME = 0x010000;
+            const HOLD = 0x020000;
+            const NOTE = 0x800000;
+            const SCREEN = 0x400000;
+            const VOL_UP = 0x100000;
+            const VOL_DOWN = 0x200000;
+            const WLAN_UP = 0x040000;
+            const REMOTE = 0x080000;
+            const DISC = 0x1000000;
+            const MEM_STICK = 0x2000000;
+        }
+    }
+
+    /// Map 8-bit color channels into one 32-bit value
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+        (r as u32) | ((g as u32) << 8) | ((b as u32) << 16) | ((a as u32) << 24)
+    }
+}
+
+/// Math functions with the same signatures as in 'psp::math' (VFPU ones are unsafe there)
+pub mod math {
+    pub fn sqrtf(value: f32) -> f32 {
+        value.sqrt()
+    }
+
+    pub fn floorf(value: f32) -> f32 {
+        value.floor()
+    }
+
+    pub fn atan2f(y: f32, x: f32) -> f32 {
+        y.atan2(x)
+    }
+
+    pub unsafe fn sinf(value: f32) -> f32 {
+        value.sin()
+    }
+
+    pub unsafe fn cosf(value: f32) -> f32 {
+        value.cos()
+    }
+}
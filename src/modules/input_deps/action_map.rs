@@ -0,0 +1,419 @@
+use alloc::{vec::Vec, string::{String, ToString}};
+use psp::sys::CtrlButtons;
+#[cfg(target_os = "psp")]
+use crate::file_system;
+use crate::json::{self, JsonError, Value};
+use crate::input_manager::{InputManager, InputSampler, InputSample};
+use crate::analog::{self, AnalogStick, Direction8};
+use crate::input_events::InputEvent;
+
+/// Names of buttons used in profile files. Combinations are written as names joined with '+' (e.g. "L+R+CROSS")
+const BUTTON_NAMES: [(&str, CtrlButtons); 12] = [
+    ("SELECT", CtrlButtons::SELECT),
+    ("START", CtrlButtons::START),
+    ("UP", CtrlButtons::UP),
+    ("RIGHT", CtrlButtons::RIGHT),
+    ("DOWN", CtrlButtons::DOWN),
+    ("LEFT", CtrlButtons::LEFT),
+    ("L", CtrlButtons::LTRIGGER),
+    ("R", CtrlButtons::RTRIGGER),
+    ("TRIANGLE", CtrlButtons::TRIANGLE),
+    ("CIRCLE", CtrlButtons::CIRCLE),
+    ("CROSS", CtrlButtons::CROSS),
+    ("SQUARE", CtrlButtons::SQUARE),
+];
+
+/// All buttons which have got name in profile files
+fn named_buttons() -> CtrlButtons {
+    BUTTON_NAMES.iter().fold(CtrlButtons::empty(), |all, (_, button)| all | *button)
+}
+
+/// Names of stick directions used in profile files (written with "STICK_" prefix)
+const DIRECTION_NAMES: [(&str, Direction8); 8] = [
+    ("UP", Direction8::Up),
+    ("UP_RIGHT", Direction8::UpRight),
+    ("RIGHT", Direction8::Right),
+    ("DOWN_RIGHT", Direction8::DownRight),
+    ("DOWN", Direction8::Down),
+    ("DOWN_LEFT", Direction8::DownLeft),
+    ("LEFT", Direction8::Left),
+    ("UP_LEFT", Direction8::UpLeft),
+];
+
+/// Physical input which triggers action
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    /// Button or combination of buttons (all must be held)
+    Buttons(CtrlButtons),
+    /// Analog stick pushed in direction
+    Stick(Direction8),
+}
+
+impl Binding {
+    /// Text form used in profile files, e.g. "CROSS", "L+R" or "STICK_UP_LEFT"
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Buttons(buttons) => BUTTON_NAMES
+                .iter()
+                .filter(|(_, button)| buttons.contains(*button))
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join("+"),
+            Binding::Stick(direction) => {
+                let name = DIRECTION_NAMES.iter().find(|(_, dir)| dir == direction).map_or("", |(name, _)| name);
+                alloc::format!("STICK_{}", name)
+            }
+        }
+    }
+
+    /// Whether binding can be saved in profile file ('from_name' of its 'name' gives it back). Empty combination can't, as well as buttons
+    /// without name (HOME, NOTE, SCREEN, volume buttons...), which are readed only in kernel mode anyway
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Binding::Buttons(buttons) => !buttons.is_empty() && named_buttons().contains(*buttons),
+            Binding::Stick(_) => true,
+        }
+    }
+
+    /// Reverse of 'name'. None for unknown text
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(direction) = name.strip_prefix("STICK_") {
+            return DIRECTION_NAMES.iter().find(|(dir_name, _)| *dir_name == direction).map(|(_, dir)| Binding::Stick(*dir));
+        }
+
+        let mut buttons = CtrlButtons::empty();
+        for part in name.split('+') {
+            let (_, button) = BUTTON_NAMES.iter().find(|(button_name, _)| *button_name == part.trim())?;
+            buttons |= *button;
+        }
+
+        Some(Binding::Buttons(buttons))
+    }
+}
+
+/// Things which can go wrong while changing bindings or working with profile file
+#[derive(Debug)]
+pub enum ActionMapError {
+    /// Action wasn't defined by 'ActionMap::define'
+    UnknownAction,
+    /// Binding can't be saved in profile file (see 'Binding::is_valid')
+    InvalidBinding(Binding),
+    /// Binding is already used by other action (contains its name)
+    Conflict(String),
+    /// Profile file couldn't be read or written (contains error code returned by 'sceIo' function)
+    Io(i32),
+    /// Profile file isn't valid UTF-8 text
+    NotText,
+    Json(JsonError),
+    /// Profile has got wrong structure or unknown binding name. Contains name of value
+    Invalid(&'static str),
+}
+
+/// Logical game actions (e.g. "jump", "confirm") with bindings chosen by player. Game asks about actions instead of buttons,
+/// so players can change controls (e.g. swap CROSS and CIRCLE for japanese convention) and keep them in profile file
+pub struct ActionMap {
+    /// Actions in order of definition with their bindings
+    actions: Vec<(String, Vec<Binding>)>,
+    /// Processing used for stick bindings
+    pub stick: AnalogStick,
+    /// How far stick must be pushed to trigger stick binding (0.0..=1.0)
+    pub stick_threshold: f32,
+}
+
+impl ActionMap {
+    pub fn new() -> Self {
+        Self { actions: Vec::new(), stick: AnalogStick::default(), stick_threshold: 0.5 }
+    }
+
+    /// Add action with default bindings. Defining existing action replaces its bindings. Fails without any change when some binding isn't valid
+    pub fn define(&mut self, action: &str, bindings: &[Binding]) -> Result<(), ActionMapError> {
+        if let Some(binding) = bindings.iter().find(|binding| !binding.is_valid()) {
+            return Err(ActionMapError::InvalidBinding(*binding));
+        }
+
+        match self.actions.iter_mut().find(|(name, _)| name == action) {
+            Some((_, current)) => *current = bindings.to_vec(),
+            None => self.actions.push((action.to_string(), bindings.to_vec())),
+        }
+
+        Ok(())
+    }
+
+    /// Names of all defined actions
+    pub fn actions(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions.iter().find(|(name, _)| name == action).map_or(&[], |(_, bindings)| bindings.as_slice())
+    }
+
+    /// Add binding to action. Fails when binding isn't valid or is already used by other action
+    pub fn bind(&mut self, action: &str, binding: Binding) -> Result<(), ActionMapError> {
+        if !binding.is_valid() {
+            return Err(ActionMapError::InvalidBinding(binding));
+        }
+        if let Some(owner) = self.owner(binding) {
+            if owner != action {
+                return Err(ActionMapError::Conflict(owner.to_string()));
+            }
+            return Ok(());
+        }
+
+        self.bindings_mut(action)?.push(binding);
+        Ok(())
+    }
+
+    /// Add binding to action and remove it from action which used it before (if any)
+    pub fn bind_replacing(&mut self, action: &str, binding: Binding) -> Result<(), ActionMapError> {
+        if !binding.is_valid() {
+            return Err(ActionMapError::InvalidBinding(binding));
+        }
+        self.bindings_mut(action)?;
+
+        for (_, bindings) in self.actions.iter_mut() {
+            bindings.retain(|bound| *bound != binding);
+        }
+        self.bindings_mut(action)?.push(binding);
+
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, action: &str, binding: Binding) -> Result<(), ActionMapError> {
+        self.bindings_mut(action)?.retain(|bound| *bound != binding);
+        Ok(())
+    }
+
+    /// Exchange 2 buttons in all bindings (also inside combinations), e.g. CROSS and CIRCLE. Does nothing when one of them has got no name
+    /// (bindings must stay valid)
+    pub fn swap_buttons(&mut self, first: CtrlButtons, second: CtrlButtons) {
+        if !named_buttons().contains(first | second) {
+            return;
+        }
+
+        for (_, bindings) in self.actions.iter_mut() {
+            for binding in bindings.iter_mut() {
+                if let Binding::Buttons(buttons) = binding {
+                    let (has_first, has_second) = (buttons.contains(first), buttons.contains(second));
+                    buttons.remove(first | second);
+                    buttons.set(second, has_first);
+                    buttons.set(first, has_second);
+                }
+            }
+        }
+    }
+
+    /// Pairs of actions which share the same binding (can appear after loading profile edited by hand)
+    pub fn conflicts(&self) -> Vec<(&str, &str, Binding)> {
+        let mut conflicts = Vec::new();
+
+        for (i, (first, first_bindings)) in self.actions.iter().enumerate() {
+            for (second, second_bindings) in &self.actions[i + 1..] {
+                for binding in first_bindings.iter().filter(|binding| second_bindings.contains(binding)) {
+                    conflicts.push((first.as_str(), second.as_str(), *binding));
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// Any binding of action is active now
+    pub fn is_down<S: InputSampler>(&self, action: &str, inputs: &InputManager<S>) -> bool {
+        self.is_active(action, inputs.sample())
+    }
+
+    /// Action became active in this frame
+    pub fn just_pressed<S: InputSampler>(&self, action: &str, inputs: &InputManager<S>) -> bool {
+        self.is_active(action, inputs.sample()) && !self.is_active(action, inputs.previous_sample())
+    }
+
+    /// Action stopped being active in this frame
+    pub fn just_released<S: InputSampler>(&self, action: &str, inputs: &InputManager<S>) -> bool {
+        !self.is_active(action, inputs.sample()) && self.is_active(action, inputs.previous_sample())
+    }
+
+    /// Event triggers action: press or repeat of single button binding, completed combination binding (it must be watched by 'EventSource')
+    /// or stick moved into bound direction
+    pub fn triggered_by(&self, action: &str, event: &InputEvent) -> bool {
+        self.bindings(action).iter().any(|binding| match (binding, event) {
+            (Binding::Buttons(buttons), InputEvent::ButtonDown(button) | InputEvent::Repeat { button, .. }) => buttons == button,
+            (Binding::Buttons(buttons), InputEvent::Combination(combination)) => buttons == combination,
+            (Binding::Stick(stick_direction), InputEvent::AnalogMoved { direction, .. }) => *direction == Some(*stick_direction),
+            _ => false,
+        })
+    }
+
+    /// Write bindings of all actions as JSON object (action name -> array of binding names)
+    pub fn to_json(&self) -> String {
+        let actions = self.actions
+            .iter()
+            .map(|(name, bindings)| {
+                let bindings = bindings.iter().map(|binding| Value::String(binding.name())).collect();
+                (name.clone(), Value::Array(bindings))
+            })
+            .collect();
+
+        let mut out = String::new();
+        Value::Object(alloc::vec![("actions".to_string(), Value::Object(actions))]).write(&mut out);
+        out
+    }
+
+    /// Replace bindings of actions listed in JSON profile. Actions which aren't defined by game are skipped, defined ones missing in profile keep their bindings
+    pub fn apply_json(&mut self, src: &str) -> Result<(), ActionMapError> {
+        let document = json::parse(src).map_err(ActionMapError::Json)?;
+        let actions = match document.get("actions") {
+            Some(Value::Object(actions)) => actions,
+            _ => return Err(ActionMapError::Invalid("actions")),
+        };
+
+        // Whole profile is checked before anything is changed
+        let mut loaded = Vec::new();
+        for (name, bindings) in actions {
+            let bindings = bindings
+                .as_array()
+                .ok_or(ActionMapError::Invalid("bindings"))?
+                .iter()
+                .map(|binding| binding.as_str().and_then(Binding::from_name).ok_or(ActionMapError::Invalid("binding")))
+                .collect::<Result<Vec<_>, _>>()?;
+            loaded.push((name, bindings));
+        }
+
+        for (name, bindings) in loaded {
+            if let Ok(current) = self.bindings_mut(name) {
+                *current = bindings;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Save bindings to profile file
+    #[cfg(target_os = "psp")]
+    pub unsafe fn save(&self, path: &str) -> Result<(), ActionMapError> {
+        file_system::write_file(path, self.to_json().as_bytes()).map_err(ActionMapError::Io)
+    }
+
+    /// Load bindings from profile file (see 'apply_json')
+    #[cfg(target_os = "psp")]
+    pub unsafe fn load(&mut self, path: &str) -> Result<(), ActionMapError> {
+        let bytes = file_system::read_file(path).map_err(ActionMapError::Io)?;
+        let src = String::from_utf8(bytes).map_err(|_| ActionMapError::NotText)?;
+
+        self.apply_json(&src)
+    }
+
+    /// Action which uses binding
+    fn owner(&self, binding: Binding) -> Option<&str> {
+        self.actions.iter().find(|(_, bindings)| bindings.contains(&binding)).map(|(name, _)| name.as_str())
+    }
+
+    fn bindings_mut(&mut self, action: &str) -> Result<&mut Vec<Binding>, ActionMapError> {
+        self.actions.iter_mut().find(|(name, _)| name == action).map(|(_, bindings)| bindings).ok_or(ActionMapError::UnknownAction)
+    }
+
+    fn is_active(&self, action: &str, sample: &InputSample) -> bool {
+        let direction = analog::direction8(self.stick.process(sample.stick), self.stick_threshold);
+
+        self.bindings(action).iter().any(|binding| match binding {
+            Binding::Buttons(buttons) => !buttons.is_empty() && sample.buttons.contains(*buttons),
+            Binding::Stick(stick_direction) => direction == Some(*stick_direction),
+        })
+    }
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_name_round_trip() {
+        for (_, button) in BUTTON_NAMES {
+            let binding = Binding::Buttons(button);
+            assert_eq!(Binding::from_name(&binding.name()), Some(binding));
+        }
+        for (_, direction) in DIRECTION_NAMES {
+            let binding = Binding::Stick(direction);
+            assert_eq!(Binding::from_name(&binding.name()), Some(binding));
+        }
+
+        let combination = Binding::Buttons(CtrlButtons::LTRIGGER | CtrlButtons::RTRIGGER | CtrlButtons::CROSS);
+        assert_eq!(combination.name(), "L+R+CROSS");
+        assert_eq!(Binding::from_name("L + R+CROSS"), Some(combination));
+
+        assert_eq!(Binding::from_name(""), None);
+        assert_eq!(Binding::from_name("CROSS+JUMP"), None);
+        assert_eq!(Binding::from_name("STICK_FORWARD"), None);
+    }
+
+    #[test]
+    fn invalid_bindings_are_rejected() {
+        let mut actions = ActionMap::new();
+
+        assert!(matches!(actions.define("home", &[Binding::Buttons(CtrlButtons::HOME)]), Err(ActionMapError::InvalidBinding(_))));
+        assert!(matches!(actions.define("nothing", &[Binding::Buttons(CtrlButtons::empty())]), Err(ActionMapError::InvalidBinding(_))));
+        assert_eq!(actions.actions().count(), 0);
+
+        actions.define("jump", &[Binding::Buttons(CtrlButtons::CROSS)]).unwrap();
+        assert!(matches!(actions.bind("jump", Binding::Buttons(CtrlButtons::CROSS | CtrlButtons::NOTE)), Err(ActionMapError::InvalidBinding(_))));
+        assert!(matches!(actions.bind_replacing("jump", Binding::Buttons(CtrlButtons::VOL_UP)), Err(ActionMapError::InvalidBinding(_))));
+        assert_eq!(actions.bindings("jump"), [Binding::Buttons(CtrlButtons::CROSS)]);
+    }
+
+    #[test]
+    fn swap_buttons_inside_combinations() {
+        let mut actions = ActionMap::new();
+        actions.define("confirm", &[Binding::Buttons(CtrlButtons::CROSS)]).unwrap();
+        actions.define("cancel", &[Binding::Buttons(CtrlButtons::CIRCLE), Binding::Stick(Direction8::Left)]).unwrap();
+        actions.define("special", &[Binding::Buttons(CtrlButtons::CIRCLE | CtrlButtons::LTRIGGER)]).unwrap();
+
+        actions.swap_buttons(CtrlButtons::CROSS, CtrlButtons::CIRCLE);
+        assert_eq!(actions.bindings("confirm"), [Binding::Buttons(CtrlButtons::CIRCLE)]);
+        assert_eq!(actions.bindings("cancel"), [Binding::Buttons(CtrlButtons::CROSS), Binding::Stick(Direction8::Left)]);
+        assert_eq!(actions.bindings("special"), [Binding::Buttons(CtrlButtons::CROSS | CtrlButtons::LTRIGGER)]);
+
+        // Swapping with button which can't be saved would make bindings invalid
+        actions.swap_buttons(CtrlButtons::CIRCLE, CtrlButtons::HOME);
+        assert_eq!(actions.bindings("confirm"), [Binding::Buttons(CtrlButtons::CIRCLE)]);
+    }
+
+    #[test]
+    fn conflicts() {
+        let mut actions = ActionMap::new();
+        actions.define("jump", &[Binding::Buttons(CtrlButtons::CROSS)]).unwrap();
+        actions.define("attack", &[Binding::Buttons(CtrlButtons::SQUARE)]).unwrap();
+        actions.define("confirm", &[Binding::Buttons(CtrlButtons::CROSS)]).unwrap();
+
+        assert_eq!(actions.conflicts(), [("jump", "confirm", Binding::Buttons(CtrlButtons::CROSS))]);
+
+        assert!(matches!(actions.bind("attack", Binding::Buttons(CtrlButtons::CROSS)), Err(ActionMapError::Conflict(owner)) if owner == "jump"));
+        actions.bind_replacing("attack", Binding::Buttons(CtrlButtons::CROSS)).unwrap();
+        assert!(actions.conflicts().is_empty());
+        assert_eq!(actions.bindings("attack"), [Binding::Buttons(CtrlButtons::SQUARE), Binding::Buttons(CtrlButtons::CROSS)]);
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        let mut actions = ActionMap::new();
+        actions.define("jump", &[Binding::Buttons(CtrlButtons::CROSS), Binding::Stick(Direction8::Up)]).unwrap();
+        actions.define("dash", &[Binding::Buttons(CtrlButtons::LTRIGGER | CtrlButtons::RTRIGGER)]).unwrap();
+        let profile = actions.to_json();
+
+        let mut loaded = ActionMap::new();
+        loaded.define("jump", &[]).unwrap();
+        loaded.define("dash", &[]).unwrap();
+        loaded.apply_json(&profile).unwrap();
+        assert_eq!(loaded.bindings("jump"), actions.bindings("jump"));
+        assert_eq!(loaded.bindings("dash"), actions.bindings("dash"));
+
+        // One unknown binding rejects whole profile
+        assert!(matches!(loaded.apply_json(r#"{"actions":{"jump":["CIRCLE"],"dash":["HOME"]}}"#), Err(ActionMapError::Invalid("binding"))));
+        assert_eq!(loaded.bindings("jump"), actions.bindings("jump"));
+    }
+}
@@ -0,0 +1,164 @@
+use core::f32::consts::PI;
+use psp::math::{sqrtf, atan2f, floorf};
+use psp::sys::CtrlButtons;
+
+/// How stick deflection (after deadzones, 0.0..=1.0) is mapped to output value
+#[derive(Clone, Copy)]
+pub enum ResponseCurve {
+    Linear,
+    /// Fine control near center and full speed at the edge
+    Quadratic,
+    /// Any function which maps 0.0..=1.0 into 0.0..=1.0
+    Custom(fn(f32) -> f32),
+}
+
+impl ResponseCurve {
+    pub fn apply(self, value: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => value,
+            ResponseCurve::Quadratic => value * value,
+            ResponseCurve::Custom(curve) => curve(value).clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// One of 8 directions in which stick can be pushed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction8 {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction8 {
+    /// D-pad buttons which give the same direction (diagonals are 2 buttons)
+    pub fn to_buttons(self) -> CtrlButtons {
+        match self {
+            Direction8::Up => CtrlButtons::UP,
+            Direction8::UpRight => CtrlButtons::UP | CtrlButtons::RIGHT,
+            Direction8::Right => CtrlButtons::RIGHT,
+            Direction8::DownRight => CtrlButtons::DOWN | CtrlButtons::RIGHT,
+            Direction8::Down => CtrlButtons::DOWN,
+            Direction8::DownLeft => CtrlButtons::DOWN | CtrlButtons::LEFT,
+            Direction8::Left => CtrlButtons::LEFT,
+            Direction8::UpLeft => CtrlButtons::UP | CtrlButtons::LEFT,
+        }
+    }
+}
+
+/// Turns raw 0..=255 stick readings from 'SceCtrlData' ('lx', 'ly') into clean values in -1.0..=1.0 range.
+/// Processing order: center calibration, axial deadzone, radial deadzone with outer edge, response curve. Negative y means stick pushed up
+#[derive(Clone, Copy)]
+pub struct AnalogStick {
+    /// Raw position at which stick rests (PSP sticks rarely rest exactly at 128)
+    pub center: (f32, f32),
+    /// Each axis with smaller absolute value then this is 0 (removes drift along one axis while pushing along the other)
+    pub axial_deadzone: f32,
+    /// Deflection shorter then this is 0 in both axes
+    pub radial_deadzone: f32,
+    /// Deflection longer then this is treated as full one (worn sticks often can't reach the edge)
+    pub outer_edge: f32,
+    pub curve: ResponseCurve,
+}
+
+impl Default for AnalogStick {
+    fn default() -> Self {
+        Self { center: (128.0, 128.0), axial_deadzone: 0.0, radial_deadzone: 0.2, outer_edge: 0.95, curve: ResponseCurve::Linear }
+    }
+}
+
+impl AnalogStick {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_deadzones(mut self, radial: f32, axial: f32) -> Self {
+        self.radial_deadzone = radial.clamp(0.0, 0.99);
+        self.axial_deadzone = axial.clamp(0.0, 0.99);
+        self
+    }
+
+    pub fn with_curve(mut self, curve: ResponseCurve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    /// Set center from readings taken while stick isn't touched (average of all of them). Does nothing for empty iterator
+    pub fn calibrate(&mut self, samples: impl IntoIterator<Item = (u8, u8)>) {
+        let (mut sum_x, mut sum_y, mut count) = (0.0, 0.0, 0);
+        for (x, y) in samples {
+            sum_x += x as f32;
+            sum_y += y as f32;
+            count += 1;
+        }
+
+        if count > 0 {
+            self.center = (sum_x / count as f32, sum_y / count as f32);
+        }
+    }
+
+    /// Processed stick position (x, y) in -1.0..=1.0 range. Length of returned vector is never greater then 1.0
+    pub fn process(&self, raw: (u8, u8)) -> (f32, f32) {
+        let x = self.axial(normalize(raw.0, self.center.0));
+        let y = self.axial(normalize(raw.1, self.center.1));
+
+        let length = sqrtf(x * x + y * y);
+        if length <= self.radial_deadzone {
+            return (0.0, 0.0);
+        }
+
+        // Deflection between deadzone and outer edge is stretched to 0.0..=1.0, so output starts from 0 right after deadzone
+        let range = (self.outer_edge - self.radial_deadzone).max(0.01);
+        let scaled = ((length - self.radial_deadzone) / range).min(1.0);
+        let output = self.curve.apply(scaled);
+
+        (x / length * output, y / length * output)
+    }
+
+    /// Axial deadzone with the rest of axis range stretched to 0.0..=1.0
+    fn axial(&self, value: f32) -> f32 {
+        let magnitude = if value < 0.0 { -value } else { value };
+        if magnitude <= self.axial_deadzone {
+            return 0.0;
+        }
+
+        let stretched = (magnitude - self.axial_deadzone) / (1.0 - self.axial_deadzone);
+        if value < 0.0 { -stretched } else { stretched }
+    }
+}
+
+/// Raw value to -1.0..=1.0 range. Both sides of center are scaled separately, so calibrated center doesn't cut range on one side
+fn normalize(raw: u8, center: f32) -> f32 {
+    let offset = raw as f32 - center;
+    let side = if offset < 0.0 { center } else { 255.0 - center };
+
+    if side <= 0.0 { 0.0 } else { (offset / side).clamp(-1.0, 1.0) }
+}
+
+/// Direction of processed stick position, None when stick is shorter then 'threshold'. Each direction covers 45 degrees sector
+pub fn direction8(stick: (f32, f32), threshold: f32) -> Option<Direction8> {
+    let (x, y) = stick;
+    if x * x + y * y < threshold * threshold || (x == 0.0 && y == 0.0) {
+        return None;
+    }
+
+    // Angle measured clockwise from up (screen y grows downwards)
+    let angle = atan2f(x, -y);
+    let sector = (floorf(angle / (PI / 4.0) + 0.5) as i32).rem_euclid(8);
+
+    Some(match sector {
+        0 => Direction8::Up,
+        1 => Direction8::UpRight,
+        2 => Direction8::Right,
+        3 => Direction8::DownRight,
+        4 => Direction8::Down,
+        5 => Direction8::DownLeft,
+        6 => Direction8::Left,
+        _ => Direction8::UpLeft,
+    })
+}
@@ -0,0 +1,269 @@
+use alloc::{vec::Vec, collections::VecDeque};
+use psp::sys::CtrlButtons;
+
+/// Amount of input changes kept in 'ComboRecognizer::history'
+pub const HISTORY_LEN: usize = 32;
+
+/// Direction buttons. When step contains any of them, direction held by player must be exactly the same (DOWN doesn't match DOWN + RIGHT)
+const DIRECTIONS: CtrlButtons = CtrlButtons::from_bits_truncate(
+    CtrlButtons::UP.bits() | CtrlButtons::DOWN.bits() | CtrlButtons::LEFT.bits() | CtrlButtons::RIGHT.bits()
+);
+
+/// One step of combo: buttons which must be held together (single button, direction, direction + button or simultaneous press like L + R)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboStep {
+    pub buttons: CtrlButtons,
+    /// Maximal amount of frames between previous step and this one (not used by the first step)
+    pub window: u32,
+}
+
+impl ComboStep {
+    /// Step with default window (15 frames = quarter of second)
+    pub const fn press(buttons: CtrlButtons) -> Self {
+        Self { buttons, window: 15 }
+    }
+
+    pub const fn within(mut self, frames: u32) -> Self {
+        self.window = frames;
+        self
+    }
+
+    /// Step is fulfilled by held buttons
+    fn is_held(&self, held: CtrlButtons) -> bool {
+        let directions = self.buttons & DIRECTIONS;
+        let directions_match = directions.is_empty() || (held & DIRECTIONS) == directions;
+
+        directions_match && held.contains(self.buttons - DIRECTIONS)
+    }
+}
+
+/// Sequence of steps which must be entered one after another
+#[derive(Clone, Debug)]
+pub struct Combo {
+    pub name: &'static str,
+    pub steps: Vec<ComboStep>,
+}
+
+impl Combo {
+    pub fn new(name: &'static str, steps: &[ComboStep]) -> Self {
+        Self { name, steps: steps.to_vec() }
+    }
+
+    /// The same combo with LEFT and RIGHT exchanged (e.g. for character facing left, where "forward" is LEFT)
+    pub fn mirrored(&self, name: &'static str) -> Self {
+        let steps = self.steps
+            .iter()
+            .map(|step| {
+                let mut buttons = step.buttons - (CtrlButtons::LEFT | CtrlButtons::RIGHT);
+                buttons.set(CtrlButtons::LEFT, step.buttons.contains(CtrlButtons::RIGHT));
+                buttons.set(CtrlButtons::RIGHT, step.buttons.contains(CtrlButtons::LEFT));
+                ComboStep { buttons, ..*step }
+            })
+            .collect();
+
+        Self { name, steps }
+    }
+
+    /// Fighting game "quarter circle forward" motion: DOWN, DOWN + RIGHT, RIGHT + 'button'
+    pub fn quarter_circle_forward(name: &'static str, button: CtrlButtons) -> Self {
+        Self::new(name, &[
+            ComboStep::press(CtrlButtons::DOWN),
+            ComboStep::press(CtrlButtons::DOWN | CtrlButtons::RIGHT).within(10),
+            ComboStep::press(CtrlButtons::RIGHT | button).within(10),
+        ])
+    }
+
+    /// UP, UP, DOWN, DOWN, LEFT, RIGHT, LEFT, RIGHT, CIRCLE, CROSS (B and A on PSP) with half second for each step
+    pub fn konami_code(name: &'static str) -> Self {
+        let step = |buttons| ComboStep::press(buttons).within(30);
+
+        Self::new(name, &[
+            step(CtrlButtons::UP), step(CtrlButtons::UP), step(CtrlButtons::DOWN), step(CtrlButtons::DOWN),
+            step(CtrlButtons::LEFT), step(CtrlButtons::RIGHT), step(CtrlButtons::LEFT), step(CtrlButtons::RIGHT),
+            step(CtrlButtons::CIRCLE), step(CtrlButtons::CROSS),
+        ])
+    }
+}
+
+/// Combo entered by player
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboEvent {
+    /// Index of combo in order of 'ComboRecognizer::add'
+    pub index: usize,
+    pub name: &'static str,
+    /// Frame in which the last step was entered
+    pub frame: u32,
+}
+
+/// How far player got in one try of combo
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Progress {
+    /// Amount of already entered steps
+    steps: usize,
+    /// Frame in which the last entered step was entered
+    frame: u32,
+}
+
+/// Matches held buttons of consecutive frames against combos. Doesn't read controller itself, so it can be fed by any source (live input, replay, prepared frames on host).
+/// Step is entered in frame in which its buttons become held. Pressing button which doesn't enter the next step breaks try, too long delay between steps too.
+/// Every entered first step starts new try while older tries continue, so repeated beginning (UP, UP, UP, DOWN...) doesn't hide the combo
+pub struct ComboRecognizer {
+    combos: Vec<Combo>,
+    /// Running tries of each combo, the most advanced first (at most one for each amount of entered steps)
+    progress: Vec<Vec<Progress>>,
+    /// Frames in which held buttons changed with new held buttons (the newest at the end)
+    history: VecDeque<(u32, CtrlButtons)>,
+    previous: CtrlButtons,
+    frame: u32,
+    events: Vec<ComboEvent>,
+}
+
+impl ComboRecognizer {
+    pub fn new() -> Self {
+        Self { combos: Vec::new(), progress: Vec::new(), history: VecDeque::with_capacity(HISTORY_LEN), previous: CtrlButtons::empty(), frame: 0, events: Vec::new() }
+    }
+
+    /// Add combo to recognize. Returns its index used by 'ComboEvent'
+    pub fn add(&mut self, combo: Combo) -> usize {
+        self.combos.push(combo);
+        self.progress.push(Vec::new());
+        self.combos.len() - 1
+    }
+
+    /// Recent changes of held buttons (frame number and buttons held since it), from the oldest to the newest
+    pub fn history(&self) -> impl Iterator<Item = &(u32, CtrlButtons)> {
+        self.history.iter()
+    }
+
+    /// Forget started combos (e.g. after pause menu)
+    pub fn reset(&mut self) {
+        self.progress.iter_mut().for_each(Vec::clear);
+    }
+
+    /// Feed buttons held in next frame (stick can be added as directions, e.g. 'buttons | direction.to_buttons()'). Returns combos completed in this frame,
+    /// longer ones first (so when both "fireball" and "super fireball" are completed game can take only the first event)
+    pub fn update(&mut self, held: CtrlButtons) -> &[ComboEvent] {
+        let frame = self.frame;
+        self.frame = self.frame.wrapping_add(1);
+        self.events.clear();
+
+        let newly_pressed = held - self.previous;
+        let previous = core::mem::replace(&mut self.previous, held);
+        if held != previous {
+            if self.history.len() == HISTORY_LEN {
+                self.history.pop_front();
+            }
+            self.history.push_back((frame, held));
+        }
+
+        for (index, (combo, tries)) in self.combos.iter().zip(self.progress.iter_mut()).enumerate() {
+            if combo.steps.is_empty() {
+                continue;
+            }
+
+            // Every try waits for its next step. Too long delay or wrong button ends it
+            tries.retain_mut(|progress| {
+                let next = &combo.steps[progress.steps];
+                if frame.wrapping_sub(progress.frame) > next.window {
+                    false
+                } else if next.is_held(held) && !next.is_held(previous) {
+                    *progress = Progress { steps: progress.steps + 1, frame };
+                    true
+                } else {
+                    newly_pressed.is_empty()
+                }
+            });
+
+            let first = &combo.steps[0];
+            if first.is_held(held) && !first.is_held(previous) {
+                tries.push(Progress { steps: 1, frame });
+            }
+
+            // Tries with the same amount of steps wait for the same input, so only the newest one is kept
+            tries.sort_by_key(|progress| (core::cmp::Reverse(progress.steps), frame.wrapping_sub(progress.frame)));
+            tries.dedup_by_key(|progress| progress.steps);
+
+            if tries.first().is_some_and(|progress| progress.steps == combo.steps.len()) {
+                self.events.push(ComboEvent { index, name: combo.name, frame });
+                tries.clear();
+            }
+        }
+
+        let combos = &self.combos;
+        self.events.sort_by_key(|event| core::cmp::Reverse(combos[event.index].steps.len()));
+        &self.events
+    }
+}
+
+impl Default for ComboRecognizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Press each of 'buttons' for 2 frames with 2 frames of nothing after it. Returns names of entered combos
+    fn enter(recognizer: &mut ComboRecognizer, buttons: &[CtrlButtons]) -> Vec<&'static str> {
+        let mut entered = Vec::new();
+
+        for button in buttons {
+            for held in [*button, *button, CtrlButtons::empty(), CtrlButtons::empty()] {
+                entered.extend(recognizer.update(held).iter().map(|event| event.name));
+            }
+        }
+
+        entered
+    }
+
+    #[test]
+    fn konami_code() {
+        let mut recognizer = ComboRecognizer::new();
+        recognizer.add(Combo::konami_code("konami_code"));
+
+        let code = [
+            CtrlButtons::UP, CtrlButtons::UP, CtrlButtons::DOWN, CtrlButtons::DOWN, CtrlButtons::LEFT,
+            CtrlButtons::RIGHT, CtrlButtons::LEFT, CtrlButtons::RIGHT, CtrlButtons::CIRCLE, CtrlButtons::CROSS,
+        ];
+        assert_eq!(enter(&mut recognizer, &code), ["konami_code"]);
+    }
+
+    #[test]
+    fn konami_code_after_repeated_beginning() {
+        let mut recognizer = ComboRecognizer::new();
+        recognizer.add(Combo::konami_code("konami_code"));
+
+        let code = [
+            CtrlButtons::UP, CtrlButtons::UP, CtrlButtons::UP, CtrlButtons::DOWN, CtrlButtons::DOWN, CtrlButtons::LEFT,
+            CtrlButtons::RIGHT, CtrlButtons::LEFT, CtrlButtons::RIGHT, CtrlButtons::CIRCLE, CtrlButtons::CROSS,
+        ];
+        assert_eq!(enter(&mut recognizer, &code), ["konami_code"]);
+    }
+
+    #[test]
+    fn wrong_button_breaks_combo() {
+        let mut recognizer = ComboRecognizer::new();
+        recognizer.add(Combo::konami_code("konami_code"));
+
+        let code = [
+            CtrlButtons::UP, CtrlButtons::UP, CtrlButtons::DOWN, CtrlButtons::SQUARE, CtrlButtons::DOWN, CtrlButtons::LEFT,
+            CtrlButtons::RIGHT, CtrlButtons::LEFT, CtrlButtons::RIGHT, CtrlButtons::CIRCLE, CtrlButtons::CROSS,
+        ];
+        assert!(enter(&mut recognizer, &code).is_empty());
+    }
+
+    #[test]
+    fn too_slow_step_breaks_combo() {
+        let mut recognizer = ComboRecognizer::new();
+        recognizer.add(Combo::quarter_circle_forward("fireball", CtrlButtons::SQUARE));
+
+        recognizer.update(CtrlButtons::DOWN);
+        for _ in 0..20 {
+            recognizer.update(CtrlButtons::DOWN);
+        }
+        recognizer.update(CtrlButtons::DOWN | CtrlButtons::RIGHT);
+        assert!(recognizer.update(CtrlButtons::RIGHT | CtrlButtons::SQUARE).is_empty());
+    }
+}
@@ -0,0 +1,327 @@
+use alloc::vec::Vec;
+use psp::sys::CtrlButtons;
+use psp::math::sqrtf;
+use crate::input_manager::{InputManager, InputSampler};
+use crate::analog::{AnalogStick, ResponseCurve};
+
+/// Focusable UI element (button, slider, image tile) which cursor can point at. Position and size are in screen pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Widget {
+    /// Identifier chosen by application, reported in 'CursorEvent'
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Widget {
+    pub fn new(id: u32, x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { id, x, y, width, height }
+    }
+
+    pub fn contains(&self, (x, y): (f32, f32)) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (self.x + self.width / 2.0, self.y + self.height / 2.0)
+    }
+
+    /// Distance from point to the nearest point of widget (0 inside)
+    pub fn distance(&self, (x, y): (f32, f32)) -> f32 {
+        let dx = (self.x - x).max(x - (self.x + self.width)).max(0.0);
+        let dy = (self.y - y).max(y - (self.y + self.height)).max(0.0);
+        sqrtf(dx * dx + dy * dy)
+    }
+}
+
+/// Pointer interaction reported by 'Cursor::update'. 'widget' is id of widget under cursor (if any)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CursorEvent {
+    /// Cursor entered other widget or left all of them
+    HoverChanged { from: Option<u32>, to: Option<u32> },
+    Pressed { button: CtrlButtons, position: (f32, f32), widget: Option<u32> },
+    /// Button was pressed and released without dragging
+    Click { button: CtrlButtons, position: (f32, f32), widget: Option<u32> },
+    /// Cursor moved with held button further then 'Cursor::drag_threshold'. 'widget' is widget under press point
+    DragStarted { button: CtrlButtons, from: (f32, f32), widget: Option<u32> },
+    /// Cursor moved during drag by 'delta'
+    Dragged { button: CtrlButtons, position: (f32, f32), delta: (f32, f32) },
+    /// Button was released after drag. 'widget' is widget under drop point
+    DragEnded { button: CtrlButtons, from: (f32, f32), to: (f32, f32), widget: Option<u32> },
+}
+
+/// Button held down by cursor
+#[derive(Clone, Copy)]
+struct Press {
+    button: CtrlButtons,
+    from: (f32, f32),
+    widget: Option<u32>,
+    dragging: bool,
+}
+
+/// Virtual mouse pointer moved by analog stick. Speed grows while stick is pushed (slow start for precise pointing, fast travel across screen),
+/// released stick lets cursor snap to near widget. Buttons act like mouse buttons (press, click, drag)
+pub struct Cursor {
+    pub position: (f32, f32),
+    /// Area in which cursor can move (width, height), e.g. screen size
+    pub bounds: (f32, f32),
+    pub stick: AnalogStick,
+    /// Speed in pixels per second with fully pushed stick at the start of move
+    pub min_speed: f32,
+    /// Speed in pixels per second reached after 'acceleration_time' seconds of move
+    pub max_speed: f32,
+    pub acceleration_time: f32,
+    /// Widgets closer then this amount of pixels attract cursor when stick is released (0 turns snapping off)
+    pub snap_radius: f32,
+    /// Part of remaining distance to snapped widget covered per second
+    pub snap_rate: f32,
+    /// Distance in pixels which cursor must move with held button to start drag
+    pub drag_threshold: f32,
+    /// Buttons which act as mouse buttons (the first is "left" one)
+    pub buttons: Vec<CtrlButtons>,
+    moving_time: f32,
+    hovered: Option<u32>,
+    press: Option<Press>,
+    events: Vec<CursorEvent>,
+}
+
+impl Cursor {
+    /// Cursor in the middle of area with CROSS as primary and CIRCLE as secondary button
+    pub fn new(bounds: (f32, f32)) -> Self {
+        Self {
+            position: (bounds.0 / 2.0, bounds.1 / 2.0),
+            bounds,
+            stick: AnalogStick::new().with_curve(ResponseCurve::Quadratic),
+            min_speed: 60.0,
+            max_speed: 360.0,
+            acceleration_time: 0.6,
+            snap_radius: 16.0,
+            snap_rate: 15.0,
+            drag_threshold: 4.0,
+            buttons: alloc::vec![CtrlButtons::CROSS, CtrlButtons::CIRCLE],
+            moving_time: 0.0,
+            hovered: None,
+            press: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Widget under cursor
+    pub fn hovered(&self) -> Option<u32> {
+        self.hovered
+    }
+
+    /// Button which is held by cursor now (if any)
+    pub fn pressed(&self) -> Option<CtrlButtons> {
+        self.press.map(|press| press.button)
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.press.is_some_and(|press| press.dragging)
+    }
+
+    /// Move cursor by stick and check buttons. 'delta' is frame time in seconds, 'widgets' are focusable elements of current screen
+    /// (when they overlap, the last one is on top). Returns events of this frame
+    pub fn update<S: InputSampler>(&mut self, inputs: &InputManager<S>, delta: f32, widgets: &[Widget]) -> &[CursorEvent] {
+        self.events.clear();
+        let previous = self.position;
+
+        let (sx, sy) = self.stick.process(inputs.sample().stick);
+        if sx != 0.0 || sy != 0.0 {
+            self.moving_time += delta;
+            let progress = if self.acceleration_time > 0.0 { (self.moving_time / self.acceleration_time).min(1.0) } else { 1.0 };
+            let speed = self.min_speed + (self.max_speed - self.min_speed) * progress;
+            self.position.0 += sx * speed * delta;
+            self.position.1 += sy * speed * delta;
+        } else {
+            self.moving_time = 0.0;
+            if !self.is_dragging() && Self::widget_at(self.position, widgets).is_none() {
+                self.snap(delta, widgets);
+            }
+        }
+        // Bounds smaller then one pixel (or NaN) keep cursor at 0 instead of making 'clamp' panic
+        let (max_x, max_y) = ((self.bounds.0 - 1.0).max(0.0), (self.bounds.1 - 1.0).max(0.0));
+        self.position = (self.position.0.clamp(0.0, max_x), self.position.1.clamp(0.0, max_y));
+
+        let hovered = Self::widget_at(self.position, widgets);
+        if hovered != self.hovered {
+            self.events.push(CursorEvent::HoverChanged { from: self.hovered, to: hovered });
+            self.hovered = hovered;
+        }
+
+        // Only one button is handled at once, others are ignored until it's released
+        if self.press.is_none() {
+            if let Some(button) = self.buttons.iter().copied().find(|button| inputs.just_pressed(*button)) {
+                self.press = Some(Press { button, from: self.position, widget: hovered, dragging: false });
+                self.events.push(CursorEvent::Pressed { button, position: self.position, widget: hovered });
+            }
+        }
+
+        if let Some(press) = self.press.as_mut() {
+            let position = self.position;
+            let (dx, dy) = (position.0 - press.from.0, position.1 - press.from.1);
+
+            if !inputs.is_down(press.button) {
+                self.events.push(if press.dragging {
+                    CursorEvent::DragEnded { button: press.button, from: press.from, to: position, widget: hovered }
+                } else {
+                    CursorEvent::Click { button: press.button, position, widget: hovered }
+                });
+                self.press = None;
+            } else if !press.dragging && dx * dx + dy * dy > self.drag_threshold * self.drag_threshold {
+                press.dragging = true;
+                self.events.push(CursorEvent::DragStarted { button: press.button, from: press.from, widget: press.widget });
+                self.events.push(CursorEvent::Dragged { button: press.button, position, delta: (dx, dy) });
+            } else if press.dragging && position != previous {
+                self.events.push(CursorEvent::Dragged { button: press.button, position, delta: (position.0 - previous.0, position.1 - previous.1) });
+            }
+        }
+
+        &self.events
+    }
+
+    /// Pull cursor towards center of the nearest widget in 'snap_radius' (until cursor gets over it)
+    fn snap(&mut self, delta: f32, widgets: &[Widget]) {
+        let target = widgets
+            .iter()
+            .map(|widget| (widget.distance(self.position), widget))
+            .filter(|(distance, _)| *distance <= self.snap_radius)
+            .min_by(|(first, _), (second, _)| first.total_cmp(second))
+            .map(|(_, widget)| widget.center());
+
+        if let Some((x, y)) = target {
+            let (dx, dy) = (x - self.position.0, y - self.position.1);
+            let part = (self.snap_rate * delta).min(1.0);
+            self.position = (self.position.0 + dx * part, self.position.1 + dy * part);
+        }
+    }
+
+    fn widget_at(position: (f32, f32), widgets: &[Widget]) -> Option<u32> {
+        widgets.iter().rev().find(|widget| widget.contains(position)).map(|widget| widget.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_manager::{InputSample, ScriptedSampler};
+
+    /// Each update takes 0.1 s
+    const DELTA: f32 = 0.1;
+    const CENTER: (u8, u8) = (128, 128);
+    const RIGHT: (u8, u8) = (255, 128);
+
+    /// Manager fed by frames with specified buttons and stick position
+    fn inputs(frames: &[(CtrlButtons, (u8, u8))]) -> InputManager<ScriptedSampler> {
+        let samples = frames.iter().enumerate().map(|(i, (buttons, stick))| InputSample { buttons: *buttons, stick: *stick, timestamp: i as u32 * 100_000 });
+        InputManager::new(ScriptedSampler::new(samples, 100_000))
+    }
+
+    /// Cursor without snapping which moves by 'speed' pixels per second with fully pushed stick
+    fn cursor_at(position: (f32, f32), speed: f32) -> Cursor {
+        let mut cursor = Cursor::new((480.0, 272.0));
+        cursor.position = position;
+        cursor.min_speed = speed;
+        cursor.max_speed = speed;
+        cursor.snap_radius = 0.0;
+        cursor
+    }
+
+    /// Events of all frames
+    fn run(cursor: &mut Cursor, frames: &[(CtrlButtons, (u8, u8))], widgets: &[Widget]) -> Vec<Vec<CursorEvent>> {
+        let mut inputs = inputs(frames);
+        frames
+            .iter()
+            .map(|_| {
+                inputs.update();
+                cursor.update(&inputs, DELTA, widgets).to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hover() {
+        // The second widget is on top where they overlap
+        let widgets = [Widget::new(1, 0.0, 0.0, 50.0, 50.0), Widget::new(2, 40.0, 0.0, 50.0, 50.0)];
+        let mut cursor = cursor_at((45.0, 10.0), 0.0);
+        let mut inputs = inputs(&[(CtrlButtons::empty(), CENTER); 4]);
+
+        let mut hover_at = |position: (f32, f32)| {
+            cursor.position = position;
+            inputs.update();
+            cursor.update(&inputs, DELTA, &widgets).to_vec()
+        };
+
+        assert_eq!(hover_at((45.0, 10.0)), [CursorEvent::HoverChanged { from: None, to: Some(2) }]);
+        assert_eq!(hover_at((10.0, 10.0)), [CursorEvent::HoverChanged { from: Some(2), to: Some(1) }]);
+        assert_eq!(hover_at((20.0, 20.0)), []);
+        assert_eq!(hover_at((100.0, 100.0)), [CursorEvent::HoverChanged { from: Some(1), to: None }]);
+    }
+
+    #[test]
+    fn click() {
+        let widgets = [Widget::new(7, 0.0, 0.0, 100.0, 100.0)];
+        // Move shorter then drag threshold (3 px) is still click
+        let mut cursor = cursor_at((50.0, 50.0), 30.0);
+        let events = run(&mut cursor, &[(CtrlButtons::CROSS, CENTER), (CtrlButtons::CROSS, RIGHT), (CtrlButtons::empty(), CENTER)], &widgets);
+
+        assert_eq!(events[0][1], CursorEvent::Pressed { button: CtrlButtons::CROSS, position: (50.0, 50.0), widget: Some(7) });
+        assert!(events[1].is_empty());
+        assert_eq!(events[2], [CursorEvent::Click { button: CtrlButtons::CROSS, position: (53.0, 50.0), widget: Some(7) }]);
+        assert_eq!(cursor.pressed(), None);
+    }
+
+    #[test]
+    fn drag() {
+        let widgets = [Widget::new(1, 0.0, 0.0, 55.0, 100.0), Widget::new(2, 65.0, 0.0, 100.0, 100.0)];
+        let button = CtrlButtons::CIRCLE;
+        let mut cursor = cursor_at((50.0, 50.0), 100.0);
+        let frames = [(button, CENTER), (button, RIGHT), (button, RIGHT), (button, CENTER), (CtrlButtons::empty(), CENTER)];
+        let events = run(&mut cursor, &frames, &widgets);
+
+        assert_eq!(events[0][1], CursorEvent::Pressed { button, position: (50.0, 50.0), widget: Some(1) });
+        // Move by 10 px is longer then threshold, so drag starts with the whole move
+        assert_eq!(events[1], [
+            CursorEvent::HoverChanged { from: Some(1), to: None },
+            CursorEvent::DragStarted { button, from: (50.0, 50.0), widget: Some(1) },
+            CursorEvent::Dragged { button, position: (60.0, 50.0), delta: (10.0, 0.0) },
+        ]);
+        assert_eq!(events[2], [
+            CursorEvent::HoverChanged { from: None, to: Some(2) },
+            CursorEvent::Dragged { button, position: (70.0, 50.0), delta: (10.0, 0.0) },
+        ]);
+        // Cursor which doesn't move doesn't report drag
+        assert!(events[3].is_empty());
+        assert_eq!(events[4], [CursorEvent::DragEnded { button, from: (50.0, 50.0), to: (70.0, 50.0), widget: Some(2) }]);
+        assert!(!cursor.is_dragging());
+    }
+
+    #[test]
+    fn snapping() {
+        let widgets = [Widget::new(3, 100.0, 100.0, 20.0, 20.0)];
+        let mut cursor = cursor_at((95.0, 110.0), 0.0);
+        cursor.snap_radius = 16.0;
+        cursor.snap_rate = 5.0;
+
+        // Half of distance to widget center is covered in 0.1 s, then cursor stays over widget
+        let events = run(&mut cursor, &[(CtrlButtons::empty(), CENTER); 2], &widgets);
+        assert_eq!(events[0], [CursorEvent::HoverChanged { from: None, to: Some(3) }]);
+        assert_eq!(cursor.position, (102.5, 110.0));
+
+        // Widget further then snap radius doesn't attract cursor
+        let mut far = cursor_at((70.0, 110.0), 0.0);
+        far.snap_radius = 16.0;
+        run(&mut far, &[(CtrlButtons::empty(), CENTER)], &widgets);
+        assert_eq!(far.position, (70.0, 110.0));
+    }
+
+    #[test]
+    fn tiny_bounds_dont_panic() {
+        let mut cursor = Cursor::new((0.5, f32::NAN));
+        cursor.position = (10.0, 10.0);
+        run(&mut cursor, &[(CtrlButtons::empty(), RIGHT)], &[]);
+        assert_eq!(cursor.position, (0.0, 0.0));
+    }
+}
@@ -0,0 +1,368 @@
+use alloc::{vec::Vec, boxed::Box, collections::VecDeque};
+use psp::sys::CtrlButtons;
+use crate::input_manager::{InputManager, InputSampler};
+use crate::analog::{self, AnalogStick, Direction8};
+
+/// Something which happened with controller since previous 'EventSource::update'
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    /// Single button was pressed
+    ButtonDown(CtrlButtons),
+    /// Single button was released after being held for 'held' seconds
+    ButtonUp { button: CtrlButtons, held: f32 },
+    /// Held button repeats its press (see 'KeyRepeat'). 'count' starts from 1 for the first repeat
+    Repeat { button: CtrlButtons, count: u32 },
+    /// Processed stick position moved noticeably or its 8-way direction changed
+    AnalogMoved { position: (f32, f32), direction: Option<Direction8> },
+    /// Watched combination (see 'EventSource::watch_combination') became complete
+    Combination(CtrlButtons),
+}
+
+impl InputEvent {
+    /// Event is press or repeat of button (what menus usually react to)
+    pub fn is_press_of(&self, buttons: CtrlButtons) -> bool {
+        match self {
+            InputEvent::ButtonDown(button) | InputEvent::Repeat { button, .. } => buttons.contains(*button),
+            _ => false,
+        }
+    }
+}
+
+/// Auto-repeat of held buttons (e.g. for scrolling menu with held d-pad)
+#[derive(Clone, Copy, Debug)]
+pub struct KeyRepeat {
+    /// Buttons which repeat
+    pub buttons: CtrlButtons,
+    /// Seconds from press to the first repeat
+    pub delay: f32,
+    /// Repeats per second after the first one (0 turns repeating off)
+    pub rate: f32,
+}
+
+impl Default for KeyRepeat {
+    /// D-pad repeats after 0.4 s, 10 times per second
+    fn default() -> Self {
+        Self { buttons: CtrlButtons::UP | CtrlButtons::DOWN | CtrlButtons::LEFT | CtrlButtons::RIGHT, delay: 0.4, rate: 10.0 }
+    }
+}
+
+/// Turns controller samples into queue of 'InputEvent'. 'update' reads only one sample and returns immediately, so it's called once per frame
+/// from the same loop which renders (with 'PspSampler::non_blocking' it doesn't wait for vertical blank).
+/// Events of one update are queued in order: combinations, button downs, repeats, button ups, stick movement
+pub struct EventSource<S: InputSampler> {
+    inputs: InputManager<S>,
+    pub stick: AnalogStick,
+    /// Distance which stick must move from the last reported position to report it again
+    pub stick_step: f32,
+    /// How far stick must be pushed to have 8-way direction (0.0..=1.0)
+    pub stick_threshold: f32,
+    pub repeat: KeyRepeat,
+    combinations: Vec<CtrlButtons>,
+    /// Amount of repeats already sent for each button (bit of 'CtrlButtons')
+    repeats: [u32; 32],
+    last_stick: ((f32, f32), Option<Direction8>),
+    events: VecDeque<InputEvent>,
+}
+
+impl<S: InputSampler> EventSource<S> {
+    pub fn new(sampler: S) -> Self {
+        Self {
+            inputs: InputManager::new(sampler),
+            stick: AnalogStick::default(),
+            stick_step: 0.1,
+            stick_threshold: 0.5,
+            repeat: KeyRepeat::default(),
+            combinations: Vec::new(),
+            repeats: [0; 32],
+            last_stick: ((0.0, 0.0), None),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn with_repeat(mut self, repeat: KeyRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_stick(mut self, stick: AnalogStick) -> Self {
+        self.stick = stick;
+        self
+    }
+
+    /// Report 'InputEvent::Combination' when all specified buttons become held together
+    pub fn watch_combination(&mut self, buttons: CtrlButtons) {
+        if !buttons.is_empty() && !self.combinations.contains(&buttons) {
+            self.combinations.push(buttons);
+        }
+    }
+
+    /// Manager with current controller state (for polling style queries next to events, e.g. actions)
+    pub fn inputs(&self) -> &InputManager<S> {
+        &self.inputs
+    }
+
+    /// Buttons held now with stick 8-way direction added as d-pad buttons (e.g. for 'ComboRecognizer')
+    pub fn held_with_stick(&self) -> CtrlButtons {
+        self.inputs.sample().buttons | self.last_stick.1.map_or(CtrlButtons::empty(), |direction| direction.to_buttons())
+    }
+
+    /// Read next sample and queue events for it. Events which weren't taken earlier stay in queue
+    pub fn update(&mut self) {
+        self.inputs.update();
+
+        for combination in &self.combinations {
+            if self.inputs.just_pressed(*combination) {
+                self.events.push_back(InputEvent::Combination(*combination));
+            }
+        }
+
+        let buttons = self.inputs.sample().buttons | self.inputs.previous_sample().buttons;
+        let singles = || (0..32).map(|bit| CtrlButtons::from_bits_truncate(1 << bit)).filter(move |button| !button.is_empty() && buttons.contains(*button));
+
+        for button in singles() {
+            if self.inputs.just_pressed(button) {
+                self.events.push_back(InputEvent::ButtonDown(button));
+            }
+        }
+
+        for (bit, button) in singles().map(|button| (button.bits().trailing_zeros() as usize, button)) {
+            if !self.inputs.is_down(button) || !self.repeat.buttons.contains(button) || self.repeat.rate <= 0.0 {
+                self.repeats[bit] = 0;
+                continue;
+            }
+
+            let time = self.inputs.hold_time(button) - self.repeat.delay;
+            if time < 0.0 {
+                continue;
+            }
+
+            // Only one repeat per update, repeats missed during long frame are skipped instead of sent at once
+            let expected = (time * self.repeat.rate) as u32 + 1;
+            if expected > self.repeats[bit] {
+                self.repeats[bit] = expected;
+                self.events.push_back(InputEvent::Repeat { button, count: expected });
+            }
+        }
+
+        for button in singles() {
+            if let Some(held) = self.inputs.released_after(button) {
+                self.events.push_back(InputEvent::ButtonUp { button, held });
+            }
+        }
+
+        let position = self.stick.process(self.inputs.sample().stick);
+        let direction = analog::direction8(position, self.stick_threshold);
+        let ((last_x, last_y), last_direction) = self.last_stick;
+        let (dx, dy) = (position.0 - last_x, position.1 - last_y);
+        let returned = position == (0.0, 0.0) && (last_x, last_y) != (0.0, 0.0);
+
+        if direction != last_direction || returned || dx * dx + dy * dy >= self.stick_step * self.stick_step {
+            self.last_stick = (position, direction);
+            self.events.push_back(InputEvent::AnalogMoved { position, direction });
+        }
+    }
+
+    /// Take the oldest queued event
+    pub fn poll(&mut self) -> Option<InputEvent> {
+        self.events.pop_front()
+    }
+
+    /// Take all queued events
+    pub fn drain(&mut self) -> impl Iterator<Item = InputEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// Drop queued events (e.g. when screen changes and old presses shouldn't reach new one)
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// Handler returns true when it consumed event (then it isn't passed to next handlers)
+type Handler<C> = Box<dyn FnMut(&InputEvent, &mut C) -> bool>;
+
+/// Passes events to handlers registered for active screen (or scene), then to global handlers. Handlers get mutable context 'C' with game state,
+/// so they can e.g. move menu cursor or ask for other screen (dispatcher isn't borrowed by handlers, screen is switched by 'set_screen' after dispatch)
+pub struct EventDispatcher<C> {
+    screens: Vec<(&'static str, Vec<Handler<C>>)>,
+    global: Vec<Handler<C>>,
+    active: Option<&'static str>,
+}
+
+impl<C> EventDispatcher<C> {
+    pub fn new() -> Self {
+        Self { screens: Vec::new(), global: Vec::new(), active: None }
+    }
+
+    /// Register handler used only when 'screen' is active. Handlers of screen are called in order of registration
+    pub fn on(&mut self, screen: &'static str, handler: impl FnMut(&InputEvent, &mut C) -> bool + 'static) {
+        let handler: Handler<C> = Box::new(handler);
+        match self.screens.iter_mut().find(|(name, _)| *name == screen) {
+            Some((_, handlers)) => handlers.push(handler),
+            None => self.screens.push((screen, alloc::vec![handler])),
+        }
+    }
+
+    /// Register handler called for every screen after handlers of active screen
+    pub fn on_any(&mut self, handler: impl FnMut(&InputEvent, &mut C) -> bool + 'static) {
+        self.global.push(Box::new(handler));
+    }
+
+    /// Remove all handlers of screen (e.g. when level is unloaded)
+    pub fn remove_screen(&mut self, screen: &'static str) {
+        self.screens.retain(|(name, _)| *name != screen);
+        if self.active == Some(screen) {
+            self.active = None;
+        }
+    }
+
+    pub fn set_screen(&mut self, screen: &'static str) {
+        self.active = Some(screen);
+    }
+
+    pub fn screen(&self) -> Option<&'static str> {
+        self.active
+    }
+
+    /// Pass event to handlers. Returns true when some handler consumed it
+    pub fn dispatch(&mut self, event: &InputEvent, context: &mut C) -> bool {
+        let active = self.active;
+        let screen = self.screens.iter_mut().find(|(name, _)| Some(*name) == active).map(|(_, handlers)| handlers);
+
+        screen.into_iter().flatten().chain(self.global.iter_mut()).any(|handler| handler(event, context))
+    }
+
+    /// Dispatch all events queued in source
+    pub fn dispatch_all<S: InputSampler>(&mut self, source: &mut EventSource<S>, context: &mut C) {
+        while let Some(event) = source.poll() {
+            self.dispatch(&event, context);
+        }
+    }
+}
+
+impl<C> Default for EventDispatcher<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_manager::{InputSample, ScriptedSampler};
+
+    const CENTER: (u8, u8) = (128, 128);
+
+    /// Source fed by samples given as (buttons, stick, timestamp in milliseconds)
+    fn source(frames: &[(CtrlButtons, (u8, u8), u32)]) -> EventSource<ScriptedSampler> {
+        let samples = frames.iter().map(|(buttons, stick, ms)| InputSample { buttons: *buttons, stick: *stick, timestamp: ms * 1000 });
+        EventSource::new(ScriptedSampler::new(samples, 16_000))
+    }
+
+    /// Held buttons at specified moments (milliseconds)
+    fn held(buttons: CtrlButtons, moments: &[u32]) -> EventSource<ScriptedSampler> {
+        source(&moments.iter().map(|ms| (buttons, CENTER, *ms)).collect::<Vec<_>>())
+    }
+
+    fn next(source: &mut EventSource<ScriptedSampler>) -> Vec<InputEvent> {
+        source.update();
+        source.drain().collect()
+    }
+
+    fn repeats(source: &mut EventSource<ScriptedSampler>, updates: usize) -> Vec<(usize, u32)> {
+        (0..updates)
+            .flat_map(|update| next(source).into_iter().filter_map(move |event| match event {
+                InputEvent::Repeat { count, .. } => Some((update, count)),
+                _ => None,
+            }))
+            .collect()
+    }
+
+    /// Repeat after 0.35 s, then every 1/3 s (frame boundaries don't fall on repeat moments)
+    const REPEAT: KeyRepeat = KeyRepeat { buttons: CtrlButtons::DOWN, delay: 0.35, rate: 3.0 };
+
+    #[test]
+    fn repeat_delay_and_rate() {
+        let moments: Vec<u32> = (0..12).map(|frame| frame * 100).collect();
+
+        let mut down = held(CtrlButtons::DOWN, &moments).with_repeat(REPEAT);
+        assert_eq!(repeats(&mut down, 12), [(4, 1), (7, 2), (11, 3)]);
+
+        // Buttons which aren't listed and repeat turned off don't repeat
+        let mut cross = held(CtrlButtons::CROSS, &moments).with_repeat(REPEAT);
+        assert!(repeats(&mut cross, 12).is_empty());
+        let mut off = held(CtrlButtons::DOWN, &moments).with_repeat(KeyRepeat { rate: 0.0, ..REPEAT });
+        assert!(repeats(&mut off, 12).is_empty());
+    }
+
+    #[test]
+    fn missed_repeats_are_skipped() {
+        // Frame which took 1 second is followed by only one repeat which continues counting
+        let mut down = held(CtrlButtons::DOWN, &[0, 400, 1400, 1500]).with_repeat(REPEAT);
+        assert_eq!(repeats(&mut down, 4), [(1, 1), (2, 4)]);
+    }
+
+    #[test]
+    fn repeat_restarts_after_release() {
+        let frames = [(CtrlButtons::DOWN, CENTER, 0), (CtrlButtons::DOWN, CENTER, 400), (CtrlButtons::empty(), CENTER, 500), (CtrlButtons::DOWN, CENTER, 600), (CtrlButtons::DOWN, CENTER, 1000)];
+        let mut down = source(&frames).with_repeat(REPEAT);
+        assert_eq!(repeats(&mut down, 5), [(1, 1), (4, 1)]);
+    }
+
+    #[test]
+    fn events_of_one_update_are_ordered() {
+        let first = CtrlButtons::LEFT | CtrlButtons::TRIANGLE;
+        let second = CtrlButtons::LEFT | CtrlButtons::CROSS | CtrlButtons::CIRCLE;
+        let mut events = source(&[(first, CENTER, 0), (second, (255, 128), 500), (second, (255, 128), 700), (second, (255, 128), 1100)]).with_repeat(KeyRepeat { buttons: CtrlButtons::LEFT, ..REPEAT });
+        events.watch_combination(CtrlButtons::CROSS | CtrlButtons::CIRCLE);
+
+        // Single buttons go from the lowest bit
+        assert_eq!(next(&mut events), [InputEvent::ButtonDown(CtrlButtons::LEFT), InputEvent::ButtonDown(CtrlButtons::TRIANGLE)]);
+        assert_eq!(next(&mut events), [
+            InputEvent::Combination(CtrlButtons::CROSS | CtrlButtons::CIRCLE),
+            InputEvent::ButtonDown(CtrlButtons::CIRCLE),
+            InputEvent::ButtonDown(CtrlButtons::CROSS),
+            InputEvent::Repeat { button: CtrlButtons::LEFT, count: 1 },
+            InputEvent::ButtonUp { button: CtrlButtons::TRIANGLE, held: 0.5 },
+            InputEvent::AnalogMoved { position: (1.0, 0.0), direction: Some(Direction8::Right) },
+        ]);
+
+        // Events which aren't taken stay queued for next update
+        events.update();
+        events.update();
+        assert_eq!(events.poll(), Some(InputEvent::Repeat { button: CtrlButtons::LEFT, count: 2 }));
+        assert_eq!(events.poll(), Some(InputEvent::Repeat { button: CtrlButtons::LEFT, count: 3 }));
+        assert!(events.poll().is_none());
+    }
+
+    #[test]
+    fn analog_moved_thresholds() {
+        // Without radial deadzone and outer edge raw x maps to (x - 128) / 127
+        let stick = AnalogStick { radial_deadzone: 0.0, outer_edge: 1.0, ..AnalogStick::default() };
+        let raw = [128, 140, 141, 150, 190, 192, 193, 128];
+        let mut events = source(&raw.iter().enumerate().map(|(i, x)| (CtrlButtons::empty(), (*x, 128), i as u32 * 16)).collect::<Vec<_>>()).with_stick(stick);
+
+        let moved: Vec<_> = raw
+            .iter()
+            .map(|_| match next(&mut events).as_slice() {
+                [InputEvent::AnalogMoved { position, direction }] => Some((((position.0 * 127.0) + 0.5) as u8, *direction)),
+                [] => None,
+                other => panic!("unexpected events {:?}", other),
+            })
+            .collect();
+
+        assert_eq!(moved, [
+            // Movement shorter then 'stick_step' (0.1) isn't reported
+            None,
+            None,
+            Some((13, None)),
+            None,
+            Some((62, None)),
+            // Small movement is reported when 8-way direction changes (threshold 0.5)
+            Some((64, Some(Direction8::Right))),
+            None,
+            // Return to center is always reported
+            Some((0, None)),
+        ]);
+    }
+}
@@ -0,0 +1,320 @@
+use alloc::collections::VecDeque;
+use psp::sys::CtrlButtons;
+#[cfg(target_os = "psp")]
+use psp::sys::{self, SceCtrlData};
+
+/// One controller reading
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputSample {
+    pub buttons: CtrlButtons,
+    /// Raw analog stick position in 0..=255 range (128 is the center)
+    pub stick: (u8, u8),
+    /// Time of reading in microseconds (wraps around like 'SceCtrlData::timestamp')
+    pub timestamp: u32,
+}
+
+impl Default for InputSample {
+    fn default() -> Self {
+        Self { buttons: CtrlButtons::empty(), stick: (128, 128), timestamp: 0 }
+    }
+}
+
+/// Source of controller readings. 'InputManager' logic doesn't call PSP API directly, so it can be driven by prepared samples (e.g. on host or in replays)
+pub trait InputSampler {
+    /// Take next reading (called once per frame)
+    fn sample(&mut self) -> InputSample;
+}
+
+/// Reads real PSP controller. By default with 'sceCtrlReadBufferPositive', which waits for next controller sample (with default sampling cycle it's next vertical blank)
+#[cfg(target_os = "psp")]
+pub struct PspSampler {
+    blocking: bool,
+}
+
+#[cfg(target_os = "psp")]
+impl PspSampler {
+    /// Setup controller sampling (analog stick included)
+    pub unsafe fn new() -> Self {
+        sys::sceCtrlSetSamplingCycle(0);
+        sys::sceCtrlSetSamplingMode(sys::CtrlMode::Analog);
+        Self { blocking: true }
+    }
+
+    /// Sampler which takes the newest sample with 'sceCtrlPeekBufferPositive' and doesn't wait (for loops which already wait for vertical blank when rendering)
+    pub unsafe fn non_blocking() -> Self {
+        let mut sampler = Self::new();
+        sampler.blocking = false;
+        sampler
+    }
+}
+
+#[cfg(target_os = "psp")]
+impl InputSampler for PspSampler {
+    fn sample(&mut self) -> InputSample {
+        let mut data = SceCtrlData::default();
+        unsafe {
+            if self.blocking {
+                sys::sceCtrlReadBufferPositive(&mut data, 1);
+            } else {
+                sys::sceCtrlPeekBufferPositive(&mut data, 1);
+            }
+        }
+
+        InputSample { buttons: data.buttons, stick: (data.lx, data.ly), timestamp: data.timestamp }
+    }
+}
+
+/// Returns prepared samples one by one. When they end, the last one is repeated (with time going on by 'frame_time')
+pub struct ScriptedSampler {
+    samples: VecDeque<InputSample>,
+    last: InputSample,
+    /// Microseconds added to timestamp of repeated sample
+    frame_time: u32,
+}
+
+impl ScriptedSampler {
+    pub fn new(samples: impl IntoIterator<Item = InputSample>, frame_time: u32) -> Self {
+        Self { samples: samples.into_iter().collect(), last: InputSample::default(), frame_time }
+    }
+
+    /// Add sample at the end of script
+    pub fn push(&mut self, sample: InputSample) {
+        self.samples.push_back(sample);
+    }
+
+    /// Whether all prepared samples were already returned
+    pub fn is_finished(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+impl InputSampler for ScriptedSampler {
+    fn sample(&mut self) -> InputSample {
+        self.last = match self.samples.pop_front() {
+            Some(sample) => sample,
+            None => InputSample { timestamp: self.last.timestamp.wrapping_add(self.frame_time), ..self.last },
+        };
+
+        self.last
+    }
+}
+
+/// Controller state sampled once per frame with edges (pressed / released in this frame) and hold durations.
+/// Every query takes 'CtrlButtons' flags and treats them as combination, e.g. 'is_down(CIRCLE | CROSS)' is true only when both buttons are held
+pub struct InputManager<S: InputSampler> {
+    sampler: S,
+    current: InputSample,
+    previous: InputSample,
+    /// Timestamp when each button (bit of 'CtrlButtons') was pressed
+    pressed_at: [u32; 32],
+    /// Amount of frames for which each button is held
+    held_frames: [u32; 32],
+}
+
+impl<S: InputSampler> InputManager<S> {
+    pub fn new(sampler: S) -> Self {
+        Self { sampler, current: InputSample::default(), previous: InputSample::default(), pressed_at: [0; 32], held_frames: [0; 32] }
+    }
+
+    /// Read controller. Must be called once at the beginning of each frame
+    pub fn update(&mut self) {
+        self.previous = self.current;
+        self.current = self.sampler.sample();
+
+        for bit in 0..32 {
+            let button = CtrlButtons::from_bits_truncate(1 << bit);
+            if button.is_empty() || !self.current.buttons.contains(button) {
+                self.held_frames[bit] = 0;
+                continue;
+            }
+
+            if !self.previous.buttons.contains(button) {
+                self.pressed_at[bit] = self.current.timestamp;
+            }
+            self.held_frames[bit] += 1;
+        }
+    }
+
+    /// The newest sample
+    pub fn sample(&self) -> &InputSample {
+        &self.current
+    }
+
+    /// Sample from previous frame
+    pub fn previous_sample(&self) -> &InputSample {
+        &self.previous
+    }
+
+    /// Sampler which feeds this manager
+    pub fn sampler_mut(&mut self) -> &mut S {
+        &mut self.sampler
+    }
+
+    /// Take sampler back (e.g. to get recorded samples)
+    pub fn into_sampler(self) -> S {
+        self.sampler
+    }
+
+    /// All specified buttons are held now
+    pub fn is_down(&self, buttons: CtrlButtons) -> bool {
+        !buttons.is_empty() && self.current.buttons.contains(buttons)
+    }
+
+    /// At least one of specified buttons is held now
+    pub fn any_down(&self, buttons: CtrlButtons) -> bool {
+        self.current.buttons.intersects(buttons)
+    }
+
+    /// Combination became complete in this frame (all buttons are held now, but they weren't all held in previous frame).
+    /// For single button it's true only in frame in which button was pressed
+    pub fn just_pressed(&self, buttons: CtrlButtons) -> bool {
+        self.is_down(buttons) && !self.previous.buttons.contains(buttons)
+    }
+
+    /// Combination was broken in this frame (all buttons were held in previous frame, but they aren't all held now)
+    pub fn just_released(&self, buttons: CtrlButtons) -> bool {
+        !buttons.is_empty() && self.previous.buttons.contains(buttons) && !self.current.buttons.contains(buttons)
+    }
+
+    /// For how long (in seconds) whole combination is held. 0 when it isn't held
+    pub fn hold_time(&self, buttons: CtrlButtons) -> f32 {
+        if !self.is_down(buttons) {
+            return 0.0;
+        }
+
+        self.combination_age(buttons)
+    }
+
+    /// In frame in which combination was broken returns for how long (in seconds) it was held, otherwise None
+    pub fn released_after(&self, buttons: CtrlButtons) -> Option<f32> {
+        if !self.just_released(buttons) {
+            return None;
+        }
+
+        Some(self.combination_age(buttons))
+    }
+
+    /// For how many frames (including current one) whole combination is held. 0 when it isn't held
+    pub fn hold_frames(&self, buttons: CtrlButtons) -> u32 {
+        if !self.is_down(buttons) {
+            return 0;
+        }
+
+        Self::bits(buttons).map(|bit| self.held_frames[bit]).min().unwrap_or(0)
+    }
+
+    /// Seconds from the moment when combination became complete (when its last button was pressed) to the newest sample
+    fn combination_age(&self, buttons: CtrlButtons) -> f32 {
+        let shortest = Self::bits(buttons)
+            .map(|bit| self.current.timestamp.wrapping_sub(self.pressed_at[bit]))
+            .min()
+            .unwrap_or(0);
+
+        shortest as f32 / 1_000_000.0
+    }
+
+    /// Indexes of set bits
+    fn bits(buttons: CtrlButtons) -> impl Iterator<Item = usize> {
+        (0..32).filter(move |bit| buttons.bits() & (1 << bit) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One frame every 16 ms
+    const FRAME: u32 = 16_000;
+
+    fn sample(buttons: CtrlButtons, timestamp: u32) -> InputSample {
+        InputSample { buttons, timestamp, ..InputSample::default() }
+    }
+
+    /// Manager fed by consecutive frames with specified buttons held
+    fn manager(frames: &[CtrlButtons], start: u32) -> InputManager<ScriptedSampler> {
+        let samples = frames.iter().enumerate().map(|(i, buttons)| sample(*buttons, start.wrapping_add(i as u32 * FRAME)));
+        InputManager::new(ScriptedSampler::new(samples, FRAME))
+    }
+
+    fn seconds_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn single_button_edges_and_hold() {
+        let none = CtrlButtons::empty();
+        let cross = CtrlButtons::CROSS;
+        let mut input = manager(&[none, cross, cross, cross, none], 0);
+
+        input.update();
+        assert!(!input.just_pressed(cross) && !input.is_down(cross));
+
+        input.update();
+        assert!(input.just_pressed(cross));
+        assert_eq!(input.hold_frames(cross), 1);
+        assert!(seconds_eq(input.hold_time(cross), 0.0));
+
+        input.update();
+        assert!(!input.just_pressed(cross) && input.is_down(cross));
+        assert_eq!(input.hold_frames(cross), 2);
+        assert!(seconds_eq(input.hold_time(cross), 0.016));
+
+        input.update();
+        assert_eq!(input.hold_frames(cross), 3);
+        assert!(seconds_eq(input.hold_time(cross), 0.032));
+        assert_eq!(input.released_after(cross), None);
+
+        input.update();
+        assert!(input.just_released(cross));
+        assert_eq!(input.hold_frames(cross), 0);
+        assert!(seconds_eq(input.hold_time(cross), 0.0));
+        assert!(input.released_after(cross).is_some_and(|time| seconds_eq(time, 0.048)));
+
+        input.update();
+        assert!(!input.just_released(cross));
+        assert_eq!(input.released_after(cross), None);
+    }
+
+    #[test]
+    fn combination_edges_and_hold() {
+        let circle = CtrlButtons::CIRCLE;
+        let both = CtrlButtons::CIRCLE | CtrlButtons::CROSS;
+        let mut input = manager(&[circle, both, both, circle], 0);
+
+        // Combination isn't complete with one button
+        input.update();
+        assert!(input.just_pressed(circle));
+        assert!(!input.just_pressed(both) && !input.is_down(both));
+        assert!(input.any_down(both));
+
+        // Combination is complete when its last button is pressed
+        input.update();
+        assert!(input.just_pressed(both));
+        assert!(!input.just_pressed(circle));
+        assert_eq!(input.hold_frames(both), 1);
+        assert!(seconds_eq(input.hold_time(both), 0.0));
+        assert!(seconds_eq(input.hold_time(circle), 0.016));
+
+        input.update();
+        assert_eq!(input.hold_frames(both), 2);
+        assert!(seconds_eq(input.hold_time(both), 0.016));
+
+        // Releasing one button breaks combination, the other one is still held
+        input.update();
+        assert!(input.just_released(both));
+        assert!(!input.just_released(circle));
+        assert!(input.released_after(both).is_some_and(|time| seconds_eq(time, 0.032)));
+        assert_eq!(input.hold_frames(circle), 4);
+    }
+
+    #[test]
+    fn hold_time_across_timestamp_wrap() {
+        let cross = CtrlButtons::CROSS;
+        let mut input = manager(&[cross, cross, cross], u32::MAX - FRAME / 2);
+
+        input.update();
+        input.update();
+        input.update();
+        assert!(seconds_eq(input.hold_time(cross), 0.032));
+    }
+}
@@ -0,0 +1,348 @@
+use alloc::vec::Vec;
+use psp::sys::CtrlButtons;
+#[cfg(target_os = "psp")]
+use crate::file_system;
+use crate::checksum::{crc32, crc32_update};
+use crate::input_manager::{InputSampler, InputSample};
+
+/// First bytes of recording file
+const MAGIC: [u8; 4] = *b"PINR";
+/// Version of file layout, increased when layout changes
+const VERSION: u16 = 1;
+/// Magic, version, reserved, frame count, change count, frame time, checksum
+const HEADER_SIZE: usize = 4 + 2 + 2 + 4 + 4 + 4 + 4;
+/// Position of checksum in header (all bytes before it are checksummed together with changes)
+const CHECKSUM_OFFSET: usize = 20;
+/// Frame number, buttons, stick x, stick y
+const CHANGE_SIZE: usize = 4 + 4 + 1 + 1;
+
+/// Things which can go wrong while reading recording
+#[derive(Debug)]
+pub enum ReplayError {
+    /// File couldn't be read or written (contains error code returned by 'sceIo' function)
+    Io(i32),
+    /// File doesn't start with recording magic bytes
+    NotRecording,
+    /// File was written with other layout version
+    UnsupportedVersion(u16),
+    /// File is shorter then header says
+    Truncated,
+    /// Content doesn't match checksum from header (file is damaged)
+    ChecksumMismatch,
+    /// Changes aren't sorted by frame or don't fit in recorded frames
+    Invalid,
+}
+
+/// Controller state which starts in 'frame' and lasts until next change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Change {
+    frame: u32,
+    buttons: CtrlButtons,
+    stick: (u8, u8),
+}
+
+/// Controller samples of consecutive frames. Only frames in which something changed are stored, so few minutes of play takes a few KB.
+/// File layout (little endian): header ("PINR", version u16, reserved u16, frame count u32, change count u32, frame time in microseconds u32,
+/// CRC-32 of previous header fields and changes u32) followed by changes (frame number u32, buttons u32, stick x u8, stick y u8)
+#[derive(Clone, Debug, Default)]
+pub struct InputRecording {
+    changes: Vec<Change>,
+    /// Amount of recorded frames
+    frames: u32,
+    /// Average frame duration in microseconds (used for timestamps of replayed samples)
+    frame_time: u32,
+    /// Timestamp of the first and the last recorded sample
+    first_timestamp: u32,
+    last_timestamp: u32,
+}
+
+impl InputRecording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Amount of recorded frames
+    pub fn len(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames == 0
+    }
+
+    /// Add sample of next frame
+    pub fn push(&mut self, sample: &InputSample) {
+        if self.frames == 0 {
+            self.first_timestamp = sample.timestamp;
+        }
+        self.last_timestamp = sample.timestamp;
+
+        let changed = self.changes.last().is_none_or(|last| last.buttons != sample.buttons || last.stick != sample.stick);
+        if changed {
+            self.changes.push(Change { frame: self.frames, buttons: sample.buttons, stick: sample.stick });
+        }
+
+        self.frames += 1;
+        if self.frames > 1 {
+            self.frame_time = self.last_timestamp.wrapping_sub(self.first_timestamp) / (self.frames - 1);
+        }
+    }
+
+    /// Sample of specified frame (timestamps are counted from 0 with average frame time, so replays give the same results every time). None after the last frame
+    pub fn sample(&self, frame: u32) -> Option<InputSample> {
+        if frame >= self.frames {
+            return None;
+        }
+
+        // The last change which started before or in this frame
+        let index = self.changes.partition_point(|change| change.frame <= frame);
+        let change = self.changes[index - 1];
+
+        Some(InputSample { buttons: change.buttons, stick: change.stick, timestamp: frame.wrapping_mul(self.frame_time) })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut changes = Vec::with_capacity(self.changes.len() * CHANGE_SIZE);
+        for change in &self.changes {
+            changes.extend_from_slice(&change.frame.to_le_bytes());
+            changes.extend_from_slice(&change.buttons.bits().to_le_bytes());
+            changes.extend_from_slice(&[change.stick.0, change.stick.1]);
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + changes.len());
+        bytes.extend_from_slice(&MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+        bytes.extend_from_slice(&(self.changes.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.frame_time.to_le_bytes());
+        let checksum = checksum(&bytes, &changes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes.extend_from_slice(&changes);
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(if bytes.starts_with(&MAGIC) { ReplayError::Truncated } else { ReplayError::NotRecording });
+        }
+        if bytes[..4] != MAGIC {
+            return Err(ReplayError::NotRecording);
+        }
+
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+
+        let version = u16_at(4);
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let (frames, count, frame_time, stored_checksum) = (u32_at(8), u32_at(12) as usize, u32_at(16), u32_at(CHECKSUM_OFFSET));
+
+        let size = count.checked_mul(CHANGE_SIZE).ok_or(ReplayError::Truncated)?;
+        let data = bytes[HEADER_SIZE..].get(..size).ok_or(ReplayError::Truncated)?;
+        if checksum(&bytes[..CHECKSUM_OFFSET], data) != stored_checksum {
+            return Err(ReplayError::ChecksumMismatch);
+        }
+
+        let changes = data
+            .chunks_exact(CHANGE_SIZE)
+            .map(|change| Change {
+                frame: u32::from_le_bytes([change[0], change[1], change[2], change[3]]),
+                buttons: CtrlButtons::from_bits_truncate(u32::from_le_bytes([change[4], change[5], change[6], change[7]])),
+                stick: (change[8], change[9]),
+            })
+            .collect::<Vec<_>>();
+
+        // The first change must start recording and changes must be sorted, otherwise 'sample' wouldn't work
+        let sorted = changes.windows(2).all(|pair| pair[0].frame < pair[1].frame);
+        let starts = if frames > 0 { changes.first().map(|change| change.frame) == Some(0) } else { changes.is_empty() };
+        let fits = changes.last().is_none_or(|change| change.frame < frames);
+        if !sorted || !starts || !fits {
+            return Err(ReplayError::Invalid);
+        }
+
+        Ok(Self { changes, frames, frame_time, first_timestamp: 0, last_timestamp: frames.saturating_sub(1).wrapping_mul(frame_time) })
+    }
+
+    #[cfg(target_os = "psp")]
+    pub unsafe fn save(&self, path: &str) -> Result<(), ReplayError> {
+        file_system::write_file(path, &self.to_bytes()).map_err(ReplayError::Io)
+    }
+
+    #[cfg(target_os = "psp")]
+    pub unsafe fn load(path: &str) -> Result<Self, ReplayError> {
+        let bytes = file_system::read_file(path).map_err(ReplayError::Io)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// CRC-32 of header fields before checksum and changes, so damaged frame count or frame time is detected too
+fn checksum(header: &[u8], changes: &[u8]) -> u32 {
+    crc32_update(crc32(header), changes)
+}
+
+/// Passes samples from other sampler and records each of them
+pub struct RecordingSampler<S: InputSampler> {
+    sampler: S,
+    pub recording: InputRecording,
+}
+
+impl<S: InputSampler> RecordingSampler<S> {
+    pub fn new(sampler: S) -> Self {
+        Self { sampler, recording: InputRecording::new() }
+    }
+
+    /// Stop recording and take recorded samples
+    pub fn finish(self) -> InputRecording {
+        self.recording
+    }
+}
+
+impl<S: InputSampler> InputSampler for RecordingSampler<S> {
+    fn sample(&mut self) -> InputSample {
+        let sample = self.sampler.sample();
+        self.recording.push(&sample);
+        sample
+    }
+}
+
+/// Gives recorded samples instead of live controller, one per frame. After the last frame nothing is pressed
+pub struct ReplaySampler {
+    recording: InputRecording,
+    frame: u32,
+}
+
+impl ReplaySampler {
+    pub fn new(recording: InputRecording) -> Self {
+        Self { recording, frame: 0 }
+    }
+
+    /// Frame which will be returned by next 'sample'
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whether all recorded frames were already replayed
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.recording.len()
+    }
+
+    /// Start replay from the beginning (e.g. to loop attract mode demo)
+    pub fn restart(&mut self) {
+        self.frame = 0;
+    }
+}
+
+impl InputSampler for ReplaySampler {
+    fn sample(&mut self) -> InputSample {
+        let sample = self.recording.sample(self.frame).unwrap_or(InputSample {
+            timestamp: self.frame.wrapping_mul(self.recording.frame_time),
+            ..InputSample::default()
+        });
+
+        self.frame = self.frame.saturating_add(1);
+        sample
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_manager::ScriptedSampler;
+
+    /// One frame every 16 ms
+    const FRAME: u32 = 16_000;
+
+    /// Samples of consecutive frames starting at timestamp 0
+    fn script() -> Vec<InputSample> {
+        let states = [
+            (CtrlButtons::empty(), (128, 128)),
+            (CtrlButtons::CROSS, (128, 128)),
+            (CtrlButtons::CROSS, (128, 128)),
+            (CtrlButtons::CROSS | CtrlButtons::LEFT, (20, 128)),
+            (CtrlButtons::empty(), (20, 200)),
+            (CtrlButtons::empty(), (20, 200)),
+        ];
+
+        states.iter().enumerate().map(|(i, (buttons, stick))| InputSample { buttons: *buttons, stick: *stick, timestamp: i as u32 * FRAME }).collect()
+    }
+
+    fn recorded() -> InputRecording {
+        let mut sampler = RecordingSampler::new(ScriptedSampler::new(script(), FRAME));
+        for _ in 0..script().len() {
+            sampler.sample();
+        }
+
+        sampler.finish()
+    }
+
+    /// Write checksum matching changed content, so only layout checks can fail
+    fn reseal(bytes: &mut [u8]) {
+        let checksum = checksum(&bytes[..CHECKSUM_OFFSET], &bytes[HEADER_SIZE..]);
+        bytes[CHECKSUM_OFFSET..HEADER_SIZE].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn record_save_and_replay() {
+        let bytes = recorded().to_bytes();
+        // Only frames in which something changed are stored
+        assert_eq!(bytes.len(), HEADER_SIZE + 4 * CHANGE_SIZE);
+
+        let recording = InputRecording::from_bytes(&bytes).unwrap();
+        assert_eq!(recording.len(), 6);
+
+        let mut replay = ReplaySampler::new(recording);
+        for sample in script() {
+            assert!(!replay.is_finished());
+            assert_eq!(replay.sample(), sample);
+        }
+
+        // Nothing is pressed after the last frame, but time goes on
+        assert!(replay.is_finished());
+        assert_eq!(replay.sample(), InputSample { timestamp: 6 * FRAME, ..InputSample::default() });
+
+        replay.restart();
+        assert_eq!(replay.sample(), script()[0]);
+    }
+
+    #[test]
+    fn damaged_content() {
+        let bytes = recorded().to_bytes();
+
+        // Frame time in header and stick position in change
+        for position in [16, HEADER_SIZE + CHANGE_SIZE + 8] {
+            let mut damaged = bytes.clone();
+            damaged[position] ^= 1;
+            assert!(matches!(InputRecording::from_bytes(&damaged), Err(ReplayError::ChecksumMismatch)));
+        }
+
+        assert!(matches!(InputRecording::from_bytes(&bytes[..bytes.len() - 1]), Err(ReplayError::Truncated)));
+        assert!(matches!(InputRecording::from_bytes(&bytes[..HEADER_SIZE - 1]), Err(ReplayError::Truncated)));
+        assert!(matches!(InputRecording::from_bytes(b"RIFF"), Err(ReplayError::NotRecording)));
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&2u16.to_le_bytes());
+        assert!(matches!(InputRecording::from_bytes(&newer), Err(ReplayError::UnsupportedVersion(2))));
+    }
+
+    #[test]
+    fn invalid_changes() {
+        let bytes = recorded().to_bytes();
+        // Changes start in frames 0, 1, 3 and 4. Each case moves one change, so unsorted changes, recording which doesn't start with change
+        // and change after the last frame are checked (with valid checksum)
+        for (change, frame) in [(2, 1u32), (0, 1), (3, 6)] {
+            let mut invalid = bytes.clone();
+            let at = HEADER_SIZE + change * CHANGE_SIZE;
+            invalid[at..at + 4].copy_from_slice(&frame.to_le_bytes());
+            reseal(&mut invalid);
+            assert!(matches!(InputRecording::from_bytes(&invalid), Err(ReplayError::Invalid)));
+        }
+
+        // Empty recording is valid
+        let empty = InputRecording::from_bytes(&InputRecording::new().to_bytes()).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.sample(0), None);
+    }
+}
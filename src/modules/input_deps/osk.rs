@@ -0,0 +1,265 @@
+use alloc::{vec::Vec, string::String};
+#[cfg(target_os = "psp")]
+use core::mem::ManuallyDrop;
+#[cfg(target_os = "psp")]
+use alloc::boxed::Box;
+#[cfg(target_os = "psp")]
+use psp::sys::{
+    self, SceUtilityOskParams, SceUtilityOskData, SceUtilityOskInputType, SceUtilityOskInputLanguage, SceUtilityOskResult,
+    SceUtilityOskState, UtilityDialogCommon, UtilityDialogButtonAccept, SystemParamId, SystemParamLanguage,
+};
+
+/// Values returned by 'sceUtilityOskGetStatus' (common for all utility dialogs)
+#[cfg(target_os = "psp")]
+mod status {
+    pub const NONE: i32 = 0;
+    pub const INITIALIZING: i32 = 1;
+    pub const VISIBLE: i32 = 2;
+    pub const QUIT: i32 = 3;
+    pub const FINISHED: i32 = 4;
+}
+
+/// How on-screen keyboard should look and what it should accept
+#[cfg(target_os = "psp")]
+pub struct OskConfig {
+    /// Description shown above text field
+    pub title: String,
+    pub initial_text: String,
+    /// Maximal amount of characters (UTF-16 units)
+    pub max_length: usize,
+    /// Characters which can be entered (other types can be chosen with SELECT)
+    pub input_type: SceUtilityOskInputType,
+    pub language: SceUtilityOskInputLanguage,
+}
+
+#[cfg(target_os = "psp")]
+impl OskConfig {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: String::from(title),
+            initial_text: String::new(),
+            max_length: 32,
+            input_type: SceUtilityOskInputType::All,
+            language: SceUtilityOskInputLanguage::Default,
+        }
+    }
+
+    pub fn initial_text(mut self, text: &str) -> Self {
+        self.initial_text = String::from(text);
+        self
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length.max(1);
+        self
+    }
+
+    pub fn input_type(mut self, input_type: SceUtilityOskInputType) -> Self {
+        self.input_type = input_type;
+        self
+    }
+
+    pub fn language(mut self, language: SceUtilityOskInputLanguage) -> Self {
+        self.language = language;
+        self
+    }
+}
+
+/// How keyboard was closed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OskResult {
+    /// Player confirmed text (it can be the same as initial one)
+    Entered(String),
+    Cancelled,
+}
+
+/// System on-screen keyboard opened over running game. Keyboard draws itself into draw buffer, so 'update' must be called every frame
+/// after GE finished drawing frame and before buffers are swapped (see 'GMng::end_frame_with_dialog' in 'graphics.rs')
+#[cfg(target_os = "psp")]
+pub struct Osk {
+    buffers: ManuallyDrop<OskBuffers>,
+    result: Option<OskResult>,
+}
+
+/// Memory given to system while keyboard is opened. Structures and texts are boxed, so addresses given to system stay valid when 'Osk' is moved
+#[cfg(target_os = "psp")]
+struct OskBuffers {
+    _params: Box<SceUtilityOskParams>,
+    data: Box<SceUtilityOskData>,
+    _title: Vec<u16>,
+    _initial_text: Vec<u16>,
+    output: Vec<u16>,
+}
+
+#[cfg(target_os = "psp")]
+impl Osk {
+    /// Open keyboard. On failure returns error code of 'sceUtilityOskInitStart' (e.g. when other utility dialog is already opened)
+    pub fn open(config: &OskConfig) -> Result<Self, i32> {
+        let mut title = to_utf16(&config.title);
+        let mut initial_text = to_utf16(&config.initial_text);
+        // Output must have place for terminating 0
+        let mut output = alloc::vec![0u16; config.max_length + 1];
+
+        let mut data = Box::new(SceUtilityOskData {
+            unk_00: 0,
+            unk_04: 0,
+            language: config.language,
+            unk_12: 0,
+            inputtype: config.input_type,
+            lines: 1,
+            unk_24: 0,
+            desc: title.as_mut_ptr(),
+            intext: initial_text.as_mut_ptr(),
+            outtextlength: output.len() as i32,
+            outtext: output.as_mut_ptr(),
+            result: SceUtilityOskResult::Unchanged,
+            outtextlimit: config.max_length as i32,
+        });
+
+        let mut params = Box::new(SceUtilityOskParams {
+            base: unsafe { dialog_common(core::mem::size_of::<SceUtilityOskParams>()) },
+            datacount: 1,
+            data: &mut *data,
+            state: SceUtilityOskState::None,
+            unk_60: 0,
+        });
+
+        // Params and everything they point to are boxed and kept in 'OskBuffers' until system stops using them (see 'Drop')
+        let result = unsafe { sys::sceUtilityOskInitStart(&mut *params) };
+        if result < 0 {
+            return Err(result);
+        }
+
+        let buffers = OskBuffers { _params: params, data, _title: title, _initial_text: initial_text, output };
+        Ok(Self { buffers: ManuallyDrop::new(buffers), result: None })
+    }
+
+    /// Let keyboard handle input and draw itself. Returns Some when keyboard is closed completely (after that it shouldn't be updated anymore)
+    pub fn update(&mut self) -> Option<OskResult> {
+        match unsafe { sys::sceUtilityOskGetStatus() } {
+            // Keyboard is starting or shutting down
+            status::INITIALIZING | status::FINISHED => {},
+            status::VISIBLE => {
+                unsafe { sys::sceUtilityOskUpdate(1) };
+            },
+            status::QUIT => {
+                // Text is taken before keyboard is shut down
+                self.result = Some(match self.buffers.data.result {
+                    SceUtilityOskResult::Cancelled => OskResult::Cancelled,
+                    _ => OskResult::Entered(from_utf16(&self.buffers.output)),
+                });
+                unsafe { sys::sceUtilityOskShutdownStart() };
+            },
+            // Shutdown ends with NONE status, only then next dialog can be opened
+            status::NONE => {
+                if self.result.is_some() {
+                    return self.result.take();
+                }
+            },
+            _ => {},
+        }
+
+        None
+    }
+}
+
+#[cfg(target_os = "psp")]
+impl Drop for Osk {
+    fn drop(&mut self) {
+        // System still uses buffers of keyboard which wasn't closed, so it's shut down and awaited before they are freed
+        unsafe {
+            let mut shutdown_started = false;
+            for _ in 0..200 {
+                match sys::sceUtilityOskGetStatus() {
+                    status::NONE => {
+                        ManuallyDrop::drop(&mut self.buffers);
+                        return;
+                    },
+                    // Keyboard which is still starting can be shut down only after it becomes visible
+                    status::VISIBLE | status::QUIT if !shutdown_started => {
+                        sys::sceUtilityOskShutdownStart();
+                        shutdown_started = true;
+                    },
+                    _ => {},
+                }
+                sys::sceKernelDelayThread(10_000);
+            }
+            // Keyboard didn't shut down in time, so its buffers are leaked instead of being freed under system
+        }
+    }
+}
+
+/// Common part of utility dialog params with language and accept button taken from system settings
+#[cfg(target_os = "psp")]
+unsafe fn dialog_common(size: usize) -> UtilityDialogCommon {
+    let mut language = 0;
+    sys::sceUtilityGetSystemParamInt(SystemParamId::Language, &mut language);
+
+    // Parameter 9 tells which button confirms dialogs (0 = CIRCLE used in Japan, 1 = CROSS)
+    let mut button = 1;
+    sys::sceUtilityGetSystemParamInt(SystemParamId::Unknown, &mut button);
+
+    UtilityDialogCommon {
+        size: size as u32,
+        language: SystemParamLanguage::try_from(language as u32).unwrap_or(SystemParamLanguage::English),
+        button_accept: if button == 0 { UtilityDialogButtonAccept::Circle } else { UtilityDialogButtonAccept::Cross },
+        // Thread priorities used by PSPSDK samples
+        graphics_thread: 17,
+        access_thread: 19,
+        font_thread: 18,
+        sound_thread: 16,
+        result: 0,
+        reserved: [0; 4],
+    }
+}
+
+/// Text as UTF-16 ended with 0 (form used by system dialogs)
+pub fn to_utf16(text: &str) -> Vec<u16> {
+    let mut utf16: Vec<u16> = text.encode_utf16().collect();
+    utf16.push(0);
+    utf16
+}
+
+/// Text from UTF-16 buffer which ends with 0 (or at the end of buffer). Broken surrogate pairs are replaced with U+FFFD
+pub fn from_utf16(utf16: &[u16]) -> String {
+    let end = utf16.iter().position(|unit| *unit == 0).unwrap_or(utf16.len());
+
+    core::char::decode_utf16(utf16[..end].iter().copied())
+        .map(|c| c.unwrap_or(core::char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn surrogate_pair_round_trip() {
+        let text = "key 🔑";
+        let utf16 = to_utf16(text);
+
+        // Emoji outside of BMP takes 2 units, after them there is terminating 0
+        assert_eq!(utf16.len(), 4 + 2 + 1);
+        assert_eq!(utf16.last(), Some(&0));
+        assert_eq!(from_utf16(&utf16), text);
+    }
+
+    #[test]
+    fn buffer_without_terminator() {
+        let utf16: Vec<u16> = "name".encode_utf16().collect();
+        assert_eq!(from_utf16(&utf16), "name");
+    }
+
+    #[test]
+    fn text_after_terminator_is_ignored() {
+        let mut utf16 = to_utf16("new");
+        utf16.extend("old text".encode_utf16());
+        assert_eq!(from_utf16(&utf16), "new");
+    }
+
+    #[test]
+    fn lone_surrogate_is_replaced() {
+        let utf16 = [0x0061, 0xd83d, 0x0062, 0xdd11, 0];
+        assert_eq!(from_utf16(&utf16), "a\u{fffd}b\u{fffd}");
+    }
+}
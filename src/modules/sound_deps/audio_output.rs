@@ -0,0 +1,365 @@
+use alloc::{boxed::Box, sync::Arc};
+use core::cell::UnsafeCell;
+#[cfg(target_os = "psp")]
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_os = "psp")]
+use core::sync::atomic::{AtomicBool, AtomicI32};
+#[cfg(target_os = "psp")]
+use psp::sys::{self, AudioFormat, SceUid, ThreadAttributes, AUDIO_NEXT_CHANNEL, AUDIO_SAMPLE_MIN, AUDIO_SAMPLE_MAX, AUDIO_VOLUME_MAX};
+
+/// Sample rate of 'sceAudio' channels (it can't be changed, sounds with other rates must be resampled)
+pub const SAMPLE_RATE: u32 = 44100;
+
+/// Amount of channels in output samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channels {
+    Mono,
+    /// Samples are interleaved: left, right, left, right...
+    Stereo,
+}
+
+impl Channels {
+    pub fn count(self) -> usize {
+        match self {
+            Channels::Mono => 1,
+            Channels::Stereo => 2,
+        }
+    }
+
+    #[cfg(target_os = "psp")]
+    fn format(self) -> AudioFormat {
+        match self {
+            Channels::Mono => AudioFormat::Mono,
+            Channels::Stereo => AudioFormat::Stereo,
+        }
+    }
+}
+
+/// Setup of output channel
+#[cfg(target_os = "psp")]
+#[derive(Clone, Copy, Debug)]
+pub struct AudioConfig {
+    /// Samples (per channel) in one block given to 'sceAudioOutputBlocking'. Bigger blocks are safer against stutter, smaller ones give lower latency
+    pub sample_count: u32,
+    pub channels: Channels,
+    /// 0..='AUDIO_VOLUME_MAX'
+    pub volume: i32,
+    /// Priority of output thread (lower number = higher priority). Audio thread should be more important then game thread
+    pub priority: i32,
+}
+
+#[cfg(target_os = "psp")]
+impl AudioConfig {
+    /// Stereo output with full volume. Sample count is rounded up to multiply of 64 in range accepted by 'sceAudioChReserve'
+    pub fn new(sample_count: u32) -> Self {
+        let sample_count = ((sample_count.max(AUDIO_SAMPLE_MIN) + 63) & !63).min(AUDIO_SAMPLE_MAX);
+        Self { sample_count, channels: Channels::Stereo, volume: AUDIO_VOLUME_MAX as i32, priority: 0x12 }
+    }
+
+    pub fn mono(mut self) -> Self {
+        self.channels = Channels::Mono;
+        self
+    }
+
+    pub fn volume(mut self, volume: i32) -> Self {
+        self.volume = volume.clamp(0, AUDIO_VOLUME_MAX as i32);
+        self
+    }
+
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Length of one block in 'i16' values (samples of all channels)
+    pub fn block_len(&self) -> usize {
+        self.sample_count as usize * self.channels.count()
+    }
+}
+
+/// Things which can go wrong while starting output
+#[derive(Debug)]
+pub enum AudioError {
+    /// All hardware channels are used (contains error code returned by 'sceAudioChReserve')
+    Channel(i32),
+    /// Output thread couldn't be created or started (contains error code)
+    Thread(i32),
+}
+
+/// Something which produces samples. It's called from output thread, so it must be 'Send'
+pub trait AudioSource: Send {
+    /// Fill whole block with next samples (interleaved for stereo). Block is given zeroed, so silent source can leave it as it is
+    fn fill(&mut self, block: &mut [i16]);
+}
+
+impl<F: FnMut(&mut [i16]) + Send> AudioSource for F {
+    fn fill(&mut self, block: &mut [i16]) {
+        self(block)
+    }
+}
+
+/// Data shared by 'AudioOutput' and its thread. It's freed only after thread ended
+#[cfg(target_os = "psp")]
+struct Shared {
+    running: AtomicBool,
+    volume: AtomicI32,
+    channel: i32,
+    block_len: usize,
+    /// Used only by output thread while it's running
+    source: UnsafeCell<Box<dyn AudioSource>>,
+}
+
+/// Sound played on reserved hardware channel by dedicated thread. Thread asks source for next block while previous one is being played (double buffering),
+/// so source has got whole block duration (e.g. 1024 samples = 23 ms) to prepare samples. Output is stopped by 'stop' or when it's dropped
+#[cfg(target_os = "psp")]
+pub struct AudioOutput {
+    shared: *mut Shared,
+    thread: SceUid,
+    config: AudioConfig,
+}
+
+#[cfg(target_os = "psp")]
+impl AudioOutput {
+    /// Reserve channel and start output thread which plays samples from 'source'
+    pub unsafe fn start(config: AudioConfig, source: impl AudioSource + 'static) -> Result<Self, AudioError> {
+        let channel = sys::sceAudioChReserve(AUDIO_NEXT_CHANNEL, config.sample_count as i32, config.channels.format());
+        if channel < 0 {
+            return Err(AudioError::Channel(channel));
+        }
+
+        let shared = Box::into_raw(Box::new(Shared {
+            running: AtomicBool::new(true),
+            volume: AtomicI32::new(config.volume),
+            channel,
+            block_len: config.block_len(),
+            source: UnsafeCell::new(Box::new(source)),
+        }));
+
+        // Sources may use VFPU math ('psp::math::sinf' for generated tones), thread without VFPU attribute would crash on it
+        let thread = sys::sceKernelCreateThread(b"audio_output\0".as_ptr(), output_thread, config.priority, 0x4000, ThreadAttributes::USER | ThreadAttributes::VFPU, core::ptr::null_mut());
+        // Thread gets its own copy of argument, so pointer to shared data is passed as bytes
+        let mut argument = shared;
+        let started = if thread.0 < 0 { thread.0 } else { sys::sceKernelStartThread(thread, core::mem::size_of::<*mut Shared>(), &mut argument as *mut _ as *mut c_void) };
+
+        if started < 0 {
+            if thread.0 >= 0 {
+                sys::sceKernelDeleteThread(thread);
+            }
+            sys::sceAudioChRelease(channel);
+            drop(Box::from_raw(shared));
+            return Err(AudioError::Thread(started));
+        }
+
+        Ok(Self { shared, thread, config })
+    }
+
+    pub fn config(&self) -> &AudioConfig {
+        &self.config
+    }
+
+    /// Change volume (0..='AUDIO_VOLUME_MAX'), used from next block
+    pub fn set_volume(&self, volume: i32) {
+        unsafe { (*self.shared).volume.store(volume.clamp(0, AUDIO_VOLUME_MAX as i32), Ordering::Relaxed) };
+    }
+
+    /// Stop thread after block which is being prepared, wait until channel played everything and release it
+    pub fn stop(self) {
+        drop(self);
+    }
+}
+
+#[cfg(target_os = "psp")]
+impl Drop for AudioOutput {
+    fn drop(&mut self) {
+        unsafe {
+            let channel = (*self.shared).channel;
+
+            (*self.shared).running.store(false, Ordering::Release);
+            sys::sceKernelWaitThreadEnd(self.thread, core::ptr::null_mut());
+            sys::sceKernelDeleteThread(self.thread);
+
+            // Channel can't be released while it still plays the last block
+            while sys::sceAudioGetChannelRestLen(channel) > 0 {
+                sys::sceKernelDelayThread(1000);
+            }
+            sys::sceAudioChRelease(channel);
+
+            drop(Box::from_raw(self.shared));
+        }
+    }
+}
+
+/// Body of output thread. 'argp' points to copy of pointer to 'Shared'
+#[cfg(target_os = "psp")]
+unsafe extern "C" fn output_thread(_args: usize, argp: *mut c_void) -> i32 {
+    let shared = &*(*(argp as *const *mut Shared));
+    let source = &mut *shared.source.get();
+
+    // One block is played while the other is filled
+    let mut blocks = [alloc::vec![0i16; shared.block_len], alloc::vec![0i16; shared.block_len]];
+    let mut current = 0;
+
+    while shared.running.load(Ordering::Acquire) {
+        let block = &mut blocks[current];
+        block.fill(0);
+        source.fill(block);
+
+        // Audio hardware reads samples from RAM, not from CPU cache
+        sys::sceKernelDcacheWritebackRange(block.as_ptr() as *const c_void, (block.len() * 2) as u32);
+        sys::sceAudioOutputBlocking(shared.channel, shared.volume.load(Ordering::Relaxed), block.as_mut_ptr() as *mut c_void);
+
+        current ^= 1;
+    }
+
+    0
+}
+
+/// Samples waiting for output thread, written by game thread with 'AudioProducer' and read by output thread with 'AudioConsumer' (without locks)
+struct Ring {
+    /// Length is power of two, so position masked from wrapping counter stays continuous when counter overflows
+    samples: Box<[UnsafeCell<i16>]>,
+    /// Length of buffer - 1
+    mask: usize,
+    /// Total amount of written and read values (positions in buffer are these values masked by 'mask')
+    written: AtomicUsize,
+    read: AtomicUsize,
+    underruns: AtomicUsize,
+}
+
+// Each value is accessed by only one side at a time: producer writes only free part and consumer reads only filled part
+unsafe impl Sync for Ring {}
+
+/// Ring buffer for samples produced outside of output thread (e.g. decoded by game loop). Returns writing and reading end,
+/// reading end is 'AudioSource' given to 'AudioOutput::start'. 'capacity' is in 'i16' values (samples of all channels), it's rounded up to power of two
+pub fn ring_buffer(capacity: usize) -> (AudioProducer, AudioConsumer) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let ring = Arc::new(Ring {
+        samples: (0..capacity).map(|_| UnsafeCell::new(0)).collect(),
+        mask: capacity - 1,
+        written: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+        underruns: AtomicUsize::new(0),
+    });
+
+    (AudioProducer { ring: ring.clone() }, AudioConsumer { ring })
+}
+
+/// Writing end of 'ring_buffer'
+pub struct AudioProducer {
+    ring: Arc<Ring>,
+}
+
+impl AudioProducer {
+    /// Amount of values which can be pushed now
+    pub fn free(&self) -> usize {
+        let ring = &self.ring;
+        ring.samples.len() - ring.written.load(Ordering::Relaxed).wrapping_sub(ring.read.load(Ordering::Acquire))
+    }
+
+    /// Push as many samples as fits. Returns amount of pushed values
+    pub fn push(&mut self, samples: &[i16]) -> usize {
+        let ring = &self.ring;
+        let count = samples.len().min(self.free());
+        let written = ring.written.load(Ordering::Relaxed);
+
+        for (i, sample) in samples[..count].iter().enumerate() {
+            unsafe { *ring.samples[written.wrapping_add(i) & ring.mask].get() = *sample };
+        }
+        ring.written.store(written.wrapping_add(count), Ordering::Release);
+
+        count
+    }
+
+    /// How many times output thread had to play silence because buffer was empty
+    pub fn underruns(&self) -> usize {
+        self.ring.underruns.load(Ordering::Relaxed)
+    }
+}
+
+/// Reading end of 'ring_buffer'. When there isn't enough samples, the rest of block stays silent
+pub struct AudioConsumer {
+    ring: Arc<Ring>,
+}
+
+impl AudioConsumer {
+    /// Amount of values waiting in buffer
+    pub fn available(&self) -> usize {
+        let ring = &self.ring;
+        ring.written.load(Ordering::Acquire).wrapping_sub(ring.read.load(Ordering::Relaxed))
+    }
+}
+
+impl AudioSource for AudioConsumer {
+    fn fill(&mut self, block: &mut [i16]) {
+        let ring = &self.ring;
+        let count = block.len().min(self.available());
+        let read = ring.read.load(Ordering::Relaxed);
+
+        for (i, sample) in block[..count].iter_mut().enumerate() {
+            *sample = unsafe { *ring.samples[read.wrapping_add(i) & ring.mask].get() };
+        }
+        ring.read.store(read.wrapping_add(count), Ordering::Release);
+
+        if count < block.len() {
+            ring.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_is_power_of_two() {
+        assert_eq!(ring_buffer(0).0.free(), 1);
+        assert_eq!(ring_buffer(5).0.free(), 8);
+        assert_eq!(ring_buffer(1024).0.free(), 1024);
+    }
+
+    #[test]
+    fn samples_keep_order() {
+        let (mut producer, mut consumer) = ring_buffer(4);
+
+        assert_eq!(producer.push(&[1, 2, 3]), 3);
+        let mut block = [0; 2];
+        consumer.fill(&mut block);
+        assert_eq!(block, [1, 2]);
+
+        // Only free part is written
+        assert_eq!(producer.push(&[4, 5, 6, 7]), 3);
+        assert_eq!(consumer.available(), 4);
+        let mut block = [0; 4];
+        consumer.fill(&mut block);
+        assert_eq!(block, [3, 4, 5, 6]);
+        assert_eq!(producer.underruns(), 0);
+    }
+
+    #[test]
+    fn underrun_leaves_silence() {
+        let (mut producer, mut consumer) = ring_buffer(4);
+        producer.push(&[9]);
+
+        let mut block = [0; 3];
+        consumer.fill(&mut block);
+        assert_eq!(block, [9, 0, 0]);
+        assert_eq!(producer.underruns(), 1);
+    }
+
+    #[test]
+    fn counters_wrap_around() {
+        // Capacity which isn't power of two would jump to other position when counters overflow
+        let (mut producer, mut consumer) = ring_buffer(3);
+        producer.ring.written.store(usize::MAX - 1, Ordering::Relaxed);
+        producer.ring.read.store(usize::MAX - 1, Ordering::Relaxed);
+
+        for chunk in [[1, 2, 3], [4, 5, 6], [7, 8, 9]] {
+            assert_eq!(producer.push(&chunk), 3);
+            let mut block = [0; 3];
+            consumer.fill(&mut block);
+            assert_eq!(block, chunk);
+        }
+        assert_eq!(producer.free(), 4);
+    }
+}
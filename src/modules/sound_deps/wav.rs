@@ -0,0 +1,503 @@
+use alloc::vec::Vec;
+#[cfg(target_os = "psp")]
+use alloc::string::String;
+#[cfg(target_os = "psp")]
+use crate::file_system;
+use crate::audio_output::{AudioSource, Channels, SAMPLE_RATE};
+
+/// Format codes from 'fmt ' chunk
+const FORMAT_PCM: u16 = 0x0001;
+const FORMAT_IMA_ADPCM: u16 = 0x0011;
+/// Real format is stored in sub-format field
+const FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// Step index changes of IMA-ADPCM for each 4-bit code (sign bit doesn't matter)
+const IMA_INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+/// Quantizer steps of IMA-ADPCM
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66, 73, 80, 88, 97, 107, 118, 130, 143,
+    157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449, 494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552,
+    1707, 1878, 2066, 2272, 2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493, 10442, 11487,
+    12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// Things which can go wrong while reading WAV file
+#[derive(Debug)]
+pub enum WavError {
+    /// File couldn't be read (contains error code returned by 'sceIo' function)
+    Io(i32),
+    /// File doesn't start with "RIFF" header of "WAVE" type
+    NotWave,
+    /// File ends inside header or chunk
+    Truncated,
+    /// Required chunk ("fmt " or "data") wasn't found
+    MissingChunk(&'static str),
+    /// Chunk which changes meaning of samples and can't be skipped (e.g. "wavl" list with silence parts), contains its id
+    UnsupportedChunk([u8; 4]),
+    /// Compression other then PCM and IMA-ADPCM, contains format code
+    UnsupportedFormat(u16),
+    /// Bits per sample not supported by format (PCM: 8 or 16, IMA-ADPCM: 4)
+    UnsupportedBits(u16),
+    /// Only mono and stereo files are supported
+    UnsupportedChannels(u16),
+    /// "fmt " chunk has got impossible values (contains name of value)
+    Invalid(&'static str),
+}
+
+/// Decoded sound: 16-bit samples (interleaved for stereo) with their sample rate
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sound {
+    pub sample_rate: u32,
+    pub channels: Channels,
+    pub samples: Vec<i16>,
+}
+
+impl Sound {
+    /// Amount of samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.count()
+    }
+
+    /// Length in seconds
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+
+    /// The same sound with other sample rate (linear interpolation) and channels (mono is copied to both sides, stereo is mixed down)
+    pub fn converted(&self, sample_rate: u32, channels: Channels) -> Sound {
+        let count = self.channels.count();
+        let frame = |index: usize| -> (i64, i64) {
+            let first = self.samples[index * count] as i64;
+            let second = self.samples[index * count + count - 1] as i64;
+            (first, second)
+        };
+
+        let frames = self.frames();
+        let converted_frames = if self.sample_rate == 0 { 0 } else { (frames as u64 * sample_rate as u64 / self.sample_rate as u64) as usize };
+        let mut samples = Vec::with_capacity(converted_frames * channels.count());
+
+        for i in 0..converted_frames {
+            // Position in source frames in 16.16 fixed point
+            let position = i as u64 * self.sample_rate as u64 * 65536 / sample_rate as u64;
+            let (index, fraction) = ((position >> 16) as usize, (position & 0xffff) as i64);
+            let (left, right) = frame(index);
+            let (next_left, next_right) = frame((index + 1).min(frames - 1));
+
+            let left = left + (((next_left - left) * fraction) >> 16);
+            let right = right + (((next_right - right) * fraction) >> 16);
+
+            match channels {
+                Channels::Mono => samples.push(((left + right) / 2) as i16),
+                Channels::Stereo => samples.extend_from_slice(&[left as i16, right as i16]),
+            }
+        }
+
+        Sound { sample_rate, channels, samples }
+    }
+}
+
+/// Plays decoded sound on 'AudioOutput' (sound is converted to output sample rate and channels when source is created)
+pub struct SoundSource {
+    samples: Vec<i16>,
+    position: usize,
+    pub looping: bool,
+}
+
+impl SoundSource {
+    pub fn new(sound: &Sound, channels: Channels, looping: bool) -> Self {
+        let samples = if sound.sample_rate == SAMPLE_RATE && sound.channels == channels {
+            sound.samples.clone()
+        } else {
+            sound.converted(SAMPLE_RATE, channels).samples
+        };
+
+        Self { samples, position: 0, looping }
+    }
+
+    /// Whole sound was played (never true for looping source)
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.samples.len()
+    }
+}
+
+impl AudioSource for SoundSource {
+    fn fill(&mut self, block: &mut [i16]) {
+        let mut filled = 0;
+
+        while filled < block.len() && !self.samples.is_empty() {
+            if self.is_finished() {
+                if !self.looping {
+                    break;
+                }
+                self.position = 0;
+            }
+
+            let count = (block.len() - filled).min(self.samples.len() - self.position);
+            block[filled..filled + count].copy_from_slice(&self.samples[self.position..self.position + count]);
+            filled += count;
+            self.position += count;
+        }
+    }
+}
+
+/// Content of "fmt " chunk
+struct Format {
+    code: u16,
+    channels: Channels,
+    sample_rate: u32,
+    block_align: usize,
+    bits: u16,
+    /// Only for IMA-ADPCM
+    samples_per_block: usize,
+}
+
+/// Directory where sound assets are stored
+#[cfg(target_os = "psp")]
+static ASSETS_DIR: &str = "./files/";
+
+/// Load WAV file from 'files/' directory. Example: 'load_wav("chime.wav")'
+#[cfg(target_os = "psp")]
+pub unsafe fn load_wav(name: &str) -> Result<Sound, WavError> {
+    let mut path = String::from(ASSETS_DIR);
+    path.push_str(name);
+
+    let bytes = file_system::read_file(&path).map_err(WavError::Io)?;
+    parse_wav(&bytes)
+}
+
+/// Decode content of WAV file (e.g. from 'include_bytes!'). Chunks which don't matter for samples ("LIST", "fact", "cue "...) are skipped.
+/// Data chunk which is longer then file (often written by programs which crashed during recording) is cut to file end
+pub fn parse_wav(bytes: &[u8]) -> Result<Sound, WavError> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(WavError::NotWave);
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut fact_frames = None;
+    let mut position = 12;
+
+    while position + 8 <= bytes.len() {
+        let id: [u8; 4] = [bytes[position], bytes[position + 1], bytes[position + 2], bytes[position + 3]];
+        let size = u32_at(bytes, position + 4) as usize;
+        let start = position + 8;
+        let available = bytes.len() - start;
+
+        match &id {
+            b"fmt " if size <= available => format = Some(parse_format(&bytes[start..start + size])?),
+            b"fact" if size >= 4 && size <= available => fact_frames = Some(u32_at(bytes, start) as usize),
+            b"data" => data = Some(&bytes[start..start + size.min(available)]),
+            b"wavl" | b"slnt" => return Err(WavError::UnsupportedChunk(id)),
+            b"fmt " | b"fact" => return Err(WavError::Truncated),
+            _ => {}
+        }
+
+        // Chunks are aligned to 2 bytes
+        position = start.saturating_add(size).saturating_add(size & 1);
+    }
+
+    let format = format.ok_or(WavError::MissingChunk("fmt "))?;
+    let data = data.ok_or(WavError::MissingChunk("data"))?;
+    let count = format.channels.count();
+
+    let mut samples = match (format.code, format.bits) {
+        (FORMAT_PCM, 8) => data.iter().map(|sample| (*sample as i16 - 128) << 8).collect(),
+        (FORMAT_PCM, 16) => data.chunks_exact(2).map(|sample| i16::from_le_bytes([sample[0], sample[1]])).collect(),
+        (FORMAT_IMA_ADPCM, _) => decode_ima_adpcm(data, &format),
+        _ => Vec::new(),
+    };
+
+    // Incomplete frame at the end is dropped, "fact" tells real length of compressed sound (last block is padded)
+    let mut frames = samples.len() / count;
+    if let (FORMAT_IMA_ADPCM, Some(fact_frames)) = (format.code, fact_frames) {
+        frames = frames.min(fact_frames);
+    }
+    samples.truncate(frames * count);
+
+    Ok(Sound { sample_rate: format.sample_rate, channels: format.channels, samples })
+}
+
+fn parse_format(chunk: &[u8]) -> Result<Format, WavError> {
+    if chunk.len() < 16 {
+        return Err(WavError::Truncated);
+    }
+
+    let mut code = u16_at(chunk, 0);
+    let channels = match u16_at(chunk, 2) {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        other => return Err(WavError::UnsupportedChannels(other)),
+    };
+    let sample_rate = u32_at(chunk, 4);
+    let block_align = u16_at(chunk, 12) as usize;
+    let bits = u16_at(chunk, 14);
+
+    // Extensible format keeps real format code in the first 2 bytes of sub-format GUID
+    if code == FORMAT_EXTENSIBLE {
+        if chunk.len() < 26 {
+            return Err(WavError::Truncated);
+        }
+        code = u16_at(chunk, 24);
+    }
+
+    if sample_rate == 0 {
+        return Err(WavError::Invalid("sample rate"));
+    }
+
+    let mut samples_per_block = 0;
+    match code {
+        FORMAT_PCM if bits == 8 || bits == 16 => {},
+        FORMAT_IMA_ADPCM if bits == 4 => {
+            // Each channel has got 4 bytes of header in block, the rest are 4-bit samples (plus one sample kept in header)
+            let header = 4 * channels.count();
+            if block_align <= header {
+                return Err(WavError::Invalid("block align"));
+            }
+            samples_per_block = (block_align - header) * 2 / channels.count() + 1;
+        },
+        FORMAT_PCM | FORMAT_IMA_ADPCM => return Err(WavError::UnsupportedBits(bits)),
+        other => return Err(WavError::UnsupportedFormat(other)),
+    }
+
+    Ok(Format { code, channels, sample_rate, block_align, bits, samples_per_block })
+}
+
+/// Decode IMA-ADPCM blocks. Block starts with header for each channel (first sample i16, step index u8, reserved u8),
+/// next 4-bit codes follow (low nibble first), for stereo in groups of 4 bytes (8 samples) alternating between channels
+fn decode_ima_adpcm(data: &[u8], format: &Format) -> Vec<i16> {
+    let count = format.channels.count();
+    let mut samples = Vec::with_capacity(data.len() / format.block_align * format.samples_per_block * count);
+
+    for block in data.chunks_exact(format.block_align) {
+        let mut predictors = [0i32; 2];
+        let mut indexes = [0i32; 2];
+        let mut decoded = alloc::vec![0i16; format.samples_per_block * count];
+
+        for channel in 0..count {
+            predictors[channel] = i16::from_le_bytes([block[channel * 4], block[channel * 4 + 1]]) as i32;
+            indexes[channel] = (block[channel * 4 + 2] as i32).clamp(0, 88);
+            decoded[channel] = predictors[channel] as i16;
+        }
+
+        let codes = &block[4 * count..];
+        for (group, bytes) in codes.chunks_exact(4 * count).enumerate() {
+            for channel in 0..count {
+                for (i, byte) in bytes[channel * 4..channel * 4 + 4].iter().enumerate() {
+                    for (half, code) in [byte & 0x0f, byte >> 4].into_iter().enumerate() {
+                        let frame = 1 + group * 8 + i * 2 + half;
+                        let sample = ima_step(&mut predictors[channel], &mut indexes[channel], code);
+                        decoded[frame * count + channel] = sample;
+                    }
+                }
+            }
+        }
+
+        samples.extend_from_slice(&decoded);
+    }
+
+    samples
+}
+
+/// Decode one 4-bit code and update predictor state
+fn ima_step(predictor: &mut i32, index: &mut i32, code: u8) -> i16 {
+    let step = IMA_STEP_TABLE[*index as usize];
+
+    let mut difference = step >> 3;
+    if code & 1 != 0 {
+        difference += step >> 2;
+    }
+    if code & 2 != 0 {
+        difference += step >> 1;
+    }
+    if code & 4 != 0 {
+        difference += step;
+    }
+    if code & 8 != 0 {
+        difference = -difference;
+    }
+
+    *predictor = (*predictor + difference).clamp(i16::MIN as i32, i16::MAX as i32);
+    *index = (*index + IMA_INDEX_TABLE[code as usize]).clamp(0, 88);
+
+    *predictor as i16
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// WAV file made of specified chunks (odd chunks get padding byte)
+    fn riff(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut body = b"WAVE".to_vec();
+        for (id, data) in chunks {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    /// Content of 16 bytes long "fmt " chunk
+    fn fmt(code: u16, channels: u16, sample_rate: u32, block_align: u16, bits: u16) -> Vec<u8> {
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&code.to_le_bytes());
+        chunk.extend_from_slice(&channels.to_le_bytes());
+        chunk.extend_from_slice(&sample_rate.to_le_bytes());
+        chunk.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        chunk.extend_from_slice(&block_align.to_le_bytes());
+        chunk.extend_from_slice(&bits.to_le_bytes());
+        chunk
+    }
+
+    fn pcm16(samples: &[i16]) -> Vec<u8> {
+        samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+    }
+
+    /// One mono IMA-ADPCM block (9 samples): header with predictor 100 and step index 0, then codes 0, 7, 8, 0, 0, 0, 0, 0
+    const ADPCM_MONO: [u8; 8] = [100, 0, 0, 0, 0x70, 0x08, 0x00, 0x00];
+    /// Samples decoded from 'ADPCM_MONO' (low nibble of each byte is decoded first)
+    const ADPCM_MONO_SAMPLES: [i16; 9] = [100, 100, 111, 109, 110, 111, 112, 113, 114];
+
+    #[test]
+    fn pcm_16_bit() {
+        let bytes = riff(&[(b"fmt ", &fmt(FORMAT_PCM, 2, 22050, 4, 16)), (b"LIST", b"INFOabc"), (b"data", &pcm16(&[1, -2, 300, -400]))]);
+        let sound = parse_wav(&bytes).unwrap();
+
+        assert_eq!(sound, Sound { sample_rate: 22050, channels: Channels::Stereo, samples: alloc::vec![1, -2, 300, -400] });
+        assert_eq!(sound.frames(), 2);
+
+        // Incomplete frame at the end is dropped
+        let bytes = riff(&[(b"fmt ", &fmt(FORMAT_PCM, 2, 22050, 4, 16)), (b"data", &pcm16(&[1, -2, 300]))]);
+        assert_eq!(parse_wav(&bytes).unwrap().samples, [1, -2]);
+    }
+
+    #[test]
+    fn pcm_8_bit() {
+        let bytes = riff(&[(b"fmt ", &fmt(FORMAT_PCM, 1, 8000, 1, 8)), (b"data", &[0, 128, 255])]);
+        let sound = parse_wav(&bytes).unwrap();
+
+        assert_eq!(sound.channels, Channels::Mono);
+        assert_eq!(sound.samples, [-32768, 0, 32512]);
+    }
+
+    #[test]
+    fn extensible_format() {
+        let mut chunk = fmt(FORMAT_EXTENSIBLE, 1, 44100, 2, 16);
+        // Size of extension, valid bits, channel mask and sub-format GUID (only its first 2 bytes matter)
+        chunk.extend_from_slice(&[22, 0, 16, 0, 4, 0, 0, 0, 0x01, 0x00]);
+        chunk.extend_from_slice(&[0; 14]);
+
+        let bytes = riff(&[(b"fmt ", &chunk), (b"data", &pcm16(&[5, 6]))]);
+        assert_eq!(parse_wav(&bytes).unwrap().samples, [5, 6]);
+    }
+
+    #[test]
+    fn ima_adpcm_mono() {
+        let bytes = riff(&[(b"fmt ", &fmt(FORMAT_IMA_ADPCM, 1, 22050, 8, 4)), (b"data", &ADPCM_MONO)]);
+        assert_eq!(parse_wav(&bytes).unwrap().samples, ADPCM_MONO_SAMPLES);
+    }
+
+    #[test]
+    fn ima_adpcm_stereo() {
+        // Headers of both channels, then 4 bytes of left codes followed by 4 bytes of right codes
+        let mut block = alloc::vec![100, 0, 0, 0, 0xce, 0xff, 0, 0];
+        block.extend_from_slice(&ADPCM_MONO[4..]);
+        block.extend_from_slice(&[0; 4]);
+
+        let bytes = riff(&[(b"fmt ", &fmt(FORMAT_IMA_ADPCM, 2, 22050, 16, 4)), (b"data", &block)]);
+        let sound = parse_wav(&bytes).unwrap();
+
+        assert_eq!(sound.frames(), 9);
+        for (frame, left) in ADPCM_MONO_SAMPLES.iter().enumerate() {
+            assert_eq!(sound.samples[frame * 2], *left);
+            // Code 0 with the smallest step doesn't change predictor
+            assert_eq!(sound.samples[frame * 2 + 1], -50);
+        }
+    }
+
+    #[test]
+    fn fact_cuts_padded_block() {
+        let format = fmt(FORMAT_IMA_ADPCM, 1, 22050, 8, 4);
+
+        let bytes = riff(&[(b"fmt ", &format), (b"fact", &5u32.to_le_bytes()), (b"data", &ADPCM_MONO)]);
+        assert_eq!(parse_wav(&bytes).unwrap().samples, ADPCM_MONO_SAMPLES[..5]);
+
+        // Longer length than decoded samples doesn't add anything
+        let bytes = riff(&[(b"fmt ", &format), (b"fact", &100u32.to_le_bytes()), (b"data", &ADPCM_MONO)]);
+        assert_eq!(parse_wav(&bytes).unwrap().samples, ADPCM_MONO_SAMPLES);
+
+        // PCM length is given by data chunk only
+        let bytes = riff(&[(b"fmt ", &fmt(FORMAT_PCM, 1, 22050, 2, 16)), (b"fact", &1u32.to_le_bytes()), (b"data", &pcm16(&[1, 2, 3]))]);
+        assert_eq!(parse_wav(&bytes).unwrap().samples, [1, 2, 3]);
+    }
+
+    #[test]
+    fn data_longer_then_file_is_cut() {
+        let mut bytes = riff(&[(b"fmt ", &fmt(FORMAT_PCM, 1, 22050, 2, 16))]);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&1000u32.to_le_bytes());
+        bytes.extend_from_slice(&pcm16(&[7, 8]));
+
+        assert_eq!(parse_wav(&bytes).unwrap().samples, [7, 8]);
+    }
+
+    #[test]
+    fn chunks_changing_samples_are_rejected() {
+        let format = fmt(FORMAT_PCM, 1, 22050, 2, 16);
+
+        let bytes = riff(&[(b"fmt ", &format), (b"wavl", &[])]);
+        assert!(matches!(parse_wav(&bytes), Err(WavError::UnsupportedChunk(id)) if &id == b"wavl"));
+        let bytes = riff(&[(b"fmt ", &format), (b"slnt", &[0; 4]), (b"data", &[])]);
+        assert!(matches!(parse_wav(&bytes), Err(WavError::UnsupportedChunk(id)) if &id == b"slnt"));
+    }
+
+    #[test]
+    fn invalid_files() {
+        let format = fmt(FORMAT_PCM, 1, 22050, 2, 16);
+
+        assert!(matches!(parse_wav(b"RIFF\0\0\0\0AVI "), Err(WavError::NotWave)));
+        assert!(matches!(parse_wav(&riff(&[(b"data", &[])])), Err(WavError::MissingChunk("fmt "))));
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &format)])), Err(WavError::MissingChunk("data"))));
+
+        // "fmt " which is too short or ends after end of file
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &format[..14]), (b"data", &[])])), Err(WavError::Truncated)));
+        let bytes = riff(&[(b"fmt ", &format)]);
+        assert!(matches!(parse_wav(&bytes[..bytes.len() - 2]), Err(WavError::Truncated)));
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &fmt(FORMAT_EXTENSIBLE, 1, 22050, 2, 16)), (b"data", &[])])), Err(WavError::Truncated)));
+
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &fmt(3, 1, 22050, 4, 32)), (b"data", &[])])), Err(WavError::UnsupportedFormat(3))));
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &fmt(FORMAT_PCM, 1, 22050, 3, 24)), (b"data", &[])])), Err(WavError::UnsupportedBits(24))));
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &fmt(FORMAT_PCM, 3, 22050, 6, 16)), (b"data", &[])])), Err(WavError::UnsupportedChannels(3))));
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &fmt(FORMAT_PCM, 1, 0, 2, 16)), (b"data", &[])])), Err(WavError::Invalid("sample rate"))));
+        assert!(matches!(parse_wav(&riff(&[(b"fmt ", &fmt(FORMAT_IMA_ADPCM, 1, 22050, 4, 4)), (b"data", &[])])), Err(WavError::Invalid("block align"))));
+    }
+
+    #[test]
+    fn resampling() {
+        let sound = Sound { sample_rate: 22050, channels: Channels::Mono, samples: alloc::vec![0, 1000, 2000] };
+
+        // New frames are interpolated, last frame is repeated
+        assert_eq!(sound.converted(44100, Channels::Mono).samples, [0, 500, 1000, 1500, 2000, 2000]);
+        assert_eq!(sound.converted(11025, Channels::Mono).samples, [0]);
+        assert_eq!(sound.converted(22050, Channels::Stereo).samples, [0, 0, 1000, 1000, 2000, 2000]);
+
+        // Stereo is mixed down
+        let sound = Sound { sample_rate: 44100, channels: Channels::Stereo, samples: alloc::vec![100, 300, -100, -300] };
+        assert_eq!(sound.converted(44100, Channels::Mono).samples, [200, -200]);
+    }
+}
@@ -1,14 +1,58 @@
-use psp::{sys::{AUDIO_NEXT_CHANNEL, AUDIO_SAMPLE_MAX, AudioFormat}, dprintln};
+use psp::{sys, math::sinf, dprintln};
+use crate::audio_output::{AudioOutput, AudioConfig, Channels, SAMPLE_RATE};
+use crate::wav::{self, SoundSource};
 
+/// Play 2 seconds of 440 Hz tone (A4) with fade out on stereo channel
 pub unsafe fn play_sound() {
-    let channel = psp::sys::sceAudioChReserve(AUDIO_NEXT_CHANNEL, AUDIO_SAMPLE_MAX as i32, AudioFormat::Stereo);
+    const FREQUENCY: f32 = 440.0;
+    const DURATION: u32 = 2 * SAMPLE_RATE;
 
-    /* TODO: .... */
-    // let _ = psp::sys::sceAudioOutput(channel, 100, );
+    let step = 2.0 * core::f32::consts::PI * FREQUENCY / SAMPLE_RATE as f32;
+    let mut phase = 0.0f32;
+    let mut played = 0u32;
 
-    let released = psp::sys::sceAudioChRelease(channel);
-    match released {
-        0 => (),
-        _ => dprintln!("Couldn't play audio")
+    // Called by output thread for every block, samples are interleaved (left, right)
+    let tone = move |block: &mut [i16]| {
+        for frame in block.chunks_exact_mut(2) {
+            let fade = 1.0 - (played as f32 / DURATION as f32).min(1.0);
+            let sample = (sinf(phase) * fade * 8000.0) as i16;
+            frame.fill(sample);
+
+            phase += step;
+            if phase > 2.0 * core::f32::consts::PI {
+                phase -= 2.0 * core::f32::consts::PI;
+            }
+            played += 1;
+        }
+    };
+
+    match AudioOutput::start(AudioConfig::new(1024), tone) {
+        Ok(output) => {
+            sys::sceKernelDelayThread(DURATION / SAMPLE_RATE * 1_000_000);
+            output.stop();
+        },
+        Err(error) => dprintln!("Couldn't play audio ({:?})", error),
+    }
+}
+
+/// Play chime decoded from IMA-ADPCM WAV file ('files/chime.wav') embedded into program
+pub unsafe fn play_wav() {
+    let sound = match wav::parse_wav(include_bytes!("../../files/chime.wav")) {
+        Ok(sound) => sound,
+        Err(error) => {
+            dprintln!("Couldn't decode sound ({:?})", error);
+            return;
+        }
+    };
+    dprintln!("Sound: {} Hz, {:?}, {:.2} s", sound.sample_rate, sound.channels, sound.duration());
+
+    // Sound is resampled to output rate and copied to both channels
+    let source = SoundSource::new(&sound, Channels::Stereo, false);
+    match AudioOutput::start(AudioConfig::new(1024), source) {
+        Ok(output) => {
+            sys::sceKernelDelayThread((sound.duration() * 1_000_000.0) as u32 + 100_000);
+            output.stop();
+        },
+        Err(error) => dprintln!("Couldn't play audio ({:?})", error),
     }
 }
@@ -1,15 +1,52 @@
-use psp::{sys::{ sceRtcGetCurrentClockLocalTime, ScePspDateTime, sceRtcGetTick, sceRtcFormatRFC3339, sceRtcConvertLocalTimeToUTC, sceRtcGetDayOfWeek }, dprintln};
+use psp::{sys::{ sceRtcGetCurrentClockLocalTime, ScePspDateTime, sceRtcGetTick, sceRtcFormatRFC3339, sceRtcConvertLocalTimeToUTC, sceRtcGetDayOfWeek, sceRtcGetCurrentTick, sceRtcGetTickResolution }, dprintln};
 
 pub unsafe fn base() {
-    // Where actual date time is stored
-    let record = &mut ScePspDateTime::default();
-
-    // Assign time
-    sceRtcGetCurrentClockLocalTime(record);
-    
-    let ScePspDateTime { year, month, day, hour, minutes, seconds, .. } = record;
+    // Actual date time
+    let ScePspDateTime { year, month, day, hour, minutes, seconds, .. } = self::local_time();
 
     // Obtain day of week
-    let day_week = sceRtcGetDayOfWeek(*year as i32, *month as i32, *day as i32);
+    let day_week = sceRtcGetDayOfWeek(year as i32, month as i32, day as i32);
     dprintln!("Actual date is: {day:}.{month:}.{year:} {hour:}:{minutes:}.{seconds:} {day_week}/7");
 }
+
+/// Current date and time in local time zone readed from RTC clock
+pub unsafe fn local_time() -> ScePspDateTime {
+    let mut record = ScePspDateTime::default();
+    sceRtcGetCurrentClockLocalTime(&mut record);
+
+    record
+}
+
+/// Current RTC tick (amount of ticks per second is returned by 'sceRtcGetTickResolution')
+pub unsafe fn current_tick() -> u64 {
+    let mut tick = 0u64;
+    sceRtcGetCurrentTick(&mut tick);
+
+    tick
+}
+
+/// Measures time which elapsed between frames, so movement can be independent from frame rate
+pub struct DeltaTimer {
+    last_tick: u64,
+    /// Ticks per second
+    resolution: f32,
+}
+
+impl DeltaTimer {
+    /// Start measuring from current moment
+    pub unsafe fn new() -> Self {
+        Self {
+            last_tick: current_tick(),
+            resolution: sceRtcGetTickResolution() as f32,
+        }
+    }
+
+    /// Seconds which elapsed since previous call (or since timer creation)
+    pub unsafe fn delta(&mut self) -> f32 {
+        let now = current_tick();
+        let elapsed = now.saturating_sub(self.last_tick);
+        self.last_tick = now;
+
+        elapsed as f32 / self.resolution
+    }
+}
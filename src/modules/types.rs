@@ -14,6 +14,21 @@ pub struct Vertex {
     pub z: f32
 }
 
+/// Same as 'Vertex' but with normal vector which is required by hardware lighting (order of fields is forced by GE: texture, color, normal, position)
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NormalVertex {
+    pub u: f32,
+    pub v: f32,
+    pub color: u32,
+    pub nx: f32,
+    pub ny: f32,
+    pub nz: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
 #[repr(C)]
 pub struct Texture {
     pub bytes: *const c_void,
@@ -1,27 +1,228 @@
 use psp::{ sys::{ self, SceCtrlData, CtrlButtons }, dprintln };
+use crate::input_manager::{InputManager, InputSample, PspSampler};
+use crate::input_replay::{InputRecording, RecordingSampler, ReplaySampler};
+use crate::analog::{AnalogStick, Direction8};
+use crate::action_map::{ActionMap, Binding};
+use crate::combo::{ComboRecognizer, Combo};
+use crate::input_events::{EventSource, EventDispatcher, InputEvent};
 
-/// Listens for inputs into TUI
+/// Profile with player's controls (saved next to program)
+static CONTROLS_PROFILE: &str = "./controls.json";
+
+/// Items of menu screen in 'InputsListener'
+const MENU_ITEMS: [&str; 3] = ["Play", "Swap CROSS and CIRCLE", "Quit"];
+
+/// State changed by event handlers of 'InputsListener'
+struct ListenerState {
+    actions: ActionMap,
+    menu_cursor: usize,
+    /// The last printed stick direction
+    stick_direction: Option<Direction8>,
+    /// Screen requested by handler, switched after dispatch
+    next_screen: Option<&'static str>,
+    quit: bool,
+}
+
+/// Listens for inputs into TUI without blocking, so 'update' can be called from the same loop as rendering. Events are handled by screen:
+/// "menu" is navigated with UP / DOWN (held d-pad repeats) and "confirm" action, "game" prints presses, releases, stick directions and combos (Konami code, fireball)
+/// and "cancel" returns to menu. Holding CIRCLE and CROSS together is recognized on every screen, START quits. Controls are mapped to actions kept in profile file
+pub struct InputsListener {
+    events: EventSource<PspSampler>,
+    dispatcher: EventDispatcher<ListenerState>,
+    combos: ComboRecognizer,
+    state: ListenerState,
+}
+
+impl InputsListener {
+    pub unsafe fn new() -> Self {
+        let mut actions = ActionMap::new();
+        // Default bindings use only named buttons, so they are always valid
+        let _ = actions.define("confirm", &[Binding::Buttons(CtrlButtons::CROSS)]);
+        let _ = actions.define("cancel", &[Binding::Buttons(CtrlButtons::CIRCLE)]);
+        let _ = actions.define("both", &[Binding::Buttons(CtrlButtons::CIRCLE | CtrlButtons::CROSS)]);
+
+        // Saved profile replaces default bindings
+        match actions.load(CONTROLS_PROFILE) {
+            Ok(()) => dprintln!("Controls loaded from {}", CONTROLS_PROFILE),
+            Err(error) => dprintln!("Default controls used ({:?})", error),
+        }
+        for (first, second, binding) in actions.conflicts() {
+            dprintln!("'{}' and '{}' both use {}", first, second, binding.name());
+        }
+
+        let mut events = EventSource::new(PspSampler::non_blocking()).with_stick(AnalogStick::new().with_deadzones(0.25, 0.1));
+        // Combination bindings are reported only when they are watched
+        for binding in actions.bindings("both") {
+            if let Binding::Buttons(buttons) = binding {
+                events.watch_combination(*buttons);
+            }
+        }
+
+        let mut combos = ComboRecognizer::new();
+        combos.add(Combo::konami_code("Konami code"));
+        combos.add(Combo::quarter_circle_forward("Fireball", CtrlButtons::SQUARE));
+
+        let mut dispatcher = EventDispatcher::new();
+
+        dispatcher.on("menu", |event, state: &mut ListenerState| {
+            if event.is_press_of(CtrlButtons::UP | CtrlButtons::DOWN) {
+                let step = if event.is_press_of(CtrlButtons::UP) { MENU_ITEMS.len() - 1 } else { 1 };
+                state.menu_cursor = (state.menu_cursor + step) % MENU_ITEMS.len();
+                dprintln!("> {}", MENU_ITEMS[state.menu_cursor]);
+                return true;
+            }
+
+            if !state.actions.triggered_by("confirm", event) {
+                return false;
+            }
+            match state.menu_cursor {
+                0 => state.next_screen = Some("game"),
+                1 => {
+                    state.actions.swap_buttons(CtrlButtons::CROSS, CtrlButtons::CIRCLE);
+                    match state.actions.save(CONTROLS_PROFILE) {
+                        Ok(()) => dprintln!("CROSS and CIRCLE swapped, controls saved"),
+                        Err(error) => dprintln!("Controls couldn't be saved ({:?})", error),
+                    }
+                },
+                _ => state.quit = true,
+            }
+            true
+        });
+
+        dispatcher.on("game", |event, state: &mut ListenerState| {
+            if state.actions.triggered_by("cancel", event) {
+                state.next_screen = Some("menu");
+                return true;
+            }
+
+            match event {
+                InputEvent::ButtonDown(button) => dprintln!("{:?} was clicked", button),
+                InputEvent::ButtonUp { button, held } => dprintln!("{:?} was released after {:.2} s", button, held),
+                // Stick is printed only when its 8-way direction changes
+                InputEvent::AnalogMoved { direction, .. } if *direction != state.stick_direction => {
+                    dprintln!("Stick direction: {:?}", direction);
+                    state.stick_direction = *direction;
+                },
+                _ => return false,
+            }
+            true
+        });
+
+        dispatcher.on_any(|event, state: &mut ListenerState| {
+            if state.actions.triggered_by("both", event) {
+                dprintln!("Circle and Cross were clicked together");
+                true
+            } else if event.is_press_of(CtrlButtons::START) {
+                state.quit = true;
+                true
+            } else {
+                false
+            }
+        });
+
+        dispatcher.set_screen("menu");
+        dprintln!("> {}", MENU_ITEMS[0]);
+
+        Self { events, dispatcher, combos, state: ListenerState { actions, menu_cursor: 0, stick_direction: None, next_screen: None, quit: false } }
+    }
+
+    /// Handle inputs of one frame. Returns false when player asked to quit
+    pub unsafe fn update(&mut self) -> bool {
+        self.events.update();
+
+        if self.dispatcher.screen() == Some("game") {
+            for event in self.combos.update(self.events.held_with_stick()) {
+                dprintln!("{} entered!", event.name);
+            }
+        }
+
+        self.dispatcher.dispatch_all(&mut self.events, &mut self.state);
+
+        if let Some(screen) = self.state.next_screen.take() {
+            dprintln!("Screen: {}", screen);
+            self.dispatcher.set_screen(screen);
+            self.combos.reset();
+        }
+
+        !self.state.quit
+    }
+}
+
+/// Run 'InputsListener' until player quits (loop is paced by vertical blank like render loop would be)
 pub unsafe fn inputs_listener() {
-    // Setup listening for inputs buttons
-    sys::sceCtrlSetSamplingCycle(0);
-    sys::sceCtrlSetSamplingMode(sys::CtrlMode::Analog);
+    let mut listener = InputsListener::new();
+    while listener.update() {
+        sys::sceDisplayWaitVblankStart();
+    }
+}
 
-    // Storage for recived inputs datas
-    let ctrl_datas = &mut SceCtrlData::default();
+/// File with inputs recorded by 'record_and_replay'
+static INPUTS_RECORDING: &str = "./inputs.rec";
 
-    // Start listening continuosly for inputs
+/// Record controller until START is pressed, save recording to file, load it back and replay it. Replayed frames print the same messages as live ones
+pub unsafe fn record_and_replay() {
+    // Buttons pressed in frame are printed by name
+    let print_presses = |frame: u32, sample: &InputSample, previous: &InputSample| {
+        for (name, button) in [("CROSS", CtrlButtons::CROSS), ("CIRCLE", CtrlButtons::CIRCLE), ("SQUARE", CtrlButtons::SQUARE), ("TRIANGLE", CtrlButtons::TRIANGLE)] {
+            if sample.buttons.contains(button) && !previous.buttons.contains(button) {
+                dprintln!("Frame {}: {} pressed", frame, name);
+            }
+        }
+    };
+
+    dprintln!("Recording, press START to stop");
+    let mut inputs = InputManager::new(RecordingSampler::new(PspSampler::new()));
+    let mut frame = 0;
     loop {
-        // Assign potentialy recived datas to variable
-        sys::sceCtrlReadBufferPositive(ctrl_datas, 1);
+        inputs.update();
+        if inputs.just_pressed(CtrlButtons::START) {
+            break;
+        }
 
-        // Destructurize to simpre read
-        let SceCtrlData { buttons, .. } = ctrl_datas;
+        print_presses(frame, inputs.sample(), inputs.previous_sample());
+        frame += 1;
+    }
+
+    let recording = inputs.into_sampler().finish();
+    if let Err(error) = recording.save(INPUTS_RECORDING) {
+        dprintln!("Recording couldn't be saved ({:?})", error);
+        return;
+    }
 
-        // Perform action sutable for specific clicked button
-        match *buttons {
-            CtrlButtons::CIRCLE => dprintln!("Circle was clicked"),
-            CtrlButtons::CROSS => dprintln!("Cross was clicked"),
-            _ => ()
+    // Replay goes through file, so checksum and layout are checked as well
+    let recording = match InputRecording::load(INPUTS_RECORDING) {
+        Ok(recording) => recording,
+        Err(error) => {
+            dprintln!("Recording couldn't be loaded ({:?})", error);
+            return;
         }
+    };
+    dprintln!("Replaying {} frames", recording.len());
+
+    let mut inputs = InputManager::new(ReplaySampler::new(recording));
+    let mut frame = 0;
+    while !inputs.sampler_mut().is_finished() {
+        inputs.update();
+        print_presses(frame, inputs.sample(), inputs.previous_sample());
+        frame += 1;
+
+        // Replay runs with the same speed as recording
+        sys::sceDisplayWaitVblankStart();
     }
+    dprintln!("Replay finished");
+}
+
+/// Setup controller to sample analog stick (must be called once before 'read_controller')
+pub unsafe fn setup_analog() {
+    sys::sceCtrlSetSamplingCycle(0);
+    sys::sceCtrlSetSamplingMode(sys::CtrlMode::Analog);
+}
+
+/// Read current controller state without blocking. Returns pressed buttons and analog stick position normalized to -1.0..=1.0 range (x, y)
+pub unsafe fn read_controller() -> (CtrlButtons, (f32, f32)) {
+    let ctrl_datas = &mut SceCtrlData::default();
+    sys::sceCtrlPeekBufferPositive(ctrl_datas, 1);
+
+    // Raw 0..=255 values are cleaned by default stick processing (20% radial deadzone)
+    (ctrl_datas.buttons, AnalogStick::default().process((ctrl_datas.lx, ctrl_datas.ly)))
 }